@@ -0,0 +1,77 @@
+-- Add migration script here
+
+-- Merge rows that share a case-insensitive address into one: confirmed subscribers win, then
+-- the oldest signup. The kept row takes over the others' tokens and pending deliveries.
+CREATE TEMPORARY TABLE duplicate_subscriptions AS
+SELECT id, email, keeper_id, keeper_email
+FROM (
+    SELECT
+        id,
+        email,
+        first_value(id) OVER duplicates AS keeper_id,
+        first_value(email) OVER duplicates AS keeper_email,
+        row_number() OVER duplicates AS position
+    FROM subscriptions
+    WINDOW duplicates AS (
+        PARTITION BY lower(btrim(email))
+        ORDER BY status = 'confirmed' DESC, subscribed_at ASC, id ASC
+    )
+) ranked
+WHERE position > 1;
+
+-- The earliest signup of the group is kept, even if it is not the row that wins.
+UPDATE subscriptions s
+SET subscribed_at = merged.subscribed_at
+FROM (
+    SELECT d.keeper_id, min(o.subscribed_at) AS subscribed_at
+    FROM duplicate_subscriptions d
+    JOIN subscriptions o ON o.id = d.id
+    GROUP BY d.keeper_id
+) merged
+WHERE s.id = merged.keeper_id AND merged.subscribed_at < s.subscribed_at;
+
+UPDATE subscription_tokens t
+SET subscriber_id = d.keeper_id
+FROM duplicate_subscriptions d
+WHERE t.subscriber_id = d.id;
+
+-- A delivery already queued for the kept address covers the duplicate's one.
+DELETE FROM issue_delivery_queue q
+USING duplicate_subscriptions d
+WHERE q.subscriber_email = d.email
+    AND EXISTS (
+        SELECT 1 FROM issue_delivery_queue k
+        WHERE k.newsletter_issue_id = q.newsletter_issue_id
+            AND k.subscriber_email = d.keeper_email
+    );
+
+DELETE FROM issue_delivery_queue q
+USING duplicate_subscriptions d
+WHERE q.subscriber_email = d.email
+    AND q.ctid <> (
+        SELECT min(o.ctid) FROM issue_delivery_queue o
+        JOIN duplicate_subscriptions od ON o.subscriber_email = od.email
+        WHERE o.newsletter_issue_id = q.newsletter_issue_id AND od.keeper_id = d.keeper_id
+    );
+
+UPDATE issue_delivery_queue q
+SET subscriber_email = d.keeper_email
+FROM duplicate_subscriptions d
+WHERE q.subscriber_email = d.email;
+
+DELETE FROM subscriptions
+WHERE id IN (SELECT id FROM duplicate_subscriptions);
+
+DROP TABLE duplicate_subscriptions;
+
+-- Bring the remaining rows in line with `SubscriberEmail::parse`.
+UPDATE subscriptions
+SET email = btrim(email)
+WHERE email <> btrim(email);
+
+UPDATE subscriptions
+SET email = substring(email FROM '^(.*)@') || '@' || lower(substring(email FROM '@([^@]*)$'))
+WHERE substring(email FROM '@([^@]*)$') <> lower(substring(email FROM '@([^@]*)$'));
+
+ALTER TABLE subscriptions DROP CONSTRAINT subscriptions_email_key;
+CREATE UNIQUE INDEX subscriptions_email_lower_key ON subscriptions (lower(email));
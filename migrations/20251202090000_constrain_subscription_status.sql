@@ -0,0 +1,4 @@
+-- Add migration script here
+ALTER TABLE subscriptions
+    ADD CONSTRAINT subscriptions_status_check
+    CHECK (status IN ('pending_confirmation', 'confirmed', 'unsubscribed', 'bounced', 'complained'));
@@ -0,0 +1,24 @@
+-- Add migration script here
+CREATE TABLE issue_delivery_log (
+    newsletter_issue_id uuid NOT NULL REFERENCES newsletter_issues (newsletter_issue_id),
+    subscriber_email TEXT NOT NULL,
+    outcome TEXT NOT NULL CHECK (outcome IN ('sent', 'failed', 'skipped')),
+    attempted_at timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY (newsletter_issue_id, subscriber_email)
+);
+
+CREATE TABLE data_request_tokens (
+    data_request_token TEXT NOT NULL,
+    subscriber_id uuid NOT NULL REFERENCES subscriptions (id),
+    kind TEXT NOT NULL CHECK (kind IN ('export', 'erase')),
+    created_at timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY (data_request_token)
+);
+
+-- Keeps a record that an erasure happened without keeping anything about who was erased.
+CREATE TABLE data_erasure_log (
+    erasure_id uuid NOT NULL,
+    requested_by TEXT NOT NULL CHECK (requested_by IN ('subscriber', 'admin')),
+    erased_at timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY (erasure_id)
+);
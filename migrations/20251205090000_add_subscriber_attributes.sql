@@ -0,0 +1,14 @@
+-- Add migration script here
+CREATE TABLE subscriber_attribute_definitions (
+    key TEXT NOT NULL,
+    label TEXT NOT NULL,
+    kind TEXT NOT NULL CHECK (kind IN ('text', 'number', 'choice')),
+    required BOOLEAN NOT NULL DEFAULT false,
+    max_length INTEGER,
+    choices TEXT[] NOT NULL DEFAULT '{}',
+    created_at timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY (key)
+);
+
+ALTER TABLE subscriptions ADD COLUMN attributes JSONB NOT NULL DEFAULT '{}';
+CREATE INDEX subscriptions_attributes_idx ON subscriptions USING GIN (attributes);
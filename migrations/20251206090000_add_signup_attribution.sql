@@ -0,0 +1,17 @@
+-- Add migration script here
+ALTER TABLE subscriptions
+    ADD COLUMN signup_source TEXT,
+    ADD COLUMN referrer TEXT,
+    ADD COLUMN utm_source TEXT,
+    ADD COLUMN utm_medium TEXT,
+    ADD COLUMN utm_campaign TEXT,
+    ADD COLUMN utm_term TEXT,
+    ADD COLUMN utm_content TEXT,
+    ADD COLUMN confirmed_at timestamptz;
+
+-- Unsubscribed rows may never have been confirmed, so only backfill the ones we know were.
+UPDATE subscriptions
+SET confirmed_at = subscribed_at
+WHERE status IN ('confirmed', 'bounced', 'complained');
+
+CREATE INDEX subscriptions_subscribed_at_idx ON subscriptions (subscribed_at);
@@ -0,0 +1,23 @@
+-- Add migration script here
+CREATE TABLE drip_steps (
+    drip_step_id uuid NOT NULL,
+    delay_hours INTEGER NOT NULL CHECK (delay_hours >= 0),
+    subject TEXT NOT NULL,
+    html_content TEXT NOT NULL,
+    text_content TEXT NOT NULL,
+    created_at timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY (drip_step_id)
+);
+
+CREATE TABLE subscriber_drip_emails (
+    subscriber_id uuid NOT NULL REFERENCES subscriptions (id) ON DELETE CASCADE,
+    drip_step_id uuid NOT NULL REFERENCES drip_steps (drip_step_id) ON DELETE CASCADE,
+    status TEXT NOT NULL CHECK (status IN ('scheduled', 'sent', 'cancelled')),
+    send_after timestamptz NOT NULL,
+    sent_at timestamptz,
+    PRIMARY KEY (subscriber_id, drip_step_id)
+);
+
+CREATE INDEX subscriber_drip_emails_due_idx
+    ON subscriber_drip_emails (send_after)
+    WHERE status = 'scheduled';
@@ -0,0 +1,12 @@
+-- Add migration script here
+ALTER TABLE subscriptions
+    ADD COLUMN pending_since timestamptz,
+    ADD COLUMN confirmation_reminder_sent_at timestamptz;
+
+UPDATE subscriptions
+SET pending_since = subscribed_at
+WHERE status = 'pending_confirmation';
+
+CREATE INDEX subscriptions_pending_since_idx
+    ON subscriptions (pending_since)
+    WHERE status = 'pending_confirmation';
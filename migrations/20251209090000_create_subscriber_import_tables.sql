@@ -0,0 +1,28 @@
+-- Add migration script here
+CREATE TABLE subscriber_imports (
+    import_id uuid NOT NULL,
+    file_name TEXT NOT NULL,
+    mode TEXT NOT NULL CHECK (mode IN ('confirmed', 'pending')),
+    status TEXT NOT NULL CHECK (status IN ('processing', 'completed')),
+    created_by uuid NOT NULL REFERENCES users (user_id),
+    -- Importing as confirmed skips double opt-in, so the admin has to vouch for consent.
+    consent_attested_at timestamptz,
+    created_at timestamptz NOT NULL DEFAULT now(),
+    finished_at timestamptz,
+    PRIMARY KEY (import_id),
+    CHECK (mode = 'pending' OR consent_attested_at IS NOT NULL)
+);
+
+CREATE TABLE subscriber_import_rows (
+    import_id uuid NOT NULL REFERENCES subscriber_imports (import_id) ON DELETE CASCADE,
+    line_number INTEGER NOT NULL,
+    email TEXT NOT NULL,
+    name TEXT NOT NULL,
+    outcome TEXT CHECK (outcome IN ('imported', 'duplicate', 'invalid')),
+    error TEXT,
+    PRIMARY KEY (import_id, line_number)
+);
+
+CREATE INDEX subscriber_import_rows_pending_idx
+    ON subscriber_import_rows (import_id, line_number)
+    WHERE outcome IS NULL;
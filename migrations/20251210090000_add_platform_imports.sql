@@ -0,0 +1,21 @@
+-- Add migration script here
+ALTER TABLE subscriber_imports
+    ADD COLUMN source TEXT NOT NULL DEFAULT 'csv'
+        CHECK (source IN ('csv', 'mailchimp', 'substack'));
+
+-- Platform exports can hold several files, so a row is identified by its file and line.
+ALTER TABLE subscriber_import_rows
+    ADD COLUMN file_name TEXT NOT NULL DEFAULT '',
+    -- The status on the platform we import from, NULL means "follow the import mode".
+    ADD COLUMN status TEXT,
+    ADD COLUMN subscribed_at timestamptz;
+ALTER TABLE subscriber_import_rows DROP CONSTRAINT subscriber_import_rows_pkey;
+ALTER TABLE subscriber_import_rows ADD PRIMARY KEY (import_id, file_name, line_number);
+
+DROP INDEX subscriber_import_rows_pending_idx;
+CREATE INDEX subscriber_import_rows_pending_idx
+    ON subscriber_import_rows (import_id, file_name, line_number)
+    WHERE outcome IS NULL;
+
+-- Past posts brought over from another platform. They are archived, never delivered.
+ALTER TABLE newsletter_issues ADD COLUMN imported_from TEXT;
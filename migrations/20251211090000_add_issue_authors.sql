@@ -0,0 +1,3 @@
+-- Add migration script here
+-- NULL for issues published before authors were recorded and for imported ones.
+ALTER TABLE newsletter_issues ADD COLUMN published_by uuid REFERENCES users (user_id);
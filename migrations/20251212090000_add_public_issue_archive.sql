@@ -0,0 +1,23 @@
+-- Add migration script here
+ALTER TABLE newsletter_issues
+    ADD COLUMN slug TEXT,
+    ADD COLUMN is_private BOOLEAN NOT NULL DEFAULT false,
+    -- Drives Last-Modified and ETag for the public archive and feed.
+    ADD COLUMN updated_at timestamptz NOT NULL DEFAULT now();
+
+UPDATE newsletter_issues
+SET
+    slug = coalesce(
+        nullif(
+            trim(BOTH '-' FROM left(
+                trim(BOTH '-' FROM regexp_replace(lower(title), '[^a-z0-9]+', '-', 'g')),
+                60
+            )),
+            ''
+        ),
+        'issue'
+    ) || '-' || left(replace(newsletter_issue_id::text, '-', ''), 8),
+    updated_at = published_at::timestamptz;
+
+ALTER TABLE newsletter_issues ALTER COLUMN slug SET NOT NULL;
+ALTER TABLE newsletter_issues ADD CONSTRAINT newsletter_issues_slug_key UNIQUE (slug);
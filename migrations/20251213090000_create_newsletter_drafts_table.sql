@@ -0,0 +1,14 @@
+-- Add migration script here
+-- Unfinished issues. Publishing one moves it to newsletter_issues and deletes the draft.
+CREATE TABLE newsletter_drafts (
+    draft_id uuid NOT NULL,
+    title TEXT NOT NULL DEFAULT '',
+    html_content TEXT NOT NULL DEFAULT '',
+    text_content TEXT NOT NULL DEFAULT '',
+    segment_attribute TEXT NOT NULL DEFAULT '',
+    segment_value TEXT NOT NULL DEFAULT '',
+    created_by uuid NOT NULL REFERENCES users (user_id),
+    created_at timestamptz NOT NULL DEFAULT now(),
+    updated_at timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY (draft_id)
+);
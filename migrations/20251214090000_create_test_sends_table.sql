@@ -0,0 +1,15 @@
+-- Add migration script here
+-- Every test send of a draft or an issue. These never go through issue_delivery_queue.
+CREATE TABLE test_sends (
+    test_send_id uuid NOT NULL,
+    draft_id uuid REFERENCES newsletter_drafts (draft_id) ON DELETE SET NULL,
+    newsletter_issue_id uuid REFERENCES newsletter_issues (newsletter_issue_id),
+    subject TEXT NOT NULL,
+    recipient TEXT NOT NULL,
+    outcome TEXT NOT NULL CHECK (outcome IN ('sent', 'failed')),
+    sent_by uuid NOT NULL REFERENCES users (user_id),
+    sent_at timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY (test_send_id)
+);
+CREATE INDEX test_sends_draft_idx ON test_sends (draft_id);
+CREATE INDEX test_sends_issue_idx ON test_sends (newsletter_issue_id);
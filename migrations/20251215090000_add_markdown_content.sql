@@ -0,0 +1,4 @@
+-- Add migration script here
+-- The Markdown an issue was written in, NULL for issues written as HTML.
+ALTER TABLE newsletter_issues ADD COLUMN markdown_content TEXT;
+ALTER TABLE newsletter_drafts ADD COLUMN markdown_content TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,67 @@
+-- Add migration script here
+-- Reusable wrappers for issue HTML: a Tera template that places the issue with
+-- {{ content | safe }} and links {{ unsubscribe_url }}.
+CREATE TABLE email_layouts (
+    email_layout_id uuid NOT NULL,
+    name TEXT NOT NULL,
+    template TEXT NOT NULL,
+    created_at timestamptz NOT NULL DEFAULT now(),
+    updated_at timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY (email_layout_id),
+    CONSTRAINT email_layouts_name_key UNIQUE (name)
+);
+
+-- Issues keep their layout, the worker wraps each email at send time. Drafts just lose it.
+ALTER TABLE newsletter_issues
+    ADD COLUMN email_layout_id uuid REFERENCES email_layouts (email_layout_id);
+ALTER TABLE newsletter_drafts
+    ADD COLUMN email_layout_id uuid REFERENCES email_layouts (email_layout_id) ON DELETE SET NULL;
+
+INSERT INTO email_layouts (email_layout_id, name, template)
+VALUES (
+    'b0c5a3a4-5a8e-4d53-9d0e-6f0f3d6c2a11',
+    'Default',
+    $layout$<!DOCTYPE html>
+<html lang="en">
+
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1">
+    <title>{{ title }}</title>
+    <style>
+        body { margin: 0; padding: 0; background-color: #f4f4f4; }
+        .container { max-width: 600px; background-color: #ffffff; font-family: Helvetica, Arial, sans-serif; font-size: 16px; line-height: 1.5; color: #222222; }
+        .header { padding: 24px 32px 0; font-size: 20px; font-weight: bold; }
+        .content { padding: 32px; }
+        .footer { padding: 0 32px 32px; font-size: 12px; color: #777777; }
+        .footer a { color: #777777; }
+    </style>
+</head>
+
+<body>
+    <table role="presentation" width="100%" cellpadding="0" cellspacing="0">
+        <tr>
+            <td align="center" style="padding: 24px 12px;">
+                <table role="presentation" width="600" cellpadding="0" cellspacing="0" class="container">
+                    <tr>
+                        <td class="header">Our newsletter</td>
+                    </tr>
+                    <tr>
+                        <td class="content">{{ content | safe }}</td>
+                    </tr>
+                    <tr>
+                        <td class="footer">
+                            <p>You are receiving this because you subscribed to our newsletter.
+                                <a href="{{ unsubscribe_url }}">Unsubscribe</a>.</p>
+                            <p>Our postal address goes here.</p>
+                        </td>
+                    </tr>
+                </table>
+            </td>
+        </tr>
+    </table>
+</body>
+
+</html>
+$layout$
+);
@@ -0,0 +1,11 @@
+-- Add migration script here
+-- Admin edits of the emails sent on a subscriber's behalf. Kinds without a row use the
+-- templates bundled in views/.
+CREATE TABLE transactional_email_templates (
+    kind TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    html_template TEXT NOT NULL,
+    text_template TEXT NOT NULL,
+    updated_at timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY (kind)
+);
@@ -0,0 +1,3 @@
+-- Add migration script here
+-- Why the signup policy accepted a subscriber with a warning, e.g. a role address.
+ALTER TABLE subscriptions ADD COLUMN signup_flag TEXT;
@@ -0,0 +1,6 @@
+-- Add migration script here
+-- The post's id on the platform it was imported from, so importing the same export twice
+-- doesn't archive its posts twice. Issues imported before this keep a NULL id.
+ALTER TABLE newsletter_issues ADD COLUMN imported_post_id TEXT;
+CREATE UNIQUE INDEX newsletter_issues_imported_post_idx
+    ON newsletter_issues (imported_from, imported_post_id);
@@ -0,0 +1,10 @@
+-- Add migration script here
+-- Where an admin's password reset links are sent. Accounts without one can't be reset.
+ALTER TABLE users ADD COLUMN email TEXT;
+
+CREATE TABLE password_reset_tokens (
+    password_reset_token TEXT NOT NULL,
+    user_id uuid NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+    created_at timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY (password_reset_token)
+);
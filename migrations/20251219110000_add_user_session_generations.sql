@@ -0,0 +1,4 @@
+-- Add migration script here
+-- Sessions remember the generation they logged in under. Bumping it logs the user out
+-- everywhere, e.g. after a password reset.
+ALTER TABLE users ADD COLUMN session_generation INTEGER NOT NULL DEFAULT 0;
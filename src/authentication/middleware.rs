@@ -6,10 +6,13 @@ use actix_web::{
     dev::{ServiceRequest, ServiceResponse},
     error::InternalError,
     middleware::Next,
+    web,
 };
+use sqlx::PgPool;
 use uuid::Uuid;
 
 use crate::{
+    authentication::get_session_generation,
     routes::{e500, see_other},
     session_state::TypedSession,
 };
@@ -24,7 +27,24 @@ pub async fn reject_anonymous_users(
         TypedSession::from_request(http_request, payload).await
     }?;
 
-    match session.get_user_id().map_err(e500)? {
+    let user_id = match session.get_user_id().map_err(e500)? {
+        Some(user_id) => {
+            let pool = req
+                .app_data::<web::Data<PgPool>>()
+                .ok_or_else(|| e500("The database pool is missing."))?;
+            let current = get_session_generation(user_id, pool).await.map_err(e500)?;
+            // The user was deleted or logged out everywhere since this session began.
+            if current != Some(session.get_session_generation().map_err(e500)?) {
+                session.logout();
+                None
+            } else {
+                Some(user_id)
+            }
+        }
+        None => None,
+    };
+
+    match user_id {
         Some(user_id) => {
             req.extensions_mut().insert(UserId(user_id));
             next.call(req).await
@@ -97,6 +97,22 @@ fn verify_password_hash(
     Ok(())
 }
 
+/// The rules a new password is held to, whether it is changed or reset.
+pub fn validate_new_password(
+    new_password: &SecretString,
+    new_password_check: &SecretString,
+) -> Result<(), &'static str> {
+    if new_password.expose_secret() != new_password_check.expose_secret() {
+        return Err("You entered two different new passwords - the field values must match.");
+    }
+    if new_password.expose_secret().len() < 12 || new_password.expose_secret().len() > 128 {
+        return Err(
+            "The password should be longer than 12 characters but shorter than 128 characters.",
+        );
+    }
+    Ok(())
+}
+
 #[tracing::instrument(name = "Change password", skip(password, pool))]
 pub async fn change_password(
     user_id: uuid::Uuid,
@@ -131,3 +147,35 @@ fn compute_password_hash(password: SecretString) -> Result<SecretString, anyhow:
     .to_string();
     Ok(SecretString::from(password_hash))
 }
+
+/// `None` if the user no longer exists.
+#[tracing::instrument(name = "Get session generation", skip(pool))]
+pub async fn get_session_generation(
+    user_id: Uuid,
+    pool: &PgPool,
+) -> Result<Option<i32>, anyhow::Error> {
+    sqlx::query_scalar!(
+        r#"SELECT session_generation FROM users WHERE user_id = $1"#,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to read the user's session generation.")
+}
+
+/// Logs the user out of every session they have open.
+#[tracing::instrument(name = "End all sessions", skip(pool))]
+pub async fn end_all_sessions(user_id: Uuid, pool: &PgPool) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET session_generation = session_generation + 1
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .execute(pool)
+    .await
+    .context("Failed to end the user's sessions.")?;
+    Ok(())
+}
@@ -0,0 +1,249 @@
+use hmac::{Hmac, Mac};
+use rand::{Rng, distr::Alphanumeric};
+use secrecy::{ExposeSecret, SecretString};
+use sha2::{Digest, Sha256};
+
+use crate::configuration::BotProtectionSettings;
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub struct FormChallenge {
+    pub token: String,
+    pub challenge: String,
+    pub difficulty: u8,
+}
+
+/// A form token that passed every check. It is only good for one submission, so the caller
+/// claims `challenge` until the token would have expired anyway.
+#[derive(Debug)]
+pub struct VerifiedToken<'a> {
+    pub challenge: &'a str,
+    pub expires_in_secs: u64,
+}
+
+pub struct FormSubmission<'a> {
+    pub honeypot: &'a str,
+    pub form_token: Option<&'a str>,
+    pub pow_nonce: Option<&'a str>,
+}
+
+#[derive(Clone)]
+pub struct BotProtection {
+    secret: SecretString,
+    settings: BotProtectionSettings,
+}
+
+impl BotProtection {
+    pub fn new(secret: SecretString, settings: BotProtectionSettings) -> Self {
+        Self { secret, settings }
+    }
+
+    pub fn issue_challenge(&self, now: i64) -> FormChallenge {
+        let challenge: String = rand::rng()
+            .sample_iter(Alphanumeric)
+            .map(char::from)
+            .take(16)
+            .collect();
+        let signature = self.sign(now, &challenge);
+        FormChallenge {
+            token: format!("{now}.{challenge}.{signature}"),
+            challenge,
+            difficulty: self.settings.proof_of_work_difficulty,
+        }
+    }
+
+    pub fn verify<'a>(
+        &self,
+        submission: &FormSubmission<'a>,
+        now: i64,
+    ) -> Result<Option<VerifiedToken<'a>>, String> {
+        if !submission.honeypot.is_empty() {
+            return Err("The honeypot field was filled in.".into());
+        }
+        if !self.settings.require_form_token {
+            return Ok(None);
+        }
+
+        let token = submission
+            .form_token
+            .ok_or_else(|| "The form token is missing.".to_string())?;
+        let mut parts = token.splitn(3, '.');
+        let (Some(issued_at), Some(challenge), Some(signature)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Err("The form token is malformed.".into());
+        };
+        let issued_at: i64 = issued_at
+            .parse()
+            .map_err(|_| "The form token is malformed.".to_string())?;
+        let signature =
+            hex::decode(signature).map_err(|_| "The form token is malformed.".to_string())?;
+
+        let mut mac = self.mac();
+        mac.update(Self::signed_payload(issued_at, challenge).as_bytes());
+        mac.verify_slice(&signature)
+            .map_err(|_| "The form token signature is invalid.".to_string())?;
+
+        let elapsed = now - issued_at;
+        if elapsed < self.settings.min_submit_secs {
+            return Err(format!("The form was submitted after only {elapsed}s."));
+        }
+        if elapsed > self.settings.max_token_age_secs {
+            return Err("The form token has expired.".into());
+        }
+
+        let difficulty = self.settings.proof_of_work_difficulty;
+        if difficulty > 0 {
+            let nonce = submission
+                .pow_nonce
+                .ok_or_else(|| "The proof-of-work nonce is missing.".to_string())?;
+            if !is_valid_proof_of_work(challenge, nonce, difficulty) {
+                return Err("The proof-of-work nonce is invalid.".into());
+            }
+        }
+
+        Ok(Some(VerifiedToken {
+            challenge,
+            expires_in_secs: (self.settings.max_token_age_secs - elapsed + 1) as u64,
+        }))
+    }
+
+    fn sign(&self, issued_at: i64, challenge: &str) -> String {
+        let mut mac = self.mac();
+        mac.update(Self::signed_payload(issued_at, challenge).as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    fn mac(&self) -> HmacSha256 {
+        HmacSha256::new_from_slice(self.secret.expose_secret().as_bytes())
+            .expect("HMAC can take a key of any size")
+    }
+
+    fn signed_payload(issued_at: i64, challenge: &str) -> String {
+        format!("subscribe-form:{issued_at}:{challenge}")
+    }
+}
+
+// The client must find a nonce such that sha256("{challenge}:{nonce}") starts with
+// `difficulty` zero bits - see the script in `views/home.html`.
+pub fn is_valid_proof_of_work(challenge: &str, nonce: &str, difficulty: u8) -> bool {
+    let digest = Sha256::digest(format!("{challenge}:{nonce}").as_bytes());
+    let mut remaining = u32::from(difficulty);
+    for byte in digest {
+        let zeros = byte.leading_zeros();
+        if zeros >= remaining {
+            return true;
+        }
+        if zeros < 8 {
+            return false;
+        }
+        remaining -= 8;
+    }
+    false
+}
+
+#[cfg(test)]
+mod test {
+    use claims::{assert_err, assert_ok};
+    use secrecy::SecretString;
+
+    use super::{BotProtection, FormSubmission, is_valid_proof_of_work};
+    use crate::configuration::BotProtectionSettings;
+
+    fn protection(difficulty: u8) -> BotProtection {
+        BotProtection::new(
+            SecretString::from("a-very-secret-key"),
+            BotProtectionSettings {
+                require_form_token: true,
+                min_submit_secs: 3,
+                max_token_age_secs: 3600,
+                proof_of_work_difficulty: difficulty,
+            },
+        )
+    }
+
+    fn submission<'a>(token: &'a str, nonce: Option<&'a str>) -> FormSubmission<'a> {
+        FormSubmission {
+            honeypot: "",
+            form_token: Some(token),
+            pow_nonce: nonce,
+        }
+    }
+
+    fn solve(challenge: &str, difficulty: u8) -> String {
+        (0u64..)
+            .map(|n| n.to_string())
+            .find(|n| is_valid_proof_of_work(challenge, n, difficulty))
+            .unwrap()
+    }
+
+    #[test]
+    fn a_token_submitted_after_the_minimum_time_is_accepted() {
+        let protection = protection(0);
+        let challenge = protection.issue_challenge(1_000);
+        assert_ok!(protection.verify(&submission(&challenge.token, None), 1_005));
+    }
+
+    #[test]
+    fn a_verified_token_is_claimed_until_it_expires() {
+        let protection = protection(0);
+        let challenge = protection.issue_challenge(1_000);
+        let submission = submission(&challenge.token, None);
+
+        let verified = protection.verify(&submission, 1_600).unwrap().unwrap();
+
+        assert_eq!(verified.challenge, challenge.challenge);
+        assert_eq!(verified.expires_in_secs, 3_001);
+    }
+
+    #[test]
+    fn a_token_submitted_too_quickly_is_rejected() {
+        let protection = protection(0);
+        let challenge = protection.issue_challenge(1_000);
+        assert_err!(protection.verify(&submission(&challenge.token, None), 1_001));
+    }
+
+    #[test]
+    fn an_expired_token_is_rejected() {
+        let protection = protection(0);
+        let challenge = protection.issue_challenge(1_000);
+        assert_err!(protection.verify(&submission(&challenge.token, None), 10_000));
+    }
+
+    #[test]
+    fn a_tampered_token_is_rejected() {
+        let protection = protection(0);
+        let challenge = protection.issue_challenge(1_000);
+        let tampered = challenge.token.replacen("1000", "900", 1);
+        assert_err!(protection.verify(&submission(&tampered, None), 1_005));
+    }
+
+    #[test]
+    fn a_filled_honeypot_is_rejected() {
+        let protection = protection(0);
+        let challenge = protection.issue_challenge(1_000);
+        let submission = FormSubmission {
+            honeypot: "https://spam.example",
+            form_token: Some(&challenge.token),
+            pow_nonce: None,
+        };
+        assert_err!(protection.verify(&submission, 1_005));
+    }
+
+    #[test]
+    fn proof_of_work_is_required_when_enabled() {
+        let protection = protection(8);
+        let challenge = protection.issue_challenge(1_000);
+        let nonce = solve(&challenge.challenge, 8);
+
+        assert_err!(protection.verify(&submission(&challenge.token, None), 1_005));
+        assert_ok!(protection.verify(&submission(&challenge.token, Some(&nonce)), 1_005));
+    }
+
+    #[test]
+    fn difficulty_counts_leading_zero_bits() {
+        let nonce = solve("challenge", 12);
+        assert!(is_valid_proof_of_work("challenge", &nonce, 12));
+        assert!(is_valid_proof_of_work("challenge", &nonce, 4));
+    }
+}
@@ -5,8 +5,9 @@ use serde_aux::field_attributes::deserialize_number_from_string;
 use sqlx::ConnectOptions;
 use sqlx::postgres::{PgConnectOptions, PgSslMode};
 
-use crate::domain::SubscriberEmail;
+use crate::domain::{RoleAddressPolicy, SignupPolicy, SubscriberEmail};
 use crate::email_client::EmailClient;
+use crate::rate_limiter::RateLimit;
 
 #[derive(serde::Deserialize, Debug, Clone)]
 pub struct Settings {
@@ -14,6 +15,69 @@ pub struct Settings {
     pub app: ApplicationSettings,
     pub email_client: EmailClientSettings,
     pub redis_uri: SecretString,
+    pub rate_limits: RateLimitSettings,
+    pub signup_policy: SignupPolicySettings,
+    pub bot_protection: BotProtectionSettings,
+    #[serde(default)]
+    pub confirmation_pages: ConfirmationPageSettings,
+    pub confirmation_reminders: ConfirmationReminderSettings,
+}
+
+/// Where to send subscribers after they click a confirmation link. Outcomes without a URL
+/// get the built-in page.
+#[derive(serde::Deserialize, Debug, Clone, Default)]
+pub struct ConfirmationPageSettings {
+    pub confirmed_url: Option<String>,
+    pub already_confirmed_url: Option<String>,
+    pub invalid_token_url: Option<String>,
+    pub error_url: Option<String>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct ConfirmationReminderSettings {
+    pub remind_after_hours: i32,
+    pub purge_after_hours: i32,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct BotProtectionSettings {
+    pub require_form_token: bool,
+    pub min_submit_secs: i64,
+    pub max_token_age_secs: i64,
+    pub proof_of_work_difficulty: u8,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct SignupPolicySettings {
+    pub block_disposable_domains: bool,
+    #[serde(default)]
+    pub blocked_domains: Vec<String>,
+    #[serde(default)]
+    pub allowed_domains: Vec<String>,
+    pub role_addresses: RoleAddressPolicy,
+}
+
+impl SignupPolicySettings {
+    pub fn policy(&self) -> SignupPolicy {
+        SignupPolicy::new(
+            self.block_disposable_domains,
+            &self.blocked_domains,
+            &self.allowed_domains,
+            self.role_addresses,
+        )
+    }
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct RateLimitSettings {
+    pub key_prefix: String,
+    pub subscribe_per_ip: RateLimit,
+    pub subscribe_per_email: RateLimit,
+    pub data_request_per_email: RateLimit,
+    pub password_reset_per_user: RateLimit,
+    /// Reverse proxies whose `X-Forwarded-For` header is believed.
+    #[serde(default)]
+    pub trusted_proxies: Vec<std::net::IpAddr>,
 }
 
 #[derive(serde::Deserialize, Debug, Clone)]
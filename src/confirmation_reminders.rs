@@ -0,0 +1,142 @@
+use anyhow::Context;
+use sqlx::PgPool;
+use tracing::{Span, field::display};
+use uuid::Uuid;
+
+use crate::{
+    configuration::ConfirmationReminderSettings,
+    domain::{ConfirmationToken, SubscriberEmail},
+    email_outbox::enqueue_email,
+    issue_delivery_worker::ExecutionOutcome,
+    routes::{confirmation_link, delete_tokens, store_token},
+    startup::ApplicationBaseURL,
+    transactional_emails::{TransactionalEmail, render_transactional_email},
+};
+
+/// Sends the one reminder a pending subscriber gets. The old link stops working, so the
+/// reminder is the only one that confirms.
+#[tracing::instrument(skip_all, fields(subscriber_id=tracing::field::Empty), err)]
+pub async fn try_send_confirmation_reminder(
+    pool: &PgPool,
+    base_url: &ApplicationBaseURL,
+    settings: &ConfirmationReminderSettings,
+) -> Result<ExecutionOutcome, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    let Some(pending) = sqlx::query!(
+        r#"
+        SELECT id, name, email
+        FROM subscriptions
+        WHERE status = 'pending_confirmation'
+            AND confirmation_reminder_sent_at IS NULL
+            AND pending_since <= now() - make_interval(hours => $1)
+        ORDER BY pending_since
+        FOR UPDATE
+        SKIP LOCKED
+        LIMIT 1
+        "#,
+        settings.remind_after_hours
+    )
+    .fetch_optional(&mut *transaction)
+    .await?
+    else {
+        return Ok(ExecutionOutcome::EmptyQueue);
+    };
+    Span::current().record("subscriber_id", display(pending.id));
+
+    match SubscriberEmail::parse(pending.email) {
+        Ok(recipient) => {
+            delete_tokens(&mut transaction, pending.id)
+                .await
+                .context("Failed to remove the previous confirmation token.")?;
+            let confirmation_token = ConfirmationToken::new();
+            store_token(&mut transaction, pending.id, confirmation_token.as_ref())
+                .await
+                .context("Failed to store the reminder confirmation token.")?;
+
+            let link = confirmation_link(base_url, confirmation_token.as_ref());
+            let email = render_transactional_email(
+                &mut *transaction,
+                TransactionalEmail::ConfirmationReminder,
+                &[("name", &pending.name), ("link", &link)],
+            )
+            .await
+            .context("Failed to render a confirmation reminder.")?;
+            enqueue_email(
+                &mut *transaction,
+                &recipient,
+                &email.subject,
+                &email.html,
+                &email.text,
+            )
+            .await
+            .context("Failed to queue a confirmation reminder.")?;
+        }
+        // Still marked as reminded, so it gets purged like any other abandoned signup.
+        Err(e) => {
+            tracing::error!(
+            error.message = %e,
+            "Skipping a confirmation reminder. The subscriber's stored address is invalid",
+            );
+        }
+    }
+
+    sqlx::query!(
+        r#"
+        UPDATE subscriptions SET confirmation_reminder_sent_at = now() WHERE id = $1
+        "#,
+        pending.id
+    )
+    .execute(&mut *transaction)
+    .await?;
+    transaction.commit().await?;
+
+    Ok(ExecutionOutcome::TaskCompleted)
+}
+
+/// Deletes signups that were reminded and still didn't confirm. Returning subscribers who
+/// were confirmed before keep their row and history.
+#[tracing::instrument(skip_all, err)]
+pub async fn purge_unconfirmed_subscribers(
+    pool: &PgPool,
+    settings: &ConfirmationReminderSettings,
+) -> Result<u64, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    let ids: Vec<Uuid> = sqlx::query_scalar!(
+        r#"
+        SELECT id
+        FROM subscriptions
+        WHERE status = 'pending_confirmation'
+            AND confirmed_at IS NULL
+            AND confirmation_reminder_sent_at <= now() - make_interval(hours => $1)
+        FOR UPDATE
+        SKIP LOCKED
+        "#,
+        settings.purge_after_hours
+    )
+    .fetch_all(&mut *transaction)
+    .await?;
+    if ids.is_empty() {
+        return Ok(0);
+    }
+
+    sqlx::query!(
+        r#"DELETE FROM subscription_tokens WHERE subscriber_id = ANY($1)"#,
+        &ids
+    )
+    .execute(&mut *transaction)
+    .await?;
+    sqlx::query!(
+        r#"DELETE FROM data_request_tokens WHERE subscriber_id = ANY($1)"#,
+        &ids
+    )
+    .execute(&mut *transaction)
+    .await?;
+    let n_purged = sqlx::query!(r#"DELETE FROM subscriptions WHERE id = ANY($1)"#, &ids)
+        .execute(&mut *transaction)
+        .await?
+        .rows_affected();
+    transaction.commit().await?;
+
+    tracing::info!("Purged {} unconfirmed subscribers", n_purged);
+    Ok(n_purged)
+}
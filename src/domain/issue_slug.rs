@@ -0,0 +1,64 @@
+use uuid::Uuid;
+
+const MAX_TITLE_LENGTH: usize = 60;
+
+/// The public address of an issue, e.g. `/issues/our-first-issue-3f2a9c1e`. The id suffix keeps
+/// slugs unique when two issues share a title.
+#[derive(Debug)]
+pub struct IssueSlug(String);
+
+impl IssueSlug {
+    pub fn new(title: &str, issue_id: Uuid) -> Self {
+        let mut slug = String::new();
+        for c in title.chars().map(|c| c.to_ascii_lowercase()) {
+            if c.is_ascii_alphanumeric() {
+                slug.push(c);
+            } else if !slug.is_empty() && !slug.ends_with('-') {
+                slug.push('-');
+            }
+        }
+        slug.truncate(MAX_TITLE_LENGTH);
+        let slug = match slug.trim_end_matches('-') {
+            "" => "issue",
+            s => s,
+        };
+        let suffix = &issue_id.simple().to_string()[..8];
+        Self(format!("{slug}-{suffix}"))
+    }
+}
+
+impl AsRef<str> for IssueSlug {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use uuid::Uuid;
+
+    use crate::domain::IssueSlug;
+
+    fn id() -> Uuid {
+        Uuid::parse_str("3f2a9c1e-0000-4000-8000-000000000000").unwrap()
+    }
+
+    #[test]
+    fn titles_are_reduced_to_lowercase_words() {
+        let slug = IssueSlug::new("  Hello, World! Issue #3 ", id());
+        assert_eq!(slug.as_ref(), "hello-world-issue-3-3f2a9c1e");
+    }
+
+    #[test]
+    fn titles_without_usable_characters_still_get_a_slug() {
+        let slug = IssueSlug::new("¡¿?!", id());
+        assert_eq!(slug.as_ref(), "issue-3f2a9c1e");
+    }
+
+    #[test]
+    fn long_titles_are_shortened() {
+        let slug = IssueSlug::new(&"word ".repeat(40), id());
+        assert!(slug.as_ref().len() <= 60 + 9);
+        assert!(!slug.as_ref().contains("--"));
+    }
+}
@@ -1,9 +1,19 @@
 mod confirmation_token;
+mod issue_slug;
 mod new_subscriber;
+mod signup_attribution;
+mod signup_policy;
+mod subscriber_attributes;
 mod subscriber_email;
 mod subscriber_name;
+mod subscription_status;
 
 pub use confirmation_token::ConfirmationToken;
+pub use issue_slug::IssueSlug;
 pub use new_subscriber::NewSubscriber;
+pub use signup_attribution::{RawSignupAttribution, SignupAttribution};
+pub use signup_policy::{PolicyVerdict, RoleAddressPolicy, SignupPolicy};
+pub use subscriber_attributes::{AttributeDefinition, AttributeKind, SubscriberAttributes};
 pub use subscriber_email::SubscriberEmail;
 pub use subscriber_name::SubscriberName;
+pub use subscription_status::SubscriptionStatus;
@@ -1,18 +1,35 @@
-use crate::routes::FormData;
+use std::collections::HashMap;
 
-use super::{SubscriberEmail, SubscriberName};
+use super::{
+    AttributeDefinition, SignupAttribution, SubscriberAttributes, SubscriberEmail, SubscriberName,
+};
 
 pub struct NewSubscriber {
     pub email: SubscriberEmail,
     pub name: SubscriberName,
+    pub attributes: SubscriberAttributes,
+    pub attribution: SignupAttribution,
+    /// Set when the signup policy accepted the address with a warning.
+    pub signup_flag: Option<String>,
 }
 
-impl TryFrom<FormData> for NewSubscriber {
-    type Error = String;
-
-    fn try_from(form: FormData) -> Result<Self, Self::Error> {
-        let name = SubscriberName::parse(form.name)?;
-        let email = SubscriberEmail::parse(form.email)?;
-        Ok(NewSubscriber { email, name })
+impl NewSubscriber {
+    pub fn parse(
+        name: String,
+        email: String,
+        attributes: HashMap<String, String>,
+        definitions: &[AttributeDefinition],
+        attribution: SignupAttribution,
+    ) -> Result<Self, String> {
+        let name = SubscriberName::parse(name)?;
+        let email = SubscriberEmail::parse(email)?;
+        let attributes = SubscriberAttributes::parse(attributes, definitions)?;
+        Ok(NewSubscriber {
+            email,
+            name,
+            attributes,
+            attribution,
+            signup_flag: None,
+        })
     }
 }
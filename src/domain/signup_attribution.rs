@@ -0,0 +1,106 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+// Attribution is best effort: oversized values are cut short rather than failing the signup.
+const MAX_LENGTH: usize = 256;
+
+/// Where a signup came from, as reported by the form or API client.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct SignupAttribution {
+    pub source: Option<String>,
+    pub referrer: Option<String>,
+    pub utm_source: Option<String>,
+    pub utm_medium: Option<String>,
+    pub utm_campaign: Option<String>,
+    pub utm_term: Option<String>,
+    pub utm_content: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct RawSignupAttribution {
+    pub source: Option<String>,
+    pub referrer: Option<String>,
+    pub utm_source: Option<String>,
+    pub utm_medium: Option<String>,
+    pub utm_campaign: Option<String>,
+    pub utm_term: Option<String>,
+    pub utm_content: Option<String>,
+}
+
+impl SignupAttribution {
+    /// `fallback_referrer` is the request's `Referer` header, used when the client didn't
+    /// pass one explicitly.
+    pub fn parse(raw: RawSignupAttribution, fallback_referrer: Option<&str>) -> Self {
+        Self {
+            source: clean(raw.source),
+            referrer: clean(raw.referrer).or_else(|| clean(fallback_referrer.map(str::to_owned))),
+            utm_source: clean(raw.utm_source),
+            utm_medium: clean(raw.utm_medium),
+            utm_campaign: clean(raw.utm_campaign),
+            utm_term: clean(raw.utm_term),
+            utm_content: clean(raw.utm_content),
+        }
+    }
+}
+
+fn clean(value: Option<String>) -> Option<String> {
+    let value = value?;
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    Some(value.graphemes(true).take(MAX_LENGTH).collect())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::domain::{RawSignupAttribution, SignupAttribution};
+
+    #[test]
+    fn blank_values_are_dropped_and_the_rest_trimmed() {
+        let attribution = SignupAttribution::parse(
+            RawSignupAttribution {
+                source: Some("  ".into()),
+                utm_source: Some(" twitter ".into()),
+                ..Default::default()
+            },
+            None,
+        );
+
+        assert_eq!(attribution.source, None);
+        assert_eq!(attribution.utm_source.as_deref(), Some("twitter"));
+    }
+
+    #[test]
+    fn referer_header_is_used_when_no_referrer_is_given() {
+        let attribution =
+            SignupAttribution::parse(Default::default(), Some("https://blog.example/post"));
+        assert_eq!(
+            attribution.referrer.as_deref(),
+            Some("https://blog.example/post")
+        );
+
+        let attribution = SignupAttribution::parse(
+            RawSignupAttribution {
+                referrer: Some("https://news.example".into()),
+                ..Default::default()
+            },
+            Some("https://blog.example/post"),
+        );
+        assert_eq!(
+            attribution.referrer.as_deref(),
+            Some("https://news.example")
+        );
+    }
+
+    #[test]
+    fn long_values_are_truncated() {
+        let attribution = SignupAttribution::parse(
+            RawSignupAttribution {
+                utm_campaign: Some("a".repeat(1000)),
+                ..Default::default()
+            },
+            None,
+        );
+        assert_eq!(attribution.utm_campaign.unwrap().len(), 256);
+    }
+}
@@ -0,0 +1,231 @@
+use std::collections::HashSet;
+
+use super::SubscriberEmail;
+
+const BUNDLED_DISPOSABLE_DOMAINS: &str = include_str!("disposable_domains.txt");
+
+const ROLE_LOCAL_PARTS: [&str; 14] = [
+    "abuse",
+    "admin",
+    "donotreply",
+    "do-not-reply",
+    "hostmaster",
+    "info",
+    "mailer-daemon",
+    "no-reply",
+    "noreply",
+    "postmaster",
+    "root",
+    "sales",
+    "support",
+    "webmaster",
+];
+
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RoleAddressPolicy {
+    Allow,
+    Flag,
+    Reject,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PolicyVerdict {
+    Accepted,
+    Flagged(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct SignupPolicy {
+    disposable_domains: HashSet<String>,
+    blocked_domains: HashSet<String>,
+    allowed_domains: HashSet<String>,
+    role_addresses: RoleAddressPolicy,
+}
+
+impl SignupPolicy {
+    pub fn new(
+        block_disposable_domains: bool,
+        blocked_domains: &[String],
+        allowed_domains: &[String],
+        role_addresses: RoleAddressPolicy,
+    ) -> Self {
+        let disposable_domains = BUNDLED_DISPOSABLE_DOMAINS
+            .lines()
+            .filter(|_| block_disposable_domains)
+            .filter_map(normalise_domain)
+            .collect();
+        let blocked_domains = blocked_domains
+            .iter()
+            .filter_map(|d| normalise_domain(d))
+            .collect();
+        let allowed_domains = allowed_domains
+            .iter()
+            .filter_map(|d| normalise_domain(d))
+            .collect();
+
+        Self {
+            disposable_domains,
+            blocked_domains,
+            allowed_domains,
+            role_addresses,
+        }
+    }
+
+    pub fn check(&self, email: &SubscriberEmail) -> Result<PolicyVerdict, String> {
+        let (local, domain) = email
+            .as_ref()
+            .rsplit_once('@')
+            .ok_or_else(|| format!("{email} is not a valid subscriber email."))?;
+
+        if !self.allowed_domains.is_empty() && !matches_any(domain, &self.allowed_domains) {
+            return Err(format!(
+                "Sign-ups from {domain} addresses are not accepted."
+            ));
+        }
+        if matches_any(domain, &self.blocked_domains) {
+            return Err(format!(
+                "Sign-ups from {domain} addresses are not accepted."
+            ));
+        }
+        if matches_any(domain, &self.disposable_domains) {
+            return Err(format!(
+                "{domain} is a disposable email provider. Please use a permanent address."
+            ));
+        }
+
+        let local = local.to_lowercase();
+        let local = local.split('+').next().unwrap_or_default();
+        if ROLE_LOCAL_PARTS.contains(&local) {
+            return match self.role_addresses {
+                RoleAddressPolicy::Allow => Ok(PolicyVerdict::Accepted),
+                RoleAddressPolicy::Flag => Ok(PolicyVerdict::Flagged(format!(
+                    "{email} looks like a role address."
+                ))),
+                RoleAddressPolicy::Reject => Err(format!(
+                    "{email} looks like a role address. Please subscribe with a personal address."
+                )),
+            };
+        }
+
+        Ok(PolicyVerdict::Accepted)
+    }
+}
+
+fn normalise_domain(domain: &str) -> Option<String> {
+    let domain = domain.trim();
+    if domain.is_empty() || domain.starts_with('#') {
+        return None;
+    }
+    idna::domain_to_ascii(domain.trim_end_matches('.')).ok()
+}
+
+// A rule for `example.com` also covers its subdomains, e.g. `mail.example.com`.
+fn matches_any(domain: &str, domains: &HashSet<String>) -> bool {
+    let mut candidate = domain;
+    loop {
+        if domains.contains(candidate) {
+            return true;
+        }
+        match candidate.split_once('.') {
+            Some((_, parent)) => candidate = parent,
+            None => return false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::domain::{PolicyVerdict, RoleAddressPolicy, SignupPolicy, SubscriberEmail};
+    use claims::{assert_err, assert_err_eq, assert_ok_eq};
+
+    fn email(s: &str) -> SubscriberEmail {
+        SubscriberEmail::parse(s.to_string()).unwrap()
+    }
+
+    fn default_policy() -> SignupPolicy {
+        SignupPolicy::new(true, &[], &[], RoleAddressPolicy::Flag)
+    }
+
+    #[test]
+    fn regular_address_is_accepted() {
+        assert_ok_eq!(
+            default_policy().check(&email("ursula@gmail.com")),
+            PolicyVerdict::Accepted
+        );
+    }
+
+    #[test]
+    fn bundled_disposable_domain_is_rejected() {
+        assert_err!(default_policy().check(&email("ursula@mailinator.com")));
+    }
+
+    #[test]
+    fn subdomain_of_a_blocked_domain_is_rejected() {
+        assert_err!(default_policy().check(&email("ursula@inbox.Mailinator.com")));
+    }
+
+    #[test]
+    fn bundled_list_can_be_disabled() {
+        let policy = SignupPolicy::new(false, &[], &[], RoleAddressPolicy::Allow);
+        assert_ok_eq!(
+            policy.check(&email("ursula@mailinator.com")),
+            PolicyVerdict::Accepted
+        );
+    }
+
+    #[test]
+    fn configured_blocked_domain_is_rejected() {
+        let policy = SignupPolicy::new(
+            false,
+            &["Spam.example".to_string()],
+            &[],
+            RoleAddressPolicy::Allow,
+        );
+        assert_err_eq!(
+            policy.check(&email("ursula@spam.example")),
+            "Sign-ups from spam.example addresses are not accepted."
+        );
+    }
+
+    #[test]
+    fn configured_blocked_domain_is_not_called_disposable() {
+        let policy = SignupPolicy::new(
+            true,
+            &["mailinator.com".to_string()],
+            &[],
+            RoleAddressPolicy::Allow,
+        );
+        assert_err_eq!(
+            policy.check(&email("ursula@mailinator.com")),
+            "Sign-ups from mailinator.com addresses are not accepted."
+        );
+    }
+
+    #[test]
+    fn only_allowed_domains_are_accepted_when_an_allowlist_is_set() {
+        let policy = SignupPolicy::new(
+            true,
+            &[],
+            &["company.example".to_string()],
+            RoleAddressPolicy::Allow,
+        );
+        assert_ok_eq!(
+            policy.check(&email("ursula@eng.company.example")),
+            PolicyVerdict::Accepted
+        );
+        assert_err!(policy.check(&email("ursula@gmail.com")));
+    }
+
+    #[test]
+    fn role_address_is_flagged_by_default() {
+        let verdict = default_policy().check(&email("NoReply+news@gmail.com"));
+        assert!(matches!(verdict, Ok(PolicyVerdict::Flagged(_))));
+    }
+
+    #[test]
+    fn role_address_can_be_rejected() {
+        let policy = SignupPolicy::new(true, &[], &[], RoleAddressPolicy::Reject);
+        assert_err!(policy.check(&email("postmaster@gmail.com")));
+    }
+}
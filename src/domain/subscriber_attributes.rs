@@ -0,0 +1,334 @@
+use std::collections::HashMap;
+
+use serde_json::{Map, Value};
+use unicode_segmentation::UnicodeSegmentation;
+
+// Field names the subscribe endpoints already use for something else.
+const RESERVED_KEYS: [&str; 8] = [
+    "name",
+    "email",
+    "attributes",
+    "website",
+    "form_token",
+    "pow_nonce",
+    "source",
+    "referrer",
+];
+const RESERVED_PREFIX: &str = "utm_";
+const MAX_KEY_LENGTH: usize = 32;
+const DEFAULT_MAX_LENGTH: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AttributeKind {
+    Text,
+    Number,
+    Choice,
+}
+
+impl AttributeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AttributeKind::Text => "text",
+            AttributeKind::Number => "number",
+            AttributeKind::Choice => "choice",
+        }
+    }
+}
+
+impl TryFrom<String> for AttributeKind {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "text" => Ok(AttributeKind::Text),
+            "number" => Ok(AttributeKind::Number),
+            "choice" => Ok(AttributeKind::Choice),
+            other => Err(format!("{other} is not a valid attribute kind.")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct AttributeDefinition {
+    pub key: String,
+    pub label: String,
+    pub kind: AttributeKind,
+    pub required: bool,
+    pub max_length: Option<i32>,
+    pub choices: Vec<String>,
+}
+
+impl AttributeDefinition {
+    pub fn parse(
+        key: String,
+        label: String,
+        kind: AttributeKind,
+        required: bool,
+        max_length: Option<i32>,
+        choices: Vec<String>,
+    ) -> Result<Self, String> {
+        let key = key.trim().to_string();
+        let is_valid_key = key.len() <= MAX_KEY_LENGTH
+            && key.starts_with(|c: char| c.is_ascii_lowercase())
+            && key
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+        if !is_valid_key {
+            return Err(format!(
+                "{key} is not a valid attribute key. Use up to {MAX_KEY_LENGTH} lowercase letters, digits and underscores, starting with a letter."
+            ));
+        }
+        if RESERVED_KEYS.contains(&key.as_str()) || key.starts_with(RESERVED_PREFIX) {
+            return Err(format!(
+                "{key} is reserved and cannot be used as an attribute key."
+            ));
+        }
+
+        let label = label.trim().to_string();
+        if label.is_empty() {
+            return Err("An attribute needs a label.".into());
+        }
+
+        if max_length.is_some_and(|l| l <= 0) {
+            return Err("The maximum length must be a positive number.".into());
+        }
+
+        let choices: Vec<String> = choices
+            .into_iter()
+            .map(|c| c.trim().to_string())
+            .filter(|c| !c.is_empty())
+            .collect();
+        if kind == AttributeKind::Choice && choices.is_empty() {
+            return Err("A choice attribute needs at least one choice.".into());
+        }
+
+        Ok(Self {
+            key,
+            label,
+            kind,
+            required,
+            max_length,
+            choices,
+        })
+    }
+
+    fn validate(&self, raw: &str) -> Result<Value, String> {
+        let raw = raw.trim();
+        match self.kind {
+            AttributeKind::Text => {
+                let max_length = self.max_length.map_or(DEFAULT_MAX_LENGTH, |l| l as usize);
+                if raw.graphemes(true).count() > max_length {
+                    return Err(format!(
+                        "{} must be at most {max_length} characters long.",
+                        self.label
+                    ));
+                }
+                Ok(Value::String(raw.to_string()))
+            }
+            // Whole numbers are kept as integers so `12` and `12.0` segment the same way.
+            AttributeKind::Number => raw
+                .parse::<i64>()
+                .map(serde_json::Number::from)
+                .ok()
+                .or_else(|| {
+                    raw.parse::<f64>()
+                        .ok()
+                        .filter(|n| n.fract() != 0.0)
+                        .and_then(serde_json::Number::from_f64)
+                })
+                .map(Value::Number)
+                .ok_or_else(|| format!("{} must be a number.", self.label)),
+            AttributeKind::Choice => {
+                if self.choices.iter().any(|c| c == raw) {
+                    Ok(Value::String(raw.to_string()))
+                } else {
+                    Err(format!(
+                        "{} must be one of: {}.",
+                        self.label,
+                        self.choices.join(", ")
+                    ))
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SubscriberAttributes(Map<String, Value>);
+
+impl SubscriberAttributes {
+    pub fn parse(
+        mut values: HashMap<String, String>,
+        definitions: &[AttributeDefinition],
+    ) -> Result<Self, String> {
+        let mut attributes = Map::new();
+        for definition in definitions {
+            match values.remove(&definition.key) {
+                Some(raw) if !raw.trim().is_empty() => {
+                    attributes.insert(definition.key.clone(), definition.validate(&raw)?);
+                }
+                _ if definition.required => {
+                    return Err(format!("{} is required.", definition.label));
+                }
+                _ => {}
+            }
+        }
+
+        // Anything else isn't stored: forms embedded on other sites can carry fields of their
+        // own, e.g. a named submit button.
+        Ok(Self(attributes))
+    }
+
+    pub fn as_json(&self) -> Value {
+        Value::Object(self.0.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use claims::{assert_err, assert_ok};
+
+    use crate::domain::{AttributeDefinition, AttributeKind, SubscriberAttributes};
+
+    fn definitions() -> Vec<AttributeDefinition> {
+        vec![
+            AttributeDefinition::parse(
+                "company".into(),
+                "Company".into(),
+                AttributeKind::Text,
+                true,
+                Some(10),
+                vec![],
+            )
+            .unwrap(),
+            AttributeDefinition::parse(
+                "employees".into(),
+                "Employees".into(),
+                AttributeKind::Number,
+                false,
+                None,
+                vec![],
+            )
+            .unwrap(),
+            AttributeDefinition::parse(
+                "role".into(),
+                "Role".into(),
+                AttributeKind::Choice,
+                false,
+                None,
+                vec!["engineer".into(), "manager".into()],
+            )
+            .unwrap(),
+        ]
+    }
+
+    fn values(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn valid_attributes_are_typed_by_their_definition() {
+        let attributes = SubscriberAttributes::parse(
+            values(&[
+                ("company", " Acme "),
+                ("employees", "12"),
+                ("role", "manager"),
+            ]),
+            &definitions(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            attributes.as_json(),
+            serde_json::json!({ "company": "Acme", "employees": 12, "role": "manager" })
+        );
+    }
+
+    #[test]
+    fn missing_required_attribute_is_rejected() {
+        assert_err!(SubscriberAttributes::parse(
+            values(&[("role", "engineer")]),
+            &definitions()
+        ));
+    }
+
+    #[test]
+    fn blank_optional_attributes_are_skipped() {
+        let attributes = SubscriberAttributes::parse(
+            values(&[("company", "Acme"), ("employees", " ")]),
+            &definitions(),
+        )
+        .unwrap();
+        assert_eq!(
+            attributes.as_json(),
+            serde_json::json!({ "company": "Acme" })
+        );
+    }
+
+    #[test]
+    fn unknown_attributes_are_ignored() {
+        let attributes = SubscriberAttributes::parse(
+            values(&[("company", "Acme"), ("shoe_size", "44")]),
+            &definitions(),
+        )
+        .unwrap();
+        assert_eq!(
+            attributes.as_json(),
+            serde_json::json!({ "company": "Acme" })
+        );
+    }
+
+    #[test]
+    fn values_are_checked_against_the_definition() {
+        for (key, value) in [
+            ("company", "A very long company name"),
+            ("employees", "a dozen"),
+            ("role", "intern"),
+        ] {
+            assert_err!(SubscriberAttributes::parse(
+                values(&[("company", "Acme"), (key, value)]),
+                &definitions()
+            ));
+        }
+    }
+
+    #[test]
+    fn reserved_and_malformed_keys_are_rejected() {
+        for key in ["email", "utm_region", "Company", "1st", "has-dash", ""] {
+            assert_err!(AttributeDefinition::parse(
+                key.into(),
+                "Label".into(),
+                AttributeKind::Text,
+                false,
+                None,
+                vec![],
+            ));
+        }
+        assert_ok!(AttributeDefinition::parse(
+            "signup_source".into(),
+            "Label".into(),
+            AttributeKind::Text,
+            false,
+            None,
+            vec![],
+        ));
+    }
+
+    #[test]
+    fn choice_attribute_needs_choices() {
+        assert_err!(AttributeDefinition::parse(
+            "role".into(),
+            "Role".into(),
+            AttributeKind::Choice,
+            false,
+            None,
+            vec![" ".into()],
+        ));
+    }
+}
@@ -5,13 +5,22 @@ pub struct SubscriberEmail(String);
 
 impl SubscriberEmail {
     pub fn parse(s: String) -> Result<Self, String> {
-        if !s.validate_email() {
+        let normalised =
+            normalise(&s).ok_or_else(|| format!("{s} is not a valid subscriber email."))?;
+        if !normalised.validate_email() {
             return Err(format!("{s} is not a valid subscriber email."));
         };
-        Ok(Self(s))
+        Ok(Self(normalised))
     }
 }
 
+// The local part is kept as given - case-insensitive uniqueness is enforced by the database.
+fn normalise(s: &str) -> Option<String> {
+    let (local, domain) = s.trim().rsplit_once('@')?;
+    let domain = idna::domain_to_ascii(domain.trim_end_matches('.')).ok()?;
+    Some(format!("{local}@{domain}"))
+}
+
 impl AsRef<str> for SubscriberEmail {
     fn as_ref(&self) -> &str {
         &self.0
@@ -67,6 +76,30 @@ mod test {
         assert_err!(SubscriberEmail::parse(email));
     }
 
+    #[test]
+    fn surrounding_whitespace_is_trimmed() {
+        let email = SubscriberEmail::parse("  ursula@domain.com \n".to_string()).unwrap();
+        assert_eq!(email.as_ref(), "ursula@domain.com");
+    }
+
+    #[test]
+    fn domain_is_lowercased() {
+        let email = SubscriberEmail::parse("Ursula@Domain.COM".to_string()).unwrap();
+        assert_eq!(email.as_ref(), "Ursula@domain.com");
+    }
+
+    #[test]
+    fn internationalised_domain_is_converted_to_punycode() {
+        let email = SubscriberEmail::parse("ursula@Bücher.example".to_string()).unwrap();
+        assert_eq!(email.as_ref(), "ursula@xn--bcher-kva.example");
+    }
+
+    #[test]
+    fn email_with_invalid_domain_is_rejected() {
+        let email = "ursula@domain..com".to_string();
+        assert_err!(SubscriberEmail::parse(email));
+    }
+
     #[quickcheck_macros::quickcheck]
     fn full_emails_are_parsed_successfully(valid_email: ValidEmailFixture) -> bool {
         SubscriberEmail::parse(valid_email.0).is_ok()
@@ -0,0 +1,124 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscriptionStatus {
+    PendingConfirmation,
+    Confirmed,
+    Unsubscribed,
+    Bounced,
+    Complained,
+}
+
+impl SubscriptionStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SubscriptionStatus::PendingConfirmation => "pending_confirmation",
+            SubscriptionStatus::Confirmed => "confirmed",
+            SubscriptionStatus::Unsubscribed => "unsubscribed",
+            SubscriptionStatus::Bounced => "bounced",
+            SubscriptionStatus::Complained => "complained",
+        }
+    }
+
+    pub fn can_transition_to(&self, next: SubscriptionStatus) -> bool {
+        use SubscriptionStatus::*;
+
+        matches!(
+            (self, next),
+            (PendingConfirmation, Confirmed)
+                | (PendingConfirmation, Unsubscribed)
+                | (Confirmed, Unsubscribed)
+                | (Confirmed, Bounced)
+                | (Confirmed, Complained)
+                | (Unsubscribed, PendingConfirmation)
+                | (Bounced, PendingConfirmation)
+        )
+    }
+
+    pub fn transition_to(&self, next: SubscriptionStatus) -> Result<SubscriptionStatus, String> {
+        if self.can_transition_to(next) {
+            Ok(next)
+        } else {
+            Err(format!(
+                "A subscription cannot go from `{self}` to `{next}`."
+            ))
+        }
+    }
+}
+
+impl TryFrom<String> for SubscriptionStatus {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "pending_confirmation" => Ok(SubscriptionStatus::PendingConfirmation),
+            "confirmed" => Ok(SubscriptionStatus::Confirmed),
+            "unsubscribed" => Ok(SubscriptionStatus::Unsubscribed),
+            "bounced" => Ok(SubscriptionStatus::Bounced),
+            "complained" => Ok(SubscriptionStatus::Complained),
+            other => Err(format!("{other} is not a valid subscription status.")),
+        }
+    }
+}
+
+impl std::fmt::Display for SubscriptionStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::domain::SubscriptionStatus;
+    use claims::{assert_err, assert_ok};
+
+    const ALL: [SubscriptionStatus; 5] = [
+        SubscriptionStatus::PendingConfirmation,
+        SubscriptionStatus::Confirmed,
+        SubscriptionStatus::Unsubscribed,
+        SubscriptionStatus::Bounced,
+        SubscriptionStatus::Complained,
+    ];
+
+    #[test]
+    fn every_status_round_trips_through_its_string_form() {
+        for status in ALL {
+            let parsed = SubscriptionStatus::try_from(status.as_str().to_string()).unwrap();
+            assert_eq!(parsed, status);
+        }
+    }
+
+    #[test]
+    fn unknown_status_is_rejected() {
+        assert_err!(SubscriptionStatus::try_from("active".to_string()));
+    }
+
+    #[test]
+    fn full_lifecycle_is_allowed() {
+        let status = SubscriptionStatus::PendingConfirmation;
+        let status = status.transition_to(SubscriptionStatus::Confirmed).unwrap();
+        let status = status
+            .transition_to(SubscriptionStatus::Unsubscribed)
+            .unwrap();
+        assert_ok!(status.transition_to(SubscriptionStatus::PendingConfirmation));
+    }
+
+    #[test]
+    fn a_status_cannot_transition_to_itself() {
+        for status in ALL {
+            assert_err!(status.transition_to(status));
+        }
+    }
+
+    #[test]
+    fn pending_subscriber_cannot_bounce_or_complain() {
+        let status = SubscriptionStatus::PendingConfirmation;
+        assert_err!(status.transition_to(SubscriptionStatus::Bounced));
+        assert_err!(status.transition_to(SubscriptionStatus::Complained));
+    }
+
+    #[test]
+    fn complained_subscriber_cannot_be_reactivated() {
+        for next in ALL {
+            assert_err!(SubscriptionStatus::Complained.transition_to(next));
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use crate::{
+    domain::{SubscriberEmail, SubscriptionStatus},
+    email_layouts::{DEFAULT_LAYOUT, lay_out_for_recipient},
+    email_outbox::enqueue_email,
+    issue_delivery_worker::ExecutionOutcome,
+    issue_rendering::{RecipientContext, render_for_recipient},
+    startup::ApplicationBaseURL,
+};
+use anyhow::Context;
+use sqlx::{PgExecutor, PgPool};
+use tracing::{Span, field::display};
+use uuid::Uuid;
+
+/// Schedules every configured step for a subscriber who has just confirmed. A subscriber who
+/// confirms again after re-subscribing doesn't get the steps they already had.
+#[tracing::instrument(name = "Scheduling the drip sequence", skip(executor))]
+pub async fn schedule_drip_sequence(
+    executor: impl PgExecutor<'_>,
+    subscriber_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriber_drip_emails (subscriber_id, drip_step_id, status, send_after)
+        SELECT $1, drip_step_id, 'scheduled', now() + make_interval(hours => delay_hours)
+        FROM drip_steps
+        ON CONFLICT (subscriber_id, drip_step_id) DO NOTHING
+        "#,
+        subscriber_id
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+#[tracing::instrument(name = "Cancelling the drip sequence", skip(executor))]
+pub async fn cancel_drip_sequence(
+    executor: impl PgExecutor<'_>,
+    subscriber_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE subscriber_drip_emails
+        SET status = 'cancelled'
+        WHERE subscriber_id = $1 AND status = 'scheduled'
+        "#,
+        subscriber_id
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// Moves one due drip email into the outbox, which takes care of sending and retries. Steps
+/// have no layout of their own, they go out in the default one so they carry an unsubscribe
+/// link like issues do.
+#[tracing::instrument(
+    skip_all,
+    fields(
+        subscriber_id=tracing::field::Empty,
+        drip_step_id=tracing::field::Empty
+    ),
+    err
+)]
+pub async fn try_enqueue_due_drip_email(
+    pool: &PgPool,
+    base_url: &ApplicationBaseURL,
+) -> Result<ExecutionOutcome, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    let Some(due) = sqlx::query!(
+        r#"
+        SELECT
+            d.subscriber_id, d.drip_step_id,
+            s.name, s.email, s.status, s.attributes,
+            st.subject, st.html_content, st.text_content
+        FROM subscriber_drip_emails d
+        JOIN subscriptions s ON s.id = d.subscriber_id
+        JOIN drip_steps st USING (drip_step_id)
+        WHERE d.status = 'scheduled' AND d.send_after <= now()
+        ORDER BY d.send_after
+        FOR UPDATE OF d
+        SKIP LOCKED
+        LIMIT 1
+        "#
+    )
+    .fetch_optional(&mut *transaction)
+    .await?
+    else {
+        return Ok(ExecutionOutcome::EmptyQueue);
+    };
+    Span::current()
+        .record("subscriber_id", display(due.subscriber_id))
+        .record("drip_step_id", display(due.drip_step_id));
+
+    let status = SubscriptionStatus::try_from(due.status).map_err(anyhow::Error::msg)?;
+    let recipient = SubscriberEmail::parse(due.email.clone());
+    let next_status = match (status, recipient) {
+        (SubscriptionStatus::Confirmed, Ok(recipient)) => {
+            let recipient_context = RecipientContext {
+                subscriber_id: Some(due.subscriber_id),
+                name: due.name,
+                email: due.email,
+                attributes: due.attributes,
+            };
+            let rendered =
+                render_for_recipient(&due.html_content, &due.text_content, &recipient_context);
+            let rendered = lay_out_for_recipient(
+                pool,
+                base_url,
+                Some(DEFAULT_LAYOUT),
+                &due.subject,
+                rendered,
+                &recipient_context,
+            )
+            .await?;
+            enqueue_email(
+                &mut *transaction,
+                &recipient,
+                &due.subject,
+                &rendered.html_content,
+                &rendered.text_content,
+            )
+            .await
+            .context("Failed to queue a drip email.")?;
+            "sent"
+        }
+        (SubscriptionStatus::Confirmed, Err(e)) => {
+            tracing::error!(
+            error.message = %e,
+            "Cancelling a drip email. The subscriber's stored address is invalid",
+            );
+            "cancelled"
+        }
+        // Catches anyone who left the list without going through the unsubscribe link.
+        _ => "cancelled",
+    };
+
+    sqlx::query!(
+        r#"
+        UPDATE subscriber_drip_emails
+        SET
+            status = $3,
+            sent_at = CASE WHEN $3 = 'sent' THEN now() END
+        WHERE subscriber_id = $1 AND drip_step_id = $2
+        "#,
+        due.subscriber_id,
+        due.drip_step_id,
+        next_status
+    )
+    .execute(&mut *transaction)
+    .await?;
+    transaction.commit().await?;
+
+    Ok(ExecutionOutcome::TaskCompleted)
+}
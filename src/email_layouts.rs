@@ -0,0 +1,487 @@
+use std::{borrow::Cow, error::Error};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use lol_html::{
+    ElementContentHandlers, RewriteStrSettings, Selector, element,
+    html_content::{ContentType, Element},
+    rewrite_str, text,
+};
+use serde::Serialize;
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+use crate::{
+    issue_rendering::{RecipientContext, RenderedIssue, render_admin_template},
+    routes::{get_or_create_unsubscribe_token, unsubscribe_link},
+    startup::ApplicationBaseURL,
+};
+
+/// What layouts are checked and previewed with.
+pub const SAMPLE_CONTENT: &str = "<h1>Sample issue</h1><p>The content of an issue goes here.</p>";
+// No characters the template would escape, so it can be found in the output.
+const SAMPLE_UNSUBSCRIBE_URL: &str = "sample-unsubscribe-url";
+
+/// The layout seeded as "Default". It can be edited or removed, so this copy is what an issue
+/// falls back to when its own layout fails, and what emails without a layout of their own
+/// (e.g. drip steps) are sent in.
+pub const DEFAULT_LAYOUT: &str = include_str!("../views/default_email_layout.html");
+
+// Where an element's own style waits while stylesheet rules are added before it.
+const OWN_STYLE_ATTRIBUTE: &str = "data-z2p-own-style";
+
+#[derive(Serialize, Debug)]
+pub struct EmailLayout {
+    pub id: Uuid,
+    pub name: String,
+    pub template: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A layout is a Tera template. Besides `subscriber`, it can use `title`, `content` (the
+/// issue's HTML, placed with `{{ content | safe }}`) and `unsubscribe_url`.
+fn render_layout(
+    template: &str,
+    title: &str,
+    content: &str,
+    recipient: &RecipientContext,
+    unsubscribe_url: &str,
+) -> Result<String, tera::Error> {
+    let mut ctx = tera::Context::new();
+    ctx.insert("title", title);
+    ctx.insert("content", content);
+    ctx.insert("subscriber", recipient);
+    ctx.insert("unsubscribe_url", unsubscribe_url);
+    render_admin_template(template, &ctx, true)
+}
+
+/// Checked before a layout is stored, so a broken one is never found out at send time.
+pub fn validate_layout(template: &str) -> Result<(), String> {
+    let rendered = render_layout(
+        template,
+        "Sample issue",
+        SAMPLE_CONTENT,
+        &RecipientContext::anonymous("subscriber@example.com"),
+        SAMPLE_UNSUBSCRIBE_URL,
+    )
+    .map_err(|e| {
+        let detail = e
+            .source()
+            .map_or_else(|| e.to_string(), ToString::to_string);
+        format!("The layout is not a valid template: {detail}")
+    })?;
+    if !rendered.contains(SAMPLE_CONTENT) {
+        return Err("The layout must place the issue with {{ content | safe }}.".into());
+    }
+    if !rendered.contains(SAMPLE_UNSUBSCRIBE_URL) {
+        return Err("The layout must link to {{ unsubscribe_url }}.".into());
+    }
+    Ok(())
+}
+
+/// Wraps an issue that has already been personalised for `recipient`. The text version has
+/// no layout, it only gets the unsubscribe link. A layout that fails for this recipient is
+/// swapped for the default one rather than sending an email without an unsubscribe link.
+pub fn apply_layout(
+    template: &str,
+    title: &str,
+    issue: RenderedIssue,
+    recipient: &RecipientContext,
+    unsubscribe_url: &str,
+) -> Result<RenderedIssue, tera::Error> {
+    let html = render_layout(
+        template,
+        title,
+        &issue.html_content,
+        recipient,
+        unsubscribe_url,
+    )
+    .or_else(|e| {
+        tracing::warn!(
+        error.cause_chain = ?e,
+        error.message = %e,
+        "Failed to render the email layout. Sending the issue in the default one.",
+        );
+        render_layout(
+            DEFAULT_LAYOUT,
+            title,
+            &issue.html_content,
+            recipient,
+            unsubscribe_url,
+        )
+    })?;
+    Ok(RenderedIssue {
+        html_content: inline_css(&html),
+        text_content: format!(
+            "{}\n\n--\nUnsubscribe: {unsubscribe_url}\n",
+            issue.text_content.trim_end()
+        ),
+    })
+}
+
+/// Wraps an issue in its layout, if it has one, with the recipient's own unsubscribe link.
+pub async fn lay_out_for_recipient(
+    pool: &PgPool,
+    base_url: &ApplicationBaseURL,
+    layout: Option<&str>,
+    title: &str,
+    issue: RenderedIssue,
+    recipient: &RecipientContext,
+) -> Result<RenderedIssue, anyhow::Error> {
+    let Some(layout) = layout else {
+        return Ok(issue);
+    };
+    let unsubscribe_url = match recipient.subscriber_id {
+        Some(subscriber_id) => {
+            let token = get_or_create_unsubscribe_token(pool, subscriber_id)
+                .await
+                .context("Failed to get the subscriber's unsubscribe token.")?;
+            unsubscribe_link(base_url, &token)
+        }
+        // Reviewers getting a test send aren't on the list, there is nothing to leave.
+        None => format!("{}/subscriptions/unsubscribe", base_url.0),
+    };
+    apply_layout(layout, title, issue, recipient, &unsubscribe_url)
+        .context("Failed to render the issue in its layout.")
+}
+
+/// Moves the rules of `<style>` blocks onto the elements they match, most mail clients
+/// ignore or strip stylesheets. Rules are applied in stylesheet order, without regard for
+/// specificity, and an element's own `style` attribute always wins. At-rules such as
+/// `@media`, and selectors that can't be matched here (e.g. `a:hover`), stay in a `<style>`
+/// block for the clients that do support one.
+pub fn inline_css(html: &str) -> String {
+    let mut stylesheet = String::new();
+    let collected = rewrite_str(
+        html,
+        RewriteStrSettings {
+            element_content_handlers: vec![text!("style", |chunk| {
+                stylesheet.push_str(chunk.as_str());
+                Ok(())
+            })],
+            ..RewriteStrSettings::new()
+        },
+    );
+    if collected.is_err() || stylesheet.trim().is_empty() {
+        return html.to_string();
+    }
+
+    let (rules, kept) = split_stylesheet(&stylesheet);
+    let mut kept = Some(kept).filter(|k| !k.trim().is_empty());
+    let mut handlers = vec![
+        element!("style", move |el| {
+            match kept.take() {
+                Some(css) => el.set_inner_content(&css, ContentType::Html),
+                None => el.remove(),
+            }
+            Ok(())
+        }),
+        element!("*", |el| {
+            if let Some(own) = el.get_attribute("style") {
+                el.remove_attribute("style");
+                el.set_attribute(OWN_STYLE_ATTRIBUTE, &own)?;
+            }
+            Ok(())
+        }),
+    ];
+    for (selector, declarations) in &rules {
+        handlers.push((
+            Cow::Borrowed(selector),
+            ElementContentHandlers::default().element(move |el: &mut Element| {
+                add_declarations(el, declarations)?;
+                Ok(())
+            }),
+        ));
+    }
+    handlers.push(element!("*", |el| {
+        if let Some(own) = el.get_attribute(OWN_STYLE_ATTRIBUTE) {
+            el.remove_attribute(OWN_STYLE_ATTRIBUTE);
+            add_declarations(el, &own)?;
+        }
+        Ok(())
+    }));
+
+    rewrite_str(
+        html,
+        RewriteStrSettings {
+            element_content_handlers: handlers,
+            ..RewriteStrSettings::new()
+        },
+    )
+    .unwrap_or_else(|e| {
+        tracing::warn!(
+        error.message = %e,
+        "Failed to inline the layout's CSS. Sending it with the stylesheet.",
+        );
+        html.to_string()
+    })
+}
+
+fn add_declarations(
+    el: &mut Element,
+    declarations: &str,
+) -> Result<(), lol_html::errors::AttributeNameError> {
+    let declarations = declarations.trim().trim_end_matches(';').trim();
+    if declarations.is_empty() {
+        return Ok(());
+    }
+    let style = match el.get_attribute("style") {
+        Some(style) if !style.trim().is_empty() => {
+            format!("{}; {declarations}", style.trim().trim_end_matches(';'))
+        }
+        _ => declarations.to_string(),
+    };
+    el.set_attribute("style", &style)
+}
+
+// Splits a stylesheet into (selector, declarations) pairs that can be inlined and the CSS
+// that has to stay in a <style> block.
+fn split_stylesheet(css: &str) -> (Vec<(Selector, String)>, String) {
+    let css = strip_comments(css);
+    let mut rules = vec![];
+    let mut kept = String::new();
+    let mut rest = css.trim_start();
+    while !rest.is_empty() {
+        let open = rest.find('{').unwrap_or(rest.len());
+        // Statements such as `@import url(...);` have no block.
+        if rest.starts_with('@')
+            && let Some(end) = rest[..open].find(';')
+        {
+            kept.push_str(&rest[..=end]);
+            kept.push('\n');
+            rest = rest[end + 1..].trim_start();
+            continue;
+        }
+        if open == rest.len() {
+            break;
+        }
+        let close = block_end(rest, open);
+        let prelude = rest[..open].trim();
+        let block = &rest[open + 1..close];
+        if prelude.starts_with('@') {
+            kept.push_str(&rest[..close.min(rest.len() - 1) + 1]);
+            kept.push('\n');
+        } else {
+            let declarations = block.trim();
+            for selector in prelude.split(',').map(str::trim) {
+                match selector.parse::<Selector>() {
+                    Ok(parsed) => rules.push((parsed, declarations.to_string())),
+                    Err(_) => kept.push_str(&format!("{selector} {{ {declarations} }}\n")),
+                }
+            }
+        }
+        rest = rest.get(close + 1..).unwrap_or_default().trim_start();
+    }
+    (rules, kept)
+}
+
+// The index of the `}` closing the block opened at `open`, or the end of `css`.
+fn block_end(css: &str, open: usize) -> usize {
+    let mut depth = 0;
+    for (i, c) in css[open..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return open + i;
+                }
+            }
+            _ => {}
+        }
+    }
+    css.len()
+}
+
+fn strip_comments(css: &str) -> String {
+    let mut stripped = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(start) = rest.find("/*") {
+        stripped.push_str(&rest[..start]);
+        rest = match rest[start + 2..].find("*/") {
+            Some(end) => &rest[start + 2 + end + 2..],
+            None => "",
+        };
+    }
+    stripped.push_str(rest);
+    stripped
+}
+
+#[tracing::instrument(name = "Getting the email layouts", skip(executor))]
+pub async fn get_email_layouts(
+    executor: impl PgExecutor<'_>,
+) -> Result<Vec<EmailLayout>, anyhow::Error> {
+    let layouts = sqlx::query_as!(
+        EmailLayout,
+        r#"
+        SELECT email_layout_id AS id, name, template, updated_at
+        FROM email_layouts
+        ORDER BY name
+        "#
+    )
+    .fetch_all(executor)
+    .await
+    .context("Failed to read the email layouts.")?;
+    Ok(layouts)
+}
+
+#[tracing::instrument(name = "Getting an email layout", skip(executor))]
+pub async fn get_email_layout(
+    executor: impl PgExecutor<'_>,
+    email_layout_id: Uuid,
+) -> Result<Option<EmailLayout>, anyhow::Error> {
+    let layout = sqlx::query_as!(
+        EmailLayout,
+        r#"
+        SELECT email_layout_id AS id, name, template, updated_at
+        FROM email_layouts
+        WHERE email_layout_id = $1
+        "#,
+        email_layout_id
+    )
+    .fetch_optional(executor)
+    .await
+    .context("Failed to read an email layout.")?;
+    Ok(layout)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{DEFAULT_LAYOUT, apply_layout, inline_css, validate_layout};
+    use crate::issue_rendering::{RecipientContext, RenderedIssue};
+
+    const LAYOUT: &str = r#"<html><head><title>{{ title }}</title></head><body><div class="header">Hi {{ subscriber.name }}</div>{{ content | safe }}<a href="{{ unsubscribe_url }}">Unsubscribe</a></body></html>"#;
+
+    fn issue() -> RenderedIssue {
+        RenderedIssue {
+            html_content: "<p>News</p>".into(),
+            text_content: "News\n".into(),
+        }
+    }
+
+    fn recipient() -> RecipientContext {
+        RecipientContext {
+            subscriber_id: None,
+            name: "Ursula <3".into(),
+            email: "ursula@example.com".into(),
+            attributes: serde_json::json!({}),
+        }
+    }
+
+    #[test]
+    fn the_issue_is_wrapped_in_the_layout() {
+        let rendered = apply_layout(
+            LAYOUT,
+            "Issue <1>",
+            issue(),
+            &recipient(),
+            "https://example.com/unsubscribe?t=abc",
+        )
+        .unwrap();
+
+        assert!(
+            rendered
+                .html_content
+                .contains("<title>Issue &lt;1&gt;</title>")
+        );
+        assert!(
+            rendered
+                .html_content
+                .contains("Hi Ursula &lt;3</div><p>News</p>")
+        );
+        assert!(
+            rendered
+                .html_content
+                .contains(r#"href="https:&#x2F;&#x2F;example.com&#x2F;unsubscribe?t=abc""#),
+            "{}",
+            rendered.html_content
+        );
+        assert_eq!(
+            rendered.text_content,
+            "News\n\n--\nUnsubscribe: https://example.com/unsubscribe?t=abc\n"
+        );
+    }
+
+    #[test]
+    fn a_broken_layout_falls_back_to_the_default_one() {
+        let rendered = apply_layout(
+            "{{ subscriber.attributes.city }}",
+            "Issue",
+            issue(),
+            &recipient(),
+            "https://example.com/unsubscribe",
+        )
+        .unwrap();
+
+        assert!(rendered.html_content.contains("<p>News</p>"));
+        assert!(
+            rendered
+                .html_content
+                .contains(r#"href="https:&#x2F;&#x2F;example.com&#x2F;unsubscribe""#),
+            "{}",
+            rendered.html_content
+        );
+    }
+
+    #[test]
+    fn layouts_cannot_read_the_environment() {
+        let layout = r#"{{ get_env(name="PATH") }}{{ content | safe }}{{ unsubscribe_url }}"#;
+        assert!(validate_layout(layout).is_err());
+    }
+
+    #[test]
+    fn the_default_layout_is_valid() {
+        assert_eq!(validate_layout(DEFAULT_LAYOUT), Ok(()));
+    }
+
+    #[test]
+    fn layouts_need_the_content_and_an_unsubscribe_link() {
+        assert!(validate_layout(LAYOUT).is_ok());
+        assert!(validate_layout("{% if %}").is_err());
+        assert!(validate_layout(r#"{{ content }}<a href="{{ unsubscribe_url }}">x</a>"#).is_err());
+        assert!(validate_layout("{{ content | safe }}").is_err());
+    }
+
+    #[test]
+    fn stylesheet_rules_are_inlined() {
+        let html = inline_css(
+            r#"<html><head><style>
+            /* brand */
+            p { color: #222; margin: 0 }
+            .footer a, h1 { color: #777; }
+            </style></head><body><h1>Hi</h1><p style="color: red">One</p><div class="footer"><a href="/">x</a></div></body></html>"#,
+        );
+
+        assert!(!html.contains("<style>"), "{html}");
+        assert!(html.contains(r#"<h1 style="color: #777">"#), "{html}");
+        assert!(
+            html.contains(r#"<p style="color: #222; margin: 0; color: red">"#),
+            "{html}"
+        );
+        assert!(
+            html.contains(r#"<a href="/" style="color: #777">"#),
+            "{html}"
+        );
+        assert!(!html.contains("data-z2p-own-style"));
+    }
+
+    #[test]
+    fn rules_that_cannot_be_inlined_are_kept() {
+        let html = inline_css(
+            r#"<style>p { color: #222 } a:hover { color: red } @media (max-width: 600px) { p { font-size: 14px } }</style><p>One</p>"#,
+        );
+
+        assert!(html.contains(r#"<p style="color: #222">"#), "{html}");
+        assert!(html.contains("a:hover { color: red }"), "{html}");
+        assert!(
+            html.contains("@media (max-width: 600px) { p { font-size: 14px } }"),
+            "{html}"
+        );
+    }
+
+    #[test]
+    fn html_without_a_stylesheet_is_unchanged() {
+        let html = r#"<p style="color: red">One</p>"#;
+        assert_eq!(inline_css(html), html);
+    }
+}
@@ -0,0 +1,176 @@
+use crate::{
+    domain::SubscriberEmail, email_client::EmailClient, issue_delivery_worker::ExecutionOutcome,
+};
+use sqlx::{PgExecutor, PgPool, Postgres, Transaction};
+use tracing::{Span, field::display};
+use uuid::Uuid;
+
+const MAX_RETRIES: i16 = 5;
+const BASE_BACKOFF_SECONDS: f64 = 30.0;
+
+#[tracing::instrument(skip(executor, html_content, text_content))]
+pub async fn enqueue_email(
+    executor: impl PgExecutor<'_>,
+    recipient: &SubscriberEmail,
+    subject: &str,
+    html_content: &str,
+    text_content: &str,
+) -> Result<Uuid, sqlx::Error> {
+    let email_id = Uuid::new_v4();
+    sqlx::query!(
+        r#"
+        INSERT INTO email_outbox (
+            email_id,
+            recipient,
+            subject,
+            html_content,
+            text_content
+        )
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        email_id,
+        recipient.as_ref(),
+        subject,
+        html_content,
+        text_content
+    )
+    .execute(executor)
+    .await?;
+    Ok(email_id)
+}
+
+#[tracing::instrument(
+    skip_all,
+    fields(
+        email_id=tracing::field::Empty,
+        recipient=tracing::field::Empty
+    ),
+    err
+)]
+pub async fn try_send_outbox_email(
+    pool: &PgPool,
+    email_client: &EmailClient,
+) -> Result<ExecutionOutcome, anyhow::Error> {
+    let Some((transaction, email)) = dequeue_email(pool).await? else {
+        return Ok(ExecutionOutcome::EmptyQueue);
+    };
+    Span::current()
+        .record("email_id", display(email.email_id))
+        .record("recipient", display(&email.recipient));
+
+    let recipient = match SubscriberEmail::parse(email.recipient.clone()) {
+        Ok(recipient) => recipient,
+        Err(e) => {
+            tracing::error!(
+            error.message = %e,
+            "Dropping an outbox email. Its recipient address is invalid",
+            );
+            delete_email(transaction, email.email_id).await?;
+            return Ok(ExecutionOutcome::TaskCompleted);
+        }
+    };
+
+    match email_client
+        .send_email(
+            vec![&recipient],
+            &email.subject,
+            &email.html_content,
+            &email.text_content,
+        )
+        .await
+    {
+        Ok(()) => delete_email(transaction, email.email_id).await?,
+        Err(e) if email.n_retries + 1 >= MAX_RETRIES => {
+            tracing::error!(
+            error.cause_chain = ?e,
+            error.message = %e,
+            "Failed to deliver an outbox email after {} attempts. Giving up.",
+            MAX_RETRIES
+            );
+            delete_email(transaction, email.email_id).await?;
+        }
+        Err(e) => {
+            tracing::warn!(
+            error.cause_chain = ?e,
+            error.message = %e,
+            "Failed to deliver an outbox email. It will be retried.",
+            );
+            schedule_retry(transaction, email.email_id, email.n_retries + 1).await?;
+        }
+    }
+
+    Ok(ExecutionOutcome::TaskCompleted)
+}
+
+type PgTransaction = Transaction<'static, Postgres>;
+
+struct OutboxEmail {
+    email_id: Uuid,
+    recipient: String,
+    subject: String,
+    html_content: String,
+    text_content: String,
+    n_retries: i16,
+}
+
+#[tracing::instrument(skip_all)]
+async fn dequeue_email(
+    pool: &PgPool,
+) -> Result<Option<(PgTransaction, OutboxEmail)>, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    let email = sqlx::query_as!(
+        OutboxEmail,
+        r#"
+        SELECT email_id, recipient, subject, html_content, text_content, n_retries
+        FROM email_outbox
+        WHERE execute_after <= now()
+        ORDER BY execute_after
+        FOR UPDATE
+        SKIP LOCKED
+        LIMIT 1
+        "#,
+    )
+    .fetch_optional(&mut *transaction)
+    .await?;
+    Ok(email.map(|email| (transaction, email)))
+}
+
+#[tracing::instrument(skip_all)]
+async fn delete_email(mut transaction: PgTransaction, email_id: Uuid) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM email_outbox
+        WHERE email_id = $1
+        "#,
+        email_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+    transaction.commit().await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+async fn schedule_retry(
+    mut transaction: PgTransaction,
+    email_id: Uuid,
+    n_retries: i16,
+) -> Result<(), anyhow::Error> {
+    let backoff_seconds = BASE_BACKOFF_SECONDS * 2f64.powi(n_retries.into());
+    sqlx::query!(
+        r#"
+        UPDATE email_outbox
+        SET
+            n_retries = $2,
+            execute_after = now() + make_interval(secs => $3)
+        WHERE email_id = $1
+        "#,
+        email_id,
+        n_retries,
+        backoff_seconds
+    )
+    .execute(&mut *transaction)
+    .await?;
+    transaction.commit().await?;
+    Ok(())
+}
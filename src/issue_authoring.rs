@@ -0,0 +1,343 @@
+use std::collections::HashSet;
+
+use once_cell::sync::Lazy;
+use pulldown_cmark::{Options, Parser, html};
+use uuid::Uuid;
+
+use crate::{issue_rendering::RenderedIssue, routes::render_html_template};
+
+const TEXT_WIDTH: usize = 78;
+
+/// What gets stored for an issue or a draft.
+#[derive(Debug)]
+pub struct IssueContent {
+    pub title: String,
+    pub html_content: String,
+    pub text_content: String,
+    /// Kept so the issue can be edited again, the bodies above are generated from it.
+    pub markdown_content: Option<String>,
+    /// Wraps the issue at send time, see `email_layouts`.
+    pub email_layout_id: Option<Uuid>,
+}
+
+impl IssueContent {
+    /// Markdown, when there is any, takes the place of the HTML and text bodies. Submitted
+    /// HTML is sanitised and an empty text body is derived from it. Markdown only gets the
+    /// built-in layout when no email layout has been picked.
+    pub fn new(
+        title: String,
+        html_content: String,
+        text_content: String,
+        markdown: String,
+        email_layout_id: Option<Uuid>,
+    ) -> Self {
+        if !markdown.trim().is_empty() {
+            let rendered = match email_layout_id {
+                Some(_) => render_markdown_body(&markdown),
+                None => render_markdown(&title, &markdown),
+            };
+            return Self {
+                title,
+                html_content: rendered.html_content,
+                text_content: rendered.text_content,
+                markdown_content: Some(markdown),
+                email_layout_id,
+            };
+        }
+        let html_content = sanitize_html(&html_content);
+        let text_content = match text_content.trim() {
+            "" => html_to_text(&html_content),
+            _ => text_content,
+        };
+        Self {
+            title,
+            html_content,
+            text_content,
+            markdown_content: None,
+            email_layout_id,
+        }
+    }
+}
+
+/// Turns an issue written in Markdown into the HTML and text bodies we send. Personalisation
+/// tags such as `{{ subscriber.name }}` are passed through for the per-recipient rendering.
+pub fn render_markdown(title: &str, markdown: &str) -> RenderedIssue {
+    let body = render_markdown_body(markdown);
+
+    let mut ctx = tera::Context::new();
+    ctx.insert("title", title);
+    ctx.insert("content", &body.html_content);
+    RenderedIssue {
+        html_content: render_html_template(&ctx, "email_layout.html"),
+        text_content: body.text_content,
+    }
+}
+
+/// The bare HTML and text bodies, for issues that are wrapped in an email layout at send time.
+pub fn render_markdown_body(markdown: &str) -> RenderedIssue {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH;
+    let mut body = String::new();
+    html::push_html(&mut body, Parser::new_ext(markdown, options));
+    // Markdown lets raw HTML through, it gets the same treatment as submitted HTML.
+    let body = sanitize_html(&body);
+    RenderedIssue {
+        text_content: html_to_text(&body),
+        html_content: body,
+    }
+}
+
+// Roughly what mail clients render reliably: layout tables, inline styles and plain markup.
+static EMAIL_SAFE_HTML: Lazy<ammonia::Builder<'static>> = Lazy::new(|| {
+    let mut builder = ammonia::Builder::empty();
+    builder
+        .add_tags([
+            "a",
+            "b",
+            "blockquote",
+            "br",
+            "caption",
+            "center",
+            "code",
+            "col",
+            "colgroup",
+            "dd",
+            "del",
+            "div",
+            "dl",
+            "dt",
+            "em",
+            "h1",
+            "h2",
+            "h3",
+            "h4",
+            "h5",
+            "h6",
+            "hr",
+            "i",
+            "img",
+            "ins",
+            "li",
+            "ol",
+            "p",
+            "pre",
+            "s",
+            "small",
+            "span",
+            "strike",
+            "strong",
+            "sub",
+            "sup",
+            "table",
+            "tbody",
+            "td",
+            "tfoot",
+            "th",
+            "thead",
+            "tr",
+            "u",
+            "ul",
+        ])
+        .clean_content_tags(HashSet::from(["script", "style", "title"]))
+        .add_generic_attributes(["align", "dir", "lang", "style", "title"])
+        .add_tag_attributes("a", ["href"])
+        .add_tag_attributes("img", ["alt", "height", "src", "width"])
+        .add_tag_attributes(
+            "table",
+            [
+                "bgcolor",
+                "border",
+                "cellpadding",
+                "cellspacing",
+                "role",
+                "width",
+            ],
+        )
+        .add_tag_attributes(
+            "td",
+            ["bgcolor", "colspan", "height", "rowspan", "valign", "width"],
+        )
+        .add_tag_attributes(
+            "th",
+            ["bgcolor", "colspan", "rowspan", "scope", "valign", "width"],
+        )
+        .add_tag_attributes("tr", ["bgcolor", "valign"])
+        .add_tag_attributes("ol", ["start"])
+        .url_schemes(HashSet::from(["http", "https", "mailto", "tel"]))
+        .filter_style_properties(HashSet::from([
+            "background",
+            "background-color",
+            "border",
+            "border-bottom",
+            "border-collapse",
+            "border-color",
+            "border-left",
+            "border-radius",
+            "border-right",
+            "border-top",
+            "color",
+            "display",
+            "font",
+            "font-family",
+            "font-size",
+            "font-style",
+            "font-weight",
+            "height",
+            "letter-spacing",
+            "line-height",
+            "list-style",
+            "margin",
+            "margin-bottom",
+            "margin-left",
+            "margin-right",
+            "margin-top",
+            "max-width",
+            "padding",
+            "padding-bottom",
+            "padding-left",
+            "padding-right",
+            "padding-top",
+            "text-align",
+            "text-decoration",
+            "text-transform",
+            "vertical-align",
+            "width",
+            "white-space",
+        ]));
+    builder
+});
+
+/// Strips everything that could run script or is unlikely to survive a mail client.
+pub fn sanitize_html(html: &str) -> String {
+    EMAIL_SAFE_HTML.clean(html).to_string()
+}
+
+/// The plain-text alternative for an HTML body.
+pub fn html_to_text(html: &str) -> String {
+    html2text::from_read(html.as_bytes(), TEXT_WIDTH).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use uuid::Uuid;
+
+    use super::{IssueContent, render_markdown, sanitize_html};
+
+    #[test]
+    fn markdown_replaces_the_html_and_text_bodies() {
+        let content = IssueContent::new(
+            "Issue".into(),
+            "<p>Stale</p>".into(),
+            "Stale".into(),
+            "Fresh".into(),
+            None,
+        );
+        assert!(content.html_content.contains("<p>Fresh</p>"));
+        assert_eq!(content.text_content.trim(), "Fresh");
+        assert_eq!(content.markdown_content.as_deref(), Some("Fresh"));
+
+        let content = IssueContent::new(
+            "Issue".into(),
+            "<p>Kept</p>".into(),
+            "Kept".into(),
+            " ".into(),
+            None,
+        );
+        assert_eq!(content.html_content, "<p>Kept</p>");
+        assert_eq!(content.markdown_content, None);
+    }
+
+    #[test]
+    fn markdown_for_an_email_layout_is_left_bare() {
+        let content = IssueContent::new(
+            "Issue".into(),
+            "".into(),
+            "".into(),
+            "Hi *there*".into(),
+            Some(Uuid::new_v4()),
+        );
+        assert_eq!(content.html_content.trim(), "<p>Hi <em>there</em></p>");
+    }
+
+    #[test]
+    fn markdown_is_wrapped_in_the_email_layout() {
+        let rendered = render_markdown("Issue <1>", "# Hello\n\nSome *news*.");
+
+        assert!(
+            rendered
+                .html_content
+                .contains("<title>Issue &lt;1&gt;</title>")
+        );
+        assert!(rendered.html_content.contains("<h1>Hello</h1>"));
+        assert!(rendered.html_content.contains("<em>news</em>"));
+    }
+
+    #[test]
+    fn text_version_is_readable() {
+        let rendered = render_markdown(
+            "Issue",
+            "# Hello\n\nRead [the post](https://example.com/post).\n\n- one\n- two",
+        );
+
+        assert!(rendered.text_content.contains("Hello"));
+        assert!(rendered.text_content.contains("https://example.com/post"));
+        assert!(rendered.text_content.contains("one"));
+        assert!(!rendered.text_content.contains('<'));
+    }
+
+    #[test]
+    fn personalisation_tags_survive() {
+        let rendered = render_markdown(
+            "Issue",
+            r#"Hi {{ subscriber.attributes.role | default(value="reader") }}"#,
+        );
+
+        assert!(
+            rendered
+                .html_content
+                .contains(r#"{{ subscriber.attributes.role | default(value="reader") }}"#)
+        );
+        assert!(
+            rendered
+                .text_content
+                .contains("{{ subscriber.attributes.role")
+        );
+    }
+
+    #[test]
+    fn scripts_and_handlers_are_removed() {
+        let html = sanitize_html(
+            r#"<p onclick="steal()">Hi<script>steal()</script></p><a href="javascript:steal()">x</a><iframe src="https://example.com"></iframe>"#,
+        );
+
+        assert!(!html.contains("steal"));
+        assert!(!html.contains("iframe"));
+        assert!(html.contains("<p>Hi</p>"));
+    }
+
+    #[test]
+    fn email_layout_markup_is_kept() {
+        let html = sanitize_html(
+            r#"<table width="600" cellpadding="0"><tr><td style="color: #222; position: fixed">Hi {{ subscriber.name }}</td></tr></table><a href="{{ unsubscribe_url }}">Bye</a>"#,
+        );
+
+        assert!(html.contains(r#"<table width="600" cellpadding="0">"#));
+        assert!(html.contains(r#"<td style="color:#222">"#), "{html}");
+        assert!(html.contains("Hi {{ subscriber.name }}"));
+        assert!(html.contains(r#"href="{{ unsubscribe_url }}""#));
+    }
+
+    #[test]
+    fn an_empty_text_body_is_derived_from_the_html() {
+        let content = IssueContent::new(
+            "Issue".into(),
+            "<h1>News</h1><p>Read <a href=\"https://example.com\">this</a>.</p>".into(),
+            " ".into(),
+            "".into(),
+            None,
+        );
+
+        assert!(content.text_content.contains("News"));
+        assert!(content.text_content.contains("https://example.com"));
+        assert!(!content.text_content.contains('<'));
+    }
+}
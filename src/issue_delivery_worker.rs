@@ -1,9 +1,18 @@
 use crate::{
-    configuration::Settings, domain::SubscriberEmail, email_client::EmailClient,
-    startup::get_connection_pull,
+    configuration::{ConfirmationReminderSettings, Settings},
+    confirmation_reminders::{purge_unconfirmed_subscribers, try_send_confirmation_reminder},
+    domain::SubscriberEmail,
+    drip_sequences::try_enqueue_due_drip_email,
+    email_client::EmailClient,
+    email_layouts::lay_out_for_recipient,
+    email_outbox::try_send_outbox_email,
+    issue_rendering::{get_recipient_context, render_for_recipient},
+    startup::{ApplicationBaseURL, get_connection_pull},
+    subscriber_imports::try_import_subscriber_batch,
 };
 use sqlx::{PgPool, Postgres, Transaction};
 use std::time::Duration;
+use tokio::time::Instant;
 use tracing::{Span, field::display};
 use uuid::Uuid;
 
@@ -23,33 +32,50 @@ pub enum ExecutionOutcome {
 pub async fn try_execute_task(
     pool: &PgPool,
     email_client: &EmailClient,
+    base_url: &ApplicationBaseURL,
 ) -> Result<ExecutionOutcome, anyhow::Error> {
     let task = dequeue_task(pool).await?;
     if task.is_none() {
         return Ok(ExecutionOutcome::EmptyQueue);
     }
-    let (transaction, issue_id, email) = task.unwrap();
+    let (mut transaction, issue_id, email) = task.unwrap();
     Span::current()
         .record("newsletter_issue_id", display(issue_id))
         .record("subscriber_email", display(&email));
-    match SubscriberEmail::parse(email.clone()) {
+    let outcome = match SubscriberEmail::parse(email.clone()) {
         Ok(email) => {
             let issue = get_issue(pool, issue_id).await?;
-            if let Err(e) = email_client
+            let recipient = get_recipient_context(pool, email.as_ref()).await?;
+            let rendered =
+                render_for_recipient(&issue.html_content, &issue.text_content, &recipient);
+            let rendered = lay_out_for_recipient(
+                pool,
+                base_url,
+                issue.layout.as_deref(),
+                &issue.title,
+                rendered,
+                &recipient,
+            )
+            .await?;
+            match email_client
                 .send_email(
                     vec![&email],
                     &issue.title,
-                    &issue.html_content,
-                    &issue.text_content,
+                    &rendered.html_content,
+                    &rendered.text_content,
                 )
                 .await
             {
-                tracing::error!(
-                error.cause_chain = ?e,
-                error.message = %e,
-                "Failed to deliver issue to a confirmed subscriber. \
-                Skipping.",
-                );
+                Ok(_) => "sent",
+                Err(e) => {
+                    tracing::error!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    "Failed to deliver issue to a confirmed subscriber. \
+                    Skipping.",
+                    );
+                    "failed"
+                }
             }
         }
         Err(e) => {
@@ -59,8 +85,10 @@ pub async fn try_execute_task(
             "Skipping a confirmed subscriber. \
             Their stored contact details are invalid",
             );
+            "skipped"
         }
-    }
+    };
+    log_delivery(&mut transaction, issue_id, &email, outcome).await?;
     delete_task(transaction, issue_id, &email).await?;
 
     Ok(ExecutionOutcome::TaskCompleted)
@@ -117,10 +145,34 @@ async fn delete_task(
     Ok(())
 }
 
+#[tracing::instrument(skip(transaction))]
+async fn log_delivery(
+    transaction: &mut PgTransaction,
+    issue_id: Uuid,
+    email: &str,
+    outcome: &str,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_log (newsletter_issue_id, subscriber_email, outcome)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (newsletter_issue_id, subscriber_email) DO UPDATE
+        SET outcome = EXCLUDED.outcome, attempted_at = now()
+        "#,
+        issue_id,
+        email,
+        outcome
+    )
+    .execute(&mut **transaction)
+    .await?;
+    Ok(())
+}
+
 struct NewsletterIssue {
     title: String,
     text_content: String,
     html_content: String,
+    layout: Option<String>,
 }
 
 #[tracing::instrument(skip_all)]
@@ -128,10 +180,11 @@ async fn get_issue(pool: &PgPool, issue_id: Uuid) -> Result<NewsletterIssue, any
     let issue = sqlx::query_as!(
         NewsletterIssue,
         r#"
-        SELECT title, text_content, html_content
-        FROM newsletter_issues
+        SELECT i.title, i.text_content, i.html_content, l.template AS "layout?"
+        FROM newsletter_issues i
+        LEFT JOIN email_layouts l USING (email_layout_id)
         WHERE
-            newsletter_issue_id = $1
+            i.newsletter_issue_id = $1
         "#,
         issue_id
     )
@@ -140,16 +193,42 @@ async fn get_issue(pool: &PgPool, issue_id: Uuid) -> Result<NewsletterIssue, any
     Ok(issue)
 }
 
-async fn worker_loop(pool: PgPool, email_client: EmailClient) -> Result<(), anyhow::Error> {
+// Purging is housekeeping, it runs on its own schedule so a busy queue can't hold it off.
+const PURGE_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+async fn worker_loop(
+    pool: PgPool,
+    email_client: EmailClient,
+    base_url: ApplicationBaseURL,
+    reminders: ConfirmationReminderSettings,
+) -> Result<(), anyhow::Error> {
+    let mut next_purge = Instant::now();
     loop {
-        match try_execute_task(&pool, &email_client).await {
-            Ok(ExecutionOutcome::EmptyQueue) => {
-                tokio::time::sleep(Duration::from_secs(10)).await;
-            }
-            Err(_) => {
-                tokio::time::sleep(Duration::from_secs(1)).await;
+        if Instant::now() >= next_purge {
+            if let Err(e) = purge_unconfirmed_subscribers(&pool, &reminders).await {
+                tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to purge unconfirmed subscribers.",
+                );
             }
-            Ok(ExecutionOutcome::TaskCompleted) => {}
+            next_purge = Instant::now() + PURGE_INTERVAL;
+        }
+
+        let outcomes = [
+            try_send_confirmation_reminder(&pool, &base_url, &reminders).await,
+            try_import_subscriber_batch(&pool, &base_url).await,
+            try_enqueue_due_drip_email(&pool, &base_url).await,
+            try_send_outbox_email(&pool, &email_client).await,
+            try_execute_task(&pool, &email_client, &base_url).await,
+        ];
+        if outcomes.iter().any(Result::is_err) {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        } else if outcomes
+            .iter()
+            .all(|o| matches!(o, Ok(ExecutionOutcome::EmptyQueue)))
+        {
+            tokio::time::sleep(Duration::from_secs(10)).await;
         }
     }
 }
@@ -157,5 +236,11 @@ async fn worker_loop(pool: PgPool, email_client: EmailClient) -> Result<(), anyh
 pub async fn run_worker_until_stopped(configuration: Settings) -> Result<(), anyhow::Error> {
     let connection_pool = get_connection_pull(&configuration.database);
     let email_client = configuration.email_client.client();
-    worker_loop(connection_pool, email_client).await
+    worker_loop(
+        connection_pool,
+        email_client,
+        ApplicationBaseURL(configuration.app.base_url),
+        configuration.confirmation_reminders,
+    )
+    .await
 }
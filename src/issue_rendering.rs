@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+/// What an issue can refer to when it is rendered for one subscriber, e.g.
+/// `{{ subscriber.name }}` or `{{ subscriber.attributes.company }}`.
+#[derive(Serialize, Debug, Clone)]
+pub struct RecipientContext {
+    /// `None` for addresses that aren't on the list, e.g. reviewers getting a test send.
+    #[serde(skip)]
+    pub subscriber_id: Option<Uuid>,
+    pub name: String,
+    pub email: String,
+    pub attributes: Value,
+}
+
+impl RecipientContext {
+    pub fn anonymous(email: &str) -> Self {
+        Self {
+            subscriber_id: None,
+            name: String::new(),
+            email: email.to_string(),
+            attributes: Value::Object(Default::default()),
+        }
+    }
+}
+
+// The name `Tera::render_str` gives the template it renders.
+const ONE_OFF_TEMPLATE_NAME: &str = "__tera_one_off";
+
+// Tera's built-ins, minus `get_env`: anyone who can write a template could otherwise print the
+// server's configuration, secrets included, into every email and the public archive.
+static ADMIN_TEMPLATES: Lazy<tera::Tera> = Lazy::new(|| {
+    let mut tera = tera::Tera::default();
+    tera.register_function("get_env", |_: &HashMap<String, Value>| {
+        Err::<Value, _>("get_env is not available in templates written in the admin area.".into())
+    });
+    tera
+});
+
+/// Renders a template that was written in the admin area: issues, layouts and email templates.
+pub fn render_admin_template(
+    template: &str,
+    ctx: &tera::Context,
+    autoescape: bool,
+) -> Result<String, tera::Error> {
+    let mut tera = ADMIN_TEMPLATES.clone();
+    tera.autoescape_on(if autoescape {
+        vec![ONE_OFF_TEMPLATE_NAME]
+    } else {
+        vec![]
+    });
+    tera.render_str(template, ctx)
+}
+
+pub struct RenderedIssue {
+    pub html_content: String,
+    pub text_content: String,
+}
+
+pub fn render_for_recipient(
+    html_content: &str,
+    text_content: &str,
+    recipient: &RecipientContext,
+) -> RenderedIssue {
+    let mut ctx = tera::Context::new();
+    ctx.insert("subscriber", recipient);
+    RenderedIssue {
+        html_content: render_or_fallback(html_content, &ctx, true),
+        text_content: render_or_fallback(text_content, &ctx, false),
+    }
+}
+
+// Content that isn't a valid template (e.g. a stray `{{` in a code sample) goes out as written.
+fn render_or_fallback(content: &str, ctx: &tera::Context, autoescape: bool) -> String {
+    match render_admin_template(content, ctx, autoescape) {
+        Ok(rendered) => rendered,
+        Err(e) => {
+            tracing::warn!(
+            error.cause_chain = ?e,
+            error.message = %e,
+            "Failed to personalise issue content. Sending it unchanged.",
+            );
+            content.to_string()
+        }
+    }
+}
+
+#[tracing::instrument(name = "Loading the recipient context", skip(executor))]
+pub async fn get_recipient_context(
+    executor: impl PgExecutor<'_>,
+    email: &str,
+) -> Result<RecipientContext, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT id, name, email, attributes
+        FROM subscriptions
+        WHERE lower(email) = lower($1)
+        "#,
+        email
+    )
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(match row {
+        Some(r) => RecipientContext {
+            subscriber_id: Some(r.id),
+            name: r.name,
+            email: r.email,
+            attributes: r.attributes,
+        },
+        None => RecipientContext::anonymous(email),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{RecipientContext, render_admin_template, render_for_recipient};
+
+    fn recipient() -> RecipientContext {
+        RecipientContext {
+            subscriber_id: None,
+            name: "Ursula <3".into(),
+            email: "ursula@example.com".into(),
+            attributes: serde_json::json!({ "company": "Acme" }),
+        }
+    }
+
+    #[test]
+    fn subscriber_fields_and_attributes_are_substituted() {
+        let rendered = render_for_recipient(
+            "<p>Hi {{ subscriber.name }} from {{ subscriber.attributes.company }}</p>",
+            "Hi {{ subscriber.name }}",
+            &recipient(),
+        );
+
+        assert_eq!(rendered.html_content, "<p>Hi Ursula &lt;3 from Acme</p>");
+        assert_eq!(rendered.text_content, "Hi Ursula <3");
+    }
+
+    #[test]
+    fn missing_attributes_can_have_a_default() {
+        let rendered = render_for_recipient(
+            r#"{{ subscriber.attributes.role | default(value="reader") }}"#,
+            "",
+            &recipient(),
+        );
+        assert_eq!(rendered.html_content, "reader");
+    }
+
+    #[test]
+    fn templates_cannot_read_the_environment() {
+        let template = r#"{{ get_env(name="PATH") }}"#;
+        let path = std::env::var("PATH").unwrap();
+
+        assert!(render_admin_template(template, &tera::Context::new(), true).is_err());
+        let rendered = render_for_recipient(template, template, &recipient());
+        assert!(!rendered.html_content.contains(&path));
+        assert!(!rendered.text_content.contains(&path));
+    }
+
+    #[test]
+    fn invalid_templates_are_sent_unchanged() {
+        let rendered = render_for_recipient("fn main() {{ }", "{% oops", &recipient());
+        assert_eq!(rendered.html_content, "fn main() {{ }");
+        assert_eq!(rendered.text_content, "{% oops");
+    }
+}
@@ -1,10 +1,20 @@
 pub mod authentication;
+pub mod bot_protection;
 pub mod configuration;
+pub mod confirmation_reminders;
 pub mod domain;
+pub mod drip_sequences;
 pub mod email_client;
+pub mod email_layouts;
+pub mod email_outbox;
 pub mod idempotency;
+pub mod issue_authoring;
 pub mod issue_delivery_worker;
+pub mod issue_rendering;
+pub mod rate_limiter;
 pub mod routes;
 pub mod session_state;
 pub mod startup;
+pub mod subscriber_imports;
 pub mod telemetry;
+pub mod transactional_emails;
@@ -0,0 +1,222 @@
+use std::{net::IpAddr, time::Duration};
+
+use actix_web::{HttpRequest, http::header::HeaderName};
+use anyhow::Context;
+use redis::aio::ConnectionManager;
+use secrecy::{ExposeSecret, SecretString};
+
+use crate::{configuration::RateLimitSettings, domain::SubscriberEmail};
+
+#[derive(serde::Deserialize, Debug, Clone, Copy)]
+pub struct RateLimit {
+    pub max_requests: u64,
+    pub window_secs: u64,
+}
+
+pub enum RateLimitDecision {
+    Allowed,
+    Throttled { retry_after: Duration },
+}
+
+#[derive(Clone)]
+pub struct RateLimiter {
+    connection: ConnectionManager,
+    settings: RateLimitSettings,
+}
+
+impl RateLimiter {
+    pub async fn new(
+        redis_uri: &SecretString,
+        settings: RateLimitSettings,
+    ) -> Result<Self, anyhow::Error> {
+        let client = redis::Client::open(redis_uri.expose_secret())
+            .context("Failed to parse the Redis connection string.")?;
+        let connection = ConnectionManager::new(client)
+            .await
+            .context("Failed to connect to Redis.")?;
+        Ok(Self {
+            connection,
+            settings,
+        })
+    }
+
+    /// The address to limit a request by. `X-Forwarded-For` is only read when the connection
+    /// comes from one of the configured proxies, anyone else could send it to dodge the limit.
+    pub fn client_ip(&self, req: &HttpRequest) -> Option<String> {
+        let forwarded_for = req
+            .headers()
+            .get_all(HeaderName::from_static("x-forwarded-for"))
+            .filter_map(|value| value.to_str().ok())
+            .collect::<Vec<_>>()
+            .join(",");
+        resolve_client_ip(
+            req.peer_addr().map(|addr| addr.ip()),
+            &forwarded_for,
+            &self.settings.trusted_proxies,
+        )
+        .map(|ip| ip.to_string())
+    }
+
+    pub async fn check_subscribe_ip(&self, ip: &str) -> Result<RateLimitDecision, anyhow::Error> {
+        self.check("subscribe:ip", ip, self.settings.subscribe_per_ip)
+            .await
+    }
+
+    pub async fn check_subscribe_email(
+        &self,
+        email: &SubscriberEmail,
+    ) -> Result<RateLimitDecision, anyhow::Error> {
+        self.check(
+            "subscribe:email",
+            &email.as_ref().to_lowercase(),
+            self.settings.subscribe_per_email,
+        )
+        .await
+    }
+
+    pub async fn check_data_request_email(
+        &self,
+        email: &SubscriberEmail,
+    ) -> Result<RateLimitDecision, anyhow::Error> {
+        self.check(
+            "data_request:email",
+            &email.as_ref().to_lowercase(),
+            self.settings.data_request_per_email,
+        )
+        .await
+    }
+
+    pub async fn check_password_reset(
+        &self,
+        username: &str,
+    ) -> Result<RateLimitDecision, anyhow::Error> {
+        self.check(
+            "password_reset:user",
+            username,
+            self.settings.password_reset_per_user,
+        )
+        .await
+    }
+
+    /// Marks a form challenge as used. Returns `false` if it already was, i.e. the form token
+    /// is being replayed.
+    #[tracing::instrument(name = "Claiming a form challenge", skip(self))]
+    pub async fn claim_form_challenge(
+        &self,
+        challenge: &str,
+        expires_in_secs: u64,
+    ) -> Result<bool, anyhow::Error> {
+        let key = format!("{}:form_challenge:{challenge}", self.settings.key_prefix);
+        let mut connection = self.connection.clone();
+        let claimed: Option<String> = redis::cmd("SET")
+            .arg(&key)
+            .arg(1)
+            .arg("NX")
+            .arg("EX")
+            .arg(expires_in_secs.max(1))
+            .query_async(&mut connection)
+            .await
+            .context("Failed to claim the form challenge.")?;
+        Ok(claimed.is_some())
+    }
+
+    #[tracing::instrument(name = "Checking rate limit", skip(self, limit))]
+    async fn check(
+        &self,
+        scope: &str,
+        identifier: &str,
+        limit: RateLimit,
+    ) -> Result<RateLimitDecision, anyhow::Error> {
+        let key = format!("{}:{scope}:{identifier}", self.settings.key_prefix);
+        let mut connection = self.connection.clone();
+
+        let (count, mut ttl): (u64, i64) = redis::pipe()
+            .incr(&key, 1)
+            .ttl(&key)
+            .query_async(&mut connection)
+            .await
+            .context("Failed to increment the rate limit counter.")?;
+
+        // The first hit of a window (or a counter that lost its expiry) starts a new window.
+        if ttl < 0 {
+            ttl = limit.window_secs as i64;
+            let _: bool = redis::cmd("EXPIRE")
+                .arg(&key)
+                .arg(ttl)
+                .query_async(&mut connection)
+                .await
+                .context("Failed to set the rate limit window.")?;
+        }
+
+        if count > limit.max_requests {
+            Ok(RateLimitDecision::Throttled {
+                retry_after: Duration::from_secs(ttl.max(1) as u64),
+            })
+        } else {
+            Ok(RateLimitDecision::Allowed)
+        }
+    }
+}
+
+// Proxies append the address they got the request from, so the client is the last entry that
+// isn't one of our own proxies. Anything left of it was written by the client.
+fn resolve_client_ip(
+    peer: Option<IpAddr>,
+    forwarded_for: &str,
+    trusted_proxies: &[IpAddr],
+) -> Option<IpAddr> {
+    let peer = peer?;
+    if !trusted_proxies.contains(&peer) {
+        return Some(peer);
+    }
+    let mut client = peer;
+    for hop in forwarded_for.rsplit(',') {
+        match hop.trim().parse::<IpAddr>() {
+            Ok(ip) if trusted_proxies.contains(&ip) => client = ip,
+            Ok(ip) => return Some(ip),
+            Err(_) => break,
+        }
+    }
+    Some(client)
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::IpAddr;
+
+    use super::resolve_client_ip;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn forwarded_headers_from_untrusted_peers_are_ignored() {
+        assert_eq!(
+            resolve_client_ip(Some(ip("203.0.113.7")), "198.51.100.1", &[]),
+            Some(ip("203.0.113.7"))
+        );
+    }
+
+    #[test]
+    fn the_last_untrusted_hop_behind_a_trusted_proxy_is_the_client() {
+        let proxies = [ip("10.0.0.1"), ip("10.0.0.2")];
+        assert_eq!(
+            resolve_client_ip(
+                Some(ip("10.0.0.1")),
+                "1.1.1.1, 203.0.113.7, 10.0.0.2",
+                &proxies
+            ),
+            Some(ip("203.0.113.7"))
+        );
+        // Without a usable header, the proxy itself is all there is to go on.
+        assert_eq!(
+            resolve_client_ip(Some(ip("10.0.0.1")), "", &proxies),
+            Some(ip("10.0.0.1"))
+        );
+        assert_eq!(
+            resolve_client_ip(Some(ip("10.0.0.1")), "garbage", &proxies),
+            Some(ip("10.0.0.1"))
+        );
+    }
+}
@@ -1,7 +1,11 @@
-use crate::{authentication::UserId, routes::helpers::e500};
+use crate::{
+    authentication::UserId,
+    routes::helpers::{e500, get_message},
+};
 
 use super::super::helpers::prepare_html_template;
-use actix_web::{HttpResponse, http::header::ContentType, web};
+use actix_web::{HttpResponse, cookie::Cookie, http::header::ContentType, web};
+use actix_web_flash_messages::IncomingFlashMessages;
 use anyhow::Context;
 use sqlx::PgPool;
 use uuid::Uuid;
@@ -9,13 +13,22 @@ use uuid::Uuid;
 pub async fn admin_dashboard(
     pool: web::Data<PgPool>,
     user_id: web::ReqData<UserId>,
+    flash_messages: IncomingFlashMessages,
 ) -> Result<HttpResponse, actix_web::Error> {
     let username = get_username(**user_id, &pool).await.map_err(e500)?;
+    let message_string = get_message(flash_messages, None);
 
-    let page_string = prepare_html_template(&[("username", &username)], "dashboard.html");
-    Ok(HttpResponse::Ok()
+    let page_string = prepare_html_template(
+        &[("username", &username), ("message", &message_string)],
+        "dashboard.html",
+    );
+    let mut response = HttpResponse::Ok()
         .content_type(ContentType::html())
-        .body(page_string))
+        .body(page_string);
+    response
+        .add_removal_cookie(&Cookie::new("_flash", ""))
+        .unwrap();
+    Ok(response)
 }
 
 #[tracing::instrument(name = "Get username", skip(pool))]
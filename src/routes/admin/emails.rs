@@ -0,0 +1,188 @@
+use actix_web::{
+    HttpResponse,
+    cookie::Cookie,
+    http::header::{CONTENT_SECURITY_POLICY, ContentType},
+    web,
+};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use crate::{
+    routes::helpers::{e500, get_message, render_html_template, see_other},
+    startup::ApplicationBaseURL,
+    transactional_emails::{
+        EmailTemplate, RenderedEmail, TransactionalEmail, get_email_template, get_email_templates,
+        render_email, validate_email_template,
+    },
+};
+
+const EMAILS_PATH: &str = "/admin/emails";
+
+#[derive(Deserialize)]
+pub struct EmailTemplateFormData {
+    subject: String,
+    html_template: String,
+    text_template: String,
+}
+
+fn email_path(kind: TransactionalEmail) -> String {
+    format!("{EMAILS_PATH}/{}", kind.as_str())
+}
+
+/// The email as its recipient would get it, with sample values for its variables.
+fn render_sample(
+    template: &EmailTemplate,
+    base_url: &ApplicationBaseURL,
+) -> Result<RenderedEmail, tera::Error> {
+    let link = match template.kind {
+        TransactionalEmail::Confirmation | TransactionalEmail::ConfirmationReminder => {
+            format!(
+                "{}/subscriptions/confirm?subscription_token=sample",
+                base_url.0
+            )
+        }
+        TransactionalEmail::DataExport => {
+            format!("{}/subscriptions/data/export?token=sample", base_url.0)
+        }
+        TransactionalEmail::DataErasure => {
+            format!("{}/subscriptions/data/erase?token=sample", base_url.0)
+        }
+        TransactionalEmail::PasswordReset => {
+            format!("{}/login/password-reset/confirm?token=sample", base_url.0)
+        }
+    };
+    render_email(template, &template.kind.sample_variables(&link))
+}
+
+pub async fn list_email_templates(
+    db_pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let templates = get_email_templates(&**db_pool).await.map_err(e500)?;
+
+    let mut ctx = tera::Context::new();
+    ctx.insert("message", &get_message(flash_messages, None));
+    ctx.insert("templates", &templates);
+    let page = render_html_template(&ctx, "email_templates.html");
+
+    let mut response = HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(page);
+    response
+        .add_removal_cookie(&Cookie::new("_flash", ""))
+        .unwrap();
+    Ok(response)
+}
+
+pub async fn edit_email_template(
+    kind: web::Path<TransactionalEmail>,
+    db_pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseURL>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let kind = kind.into_inner();
+    let template = get_email_template(&**db_pool, kind).await.map_err(e500)?;
+    let sample = render_sample(&template, &base_url).map_err(e500)?;
+
+    let mut ctx = tera::Context::new();
+    ctx.insert("message", &get_message(flash_messages, None));
+    ctx.insert("template", &template);
+    ctx.insert("variables", kind.variables());
+    ctx.insert("sample_subject", &sample.subject);
+    ctx.insert("sample_text", &sample.text);
+    let page = render_html_template(&ctx, "email_template_edit.html");
+
+    let mut response = HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(page);
+    response
+        .add_removal_cookie(&Cookie::new("_flash", ""))
+        .unwrap();
+    Ok(response)
+}
+
+/// Emails already in the outbox keep the version they were rendered with.
+#[tracing::instrument(name = "Saving an email template", skip(form, db_pool))]
+pub async fn save_email_template(
+    kind: web::Path<TransactionalEmail>,
+    form: web::Form<EmailTemplateFormData>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let kind = kind.into_inner();
+    let EmailTemplateFormData {
+        subject,
+        html_template,
+        text_template,
+    } = form.into_inner();
+    let template = EmailTemplate {
+        subject,
+        html_template,
+        text_template,
+        ..EmailTemplate::bundled(kind)
+    };
+    if let Err(e) = validate_email_template(&template) {
+        FlashMessage::error(e).send();
+        return Ok(see_other(&email_path(kind)));
+    }
+
+    sqlx::query!(
+        r#"
+        INSERT INTO transactional_email_templates (kind, subject, html_template, text_template)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (kind) DO UPDATE
+        SET subject = EXCLUDED.subject,
+            html_template = EXCLUDED.html_template,
+            text_template = EXCLUDED.text_template,
+            updated_at = now()
+        "#,
+        kind.as_str(),
+        template.subject.trim(),
+        template.html_template,
+        template.text_template
+    )
+    .execute(&**db_pool)
+    .await
+    .context("Failed to store the email template.")
+    .map_err(e500)?;
+
+    FlashMessage::info("The email has been saved.").send();
+    Ok(see_other(&email_path(kind)))
+}
+
+/// The HTML version with sample values, sandboxed like the layout previews.
+pub async fn preview_email_template(
+    kind: web::Path<TransactionalEmail>,
+    db_pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseURL>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let template = get_email_template(&**db_pool, kind.into_inner())
+        .await
+        .map_err(e500)?;
+    let sample = render_sample(&template, &base_url).map_err(e500)?;
+
+    Ok(HttpResponse::Ok()
+        .insert_header((CONTENT_SECURITY_POLICY, "sandbox"))
+        .content_type(ContentType::html())
+        .body(sample.html))
+}
+
+#[tracing::instrument(name = "Resetting an email template", skip(db_pool))]
+pub async fn reset_email_template(
+    kind: web::Path<TransactionalEmail>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let kind = kind.into_inner();
+    sqlx::query!(
+        r#"DELETE FROM transactional_email_templates WHERE kind = $1"#,
+        kind.as_str()
+    )
+    .execute(&**db_pool)
+    .await
+    .context("Failed to reset the email template.")
+    .map_err(e500)?;
+
+    FlashMessage::info("The email is back to its default.").send();
+    Ok(see_other(&email_path(kind)))
+}
@@ -0,0 +1,282 @@
+use actix_web::{
+    HttpResponse,
+    http::header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+    web::{self, Bytes},
+};
+use chrono::{DateTime, Utc};
+use futures_util::{TryStreamExt, stream};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::{domain::SubscriptionStatus, routes::helpers::e400};
+
+// Rows are produced while the response is being written, so only this many are held in memory.
+const BUFFERED_ROWS: usize = 256;
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Json => "application/json",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    }
+
+    fn encode_row(&self, row: &impl Serialize, first: bool) -> Result<Bytes, anyhow::Error> {
+        match self {
+            ExportFormat::Csv => {
+                let mut writer = csv::WriterBuilder::new()
+                    .has_headers(first)
+                    .from_writer(Vec::new());
+                writer.serialize(row)?;
+                let serialized = writer.into_inner()?;
+
+                // Read back so each cell can be defused, the header row is ours and left alone.
+                let mut reader = csv::ReaderBuilder::new()
+                    .has_headers(false)
+                    .from_reader(serialized.as_slice());
+                let mut writer = csv::Writer::from_writer(Vec::new());
+                for (i, record) in reader.records().enumerate() {
+                    let record = record?;
+                    if first && i == 0 {
+                        writer.write_record(&record)?;
+                    } else {
+                        writer.write_record(record.iter().map(defuse_formula))?;
+                    }
+                }
+                Ok(Bytes::from(writer.into_inner()?))
+            }
+            ExportFormat::Json => {
+                let mut chunk = if first {
+                    b"[\n".to_vec()
+                } else {
+                    b",\n".to_vec()
+                };
+                serde_json::to_writer(&mut chunk, row)?;
+                Ok(Bytes::from(chunk))
+            }
+        }
+    }
+
+    fn closing(&self, empty: bool) -> Option<Bytes> {
+        match (self, empty) {
+            (ExportFormat::Csv, _) => None,
+            (ExportFormat::Json, true) => Some(Bytes::from_static(b"[]\n")),
+            (ExportFormat::Json, false) => Some(Bytes::from_static(b"\n]\n")),
+        }
+    }
+}
+
+// Spreadsheets run cells starting with these as formulas, so a subscriber called
+// `=HYPERLINK(...)` would be live in the export. A leading `'` makes them plain text.
+fn defuse_formula(cell: &str) -> String {
+    if cell.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{cell}")
+    } else {
+        cell.to_string()
+    }
+}
+
+/// Streams rows as they arrive on `rows`. A failure halfway through can only be reported by
+/// cutting the response short, the status code has already been sent.
+fn export_response<T>(
+    format: ExportFormat,
+    file_stem: &str,
+    rows: mpsc::Receiver<Result<T, anyhow::Error>>,
+) -> HttpResponse
+where
+    T: Serialize + 'static,
+{
+    let body = stream::unfold(
+        (rows, true, false),
+        move |(mut rows, first, done)| async move {
+            if done {
+                return None;
+            }
+            match rows.recv().await {
+                Some(Ok(row)) => Some((format.encode_row(&row, first), (rows, false, false))),
+                Some(Err(e)) => Some((Err(e), (rows, first, true))),
+                None => format
+                    .closing(first)
+                    .map(|chunk| (Ok(chunk), (rows, first, true))),
+            }
+        },
+    )
+    .map_err(actix_web::error::ErrorInternalServerError);
+
+    HttpResponse::Ok()
+        .insert_header((CONTENT_TYPE, format.content_type()))
+        .insert_header((
+            CONTENT_DISPOSITION,
+            format!(
+                "attachment; filename=\"{file_stem}-{}.{}\"",
+                Utc::now().format("%Y%m%d"),
+                format.extension()
+            ),
+        ))
+        .streaming(body)
+}
+
+#[derive(Deserialize)]
+pub struct SubscriberExportParameters {
+    #[serde(default)]
+    format: ExportFormat,
+    #[serde(default)]
+    status: String,
+    // The same attribute segments newsletter issues can be sent to.
+    #[serde(default)]
+    segment_attribute: String,
+    #[serde(default)]
+    segment_value: String,
+}
+
+/// Attributes are exported as JSON text, so CSV and JSON exports have the same columns.
+#[derive(Serialize)]
+struct SubscriberExportRow {
+    id: Uuid,
+    email: String,
+    name: String,
+    status: String,
+    subscribed_at: DateTime<Utc>,
+    confirmed_at: Option<DateTime<Utc>>,
+    signup_source: Option<String>,
+    referrer: Option<String>,
+    utm_source: Option<String>,
+    utm_medium: Option<String>,
+    utm_campaign: Option<String>,
+    utm_term: Option<String>,
+    utm_content: Option<String>,
+    attributes: String,
+}
+
+#[tracing::instrument(name = "Exporting subscribers", skip(parameters, db_pool))]
+pub async fn export_subscribers(
+    parameters: web::Query<SubscriberExportParameters>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let SubscriberExportParameters {
+        format,
+        status,
+        segment_attribute,
+        segment_value,
+    } = parameters.into_inner();
+    let status = match status.trim() {
+        "" => None,
+        s => Some(
+            SubscriptionStatus::try_from(s.to_string())
+                .map_err(e400)?
+                .as_str(),
+        ),
+    };
+    let segment = match segment_attribute.trim() {
+        "" => None,
+        attribute => Some((attribute.to_string(), segment_value.trim().to_string())),
+    };
+    let (segment_attribute, segment_value) = segment.unzip();
+
+    let (sender, receiver) = mpsc::channel(BUFFERED_ROWS);
+    let pool = db_pool.get_ref().clone();
+    tokio::spawn(async move {
+        let mut rows = sqlx::query_as!(
+            SubscriberExportRow,
+            r#"
+            SELECT
+                id, email, name, status, subscribed_at, confirmed_at,
+                signup_source, referrer, utm_source, utm_medium, utm_campaign, utm_term,
+                utm_content, attributes::text AS "attributes!"
+            FROM subscriptions
+            WHERE ($1::text IS NULL OR status = $1)
+                AND ($2::text IS NULL OR attributes ->> $2 = $3)
+            ORDER BY subscribed_at, id
+            "#,
+            status,
+            segment_attribute,
+            segment_value
+        )
+        .fetch(&pool)
+        .map_err(anyhow::Error::from);
+        forward_rows(&mut rows, &sender).await;
+    });
+
+    Ok(export_response(format, "subscribers", receiver))
+}
+
+#[derive(Deserialize)]
+pub struct IssueExportParameters {
+    #[serde(default)]
+    format: ExportFormat,
+}
+
+#[derive(Serialize)]
+struct IssueExportRow {
+    newsletter_issue_id: Uuid,
+    title: String,
+    published_at: String,
+    text_content: String,
+    html_content: String,
+}
+
+#[tracing::instrument(name = "Exporting newsletter issues", skip(parameters, db_pool))]
+pub async fn export_issues(
+    parameters: web::Query<IssueExportParameters>,
+    db_pool: web::Data<PgPool>,
+) -> HttpResponse {
+    let (sender, receiver) = mpsc::channel(BUFFERED_ROWS);
+    let pool = db_pool.get_ref().clone();
+    tokio::spawn(async move {
+        let mut rows = sqlx::query_as!(
+            IssueExportRow,
+            r#"
+            SELECT newsletter_issue_id, title, published_at, text_content, html_content
+            FROM newsletter_issues
+            ORDER BY published_at::timestamptz, newsletter_issue_id
+            "#
+        )
+        .fetch(&pool)
+        .map_err(anyhow::Error::from);
+        forward_rows(&mut rows, &sender).await;
+    });
+
+    export_response(parameters.format, "issues", receiver)
+}
+
+// Stops early once the client has gone away and the receiving end is dropped.
+async fn forward_rows<T>(
+    rows: &mut (impl futures_util::Stream<Item = Result<T, anyhow::Error>> + Unpin),
+    sender: &mpsc::Sender<Result<T, anyhow::Error>>,
+) {
+    loop {
+        let row = match rows.try_next().await {
+            Ok(Some(row)) => Ok(row),
+            Ok(None) => return,
+            Err(e) => {
+                tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to read a row for an export.",
+                );
+                Err(e)
+            }
+        };
+        let failed = row.is_err();
+        if sender.send(row).await.is_err() || failed {
+            return;
+        }
+    }
+}
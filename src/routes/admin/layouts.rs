@@ -0,0 +1,229 @@
+use actix_web::{
+    HttpResponse,
+    cookie::Cookie,
+    http::header::{CONTENT_SECURITY_POLICY, ContentType},
+    web,
+};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use serde::Deserialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    email_layouts::{
+        SAMPLE_CONTENT, apply_layout, get_email_layout, get_email_layouts, validate_layout,
+    },
+    issue_rendering::{RecipientContext, RenderedIssue},
+    routes::helpers::{e500, get_message, render_html_template, see_other},
+    startup::ApplicationBaseURL,
+};
+
+const LAYOUTS_PATH: &str = "/admin/layouts";
+
+#[derive(Deserialize)]
+pub struct LayoutFormData {
+    name: String,
+    template: String,
+}
+
+impl LayoutFormData {
+    fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("A layout needs a name.".into());
+        }
+        validate_layout(&self.template)
+    }
+}
+
+fn layout_path(email_layout_id: Uuid) -> String {
+    format!("{LAYOUTS_PATH}/{email_layout_id}")
+}
+
+pub async fn list_email_layouts(
+    db_pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let layouts = get_email_layouts(&**db_pool).await.map_err(e500)?;
+
+    let mut ctx = tera::Context::new();
+    ctx.insert("message", &get_message(flash_messages, None));
+    ctx.insert("layouts", &layouts);
+    let page = render_html_template(&ctx, "email_layouts.html");
+
+    let mut response = HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(page);
+    response
+        .add_removal_cookie(&Cookie::new("_flash", ""))
+        .unwrap();
+    Ok(response)
+}
+
+#[tracing::instrument(name = "Adding an email layout", skip(form, db_pool))]
+pub async fn create_email_layout(
+    form: web::Form<LayoutFormData>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if let Err(e) = form.validate() {
+        FlashMessage::error(e).send();
+        return Ok(see_other(LAYOUTS_PATH));
+    }
+
+    let email_layout_id = Uuid::new_v4();
+    let n_inserted = sqlx::query!(
+        r#"
+        INSERT INTO email_layouts (email_layout_id, name, template)
+        VALUES ($1, $2, $3)
+        ON CONFLICT DO NOTHING
+        "#,
+        email_layout_id,
+        form.name.trim(),
+        form.template
+    )
+    .execute(&**db_pool)
+    .await
+    .context("Failed to store the email layout.")
+    .map_err(e500)?
+    .rows_affected();
+
+    if n_inserted == 0 {
+        FlashMessage::error(format!("The layout {} already exists.", form.name.trim())).send();
+        return Ok(see_other(LAYOUTS_PATH));
+    }
+    FlashMessage::info(format!("The layout {} has been added.", form.name.trim())).send();
+    Ok(see_other(&layout_path(email_layout_id)))
+}
+
+pub async fn edit_email_layout(
+    email_layout_id: web::Path<Uuid>,
+    db_pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let Some(layout) = get_email_layout(&**db_pool, *email_layout_id)
+        .await
+        .map_err(e500)?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+
+    let mut ctx = tera::Context::new();
+    ctx.insert("message", &get_message(flash_messages, None));
+    ctx.insert("layout", &layout);
+    let page = render_html_template(&ctx, "email_layout_edit.html");
+
+    let mut response = HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(page);
+    response
+        .add_removal_cookie(&Cookie::new("_flash", ""))
+        .unwrap();
+    Ok(response)
+}
+
+/// Issues that use the layout and are still being delivered pick up the change.
+#[tracing::instrument(name = "Saving an email layout", skip(form, db_pool))]
+pub async fn save_email_layout(
+    email_layout_id: web::Path<Uuid>,
+    form: web::Form<LayoutFormData>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let email_layout_id = email_layout_id.into_inner();
+    if let Err(e) = form.validate() {
+        FlashMessage::error(e).send();
+        return Ok(see_other(&layout_path(email_layout_id)));
+    }
+
+    let updated = sqlx::query!(
+        r#"
+        UPDATE email_layouts
+        SET name = $2, template = $3, updated_at = now()
+        WHERE email_layout_id = $1
+        "#,
+        email_layout_id,
+        form.name.trim(),
+        form.template
+    )
+    .execute(&**db_pool)
+    .await;
+    match updated {
+        Ok(r) if r.rows_affected() == 0 => return Ok(HttpResponse::NotFound().finish()),
+        Ok(_) => {}
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            FlashMessage::error(format!("The layout {} already exists.", form.name.trim())).send();
+            return Ok(see_other(&layout_path(email_layout_id)));
+        }
+        Err(e) => return Err(e500(e)),
+    }
+
+    FlashMessage::info("The layout has been saved.").send();
+    Ok(see_other(&layout_path(email_layout_id)))
+}
+
+/// The layout around some sample content, with its CSS inlined the way it will be sent.
+pub async fn preview_email_layout(
+    email_layout_id: web::Path<Uuid>,
+    db_pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseURL>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let Some(layout) = get_email_layout(&**db_pool, *email_layout_id)
+        .await
+        .map_err(e500)?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    let sample = RenderedIssue {
+        html_content: SAMPLE_CONTENT.to_string(),
+        text_content: String::new(),
+    };
+    let rendered = apply_layout(
+        &layout.template,
+        "Sample issue",
+        sample,
+        &RecipientContext::anonymous("subscriber@example.com"),
+        &format!("{}/subscriptions/unsubscribe", base_url.0),
+    )
+    .map_err(e500)?;
+
+    Ok(HttpResponse::Ok()
+        .insert_header((CONTENT_SECURITY_POLICY, "sandbox"))
+        .content_type(ContentType::html())
+        .body(rendered.html_content))
+}
+
+/// Layouts that published issues were sent in are kept, drafts that use one lose it.
+#[tracing::instrument(name = "Removing an email layout", skip(db_pool))]
+pub async fn delete_email_layout(
+    email_layout_id: web::Path<Uuid>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let email_layout_id = email_layout_id.into_inner();
+    let in_use = sqlx::query!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM newsletter_issues WHERE email_layout_id = $1
+        ) AS "in_use!"
+        "#,
+        email_layout_id
+    )
+    .fetch_one(&**db_pool)
+    .await
+    .map_err(e500)?
+    .in_use;
+    if in_use {
+        FlashMessage::error("The layout is used by published issues and can't be removed.").send();
+        return Ok(see_other(&layout_path(email_layout_id)));
+    }
+
+    sqlx::query!(
+        r#"DELETE FROM email_layouts WHERE email_layout_id = $1"#,
+        email_layout_id
+    )
+    .execute(&**db_pool)
+    .await
+    .context("Failed to remove the email layout.")
+    .map_err(e500)?;
+
+    FlashMessage::info("The layout has been removed.").send();
+    Ok(see_other(LAYOUTS_PATH))
+}
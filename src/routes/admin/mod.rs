@@ -1,9 +1,21 @@
 mod dashboard;
+mod emails;
+mod exports;
+mod layouts;
 mod logout;
 mod newsletters;
 mod password;
+mod reports;
+mod sequences;
+mod subscribers;
 
 pub use dashboard::*;
+pub use emails::*;
+pub use exports::*;
+pub use layouts::*;
 pub use logout::*;
 pub use newsletters::*;
 pub use password::*;
+pub use reports::*;
+pub use sequences::*;
+pub use subscribers::*;
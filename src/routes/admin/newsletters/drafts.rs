@@ -0,0 +1,502 @@
+use actix_web::{
+    HttpResponse,
+    cookie::Cookie,
+    http::header::{CONTENT_SECURITY_POLICY, ContentType},
+    web,
+};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+use super::{TestSendSource, get_test_sends, publish_issue};
+use crate::{
+    authentication::UserId,
+    email_layouts::{get_email_layout, get_email_layouts, lay_out_for_recipient},
+    idempotency::{IdempotencyKey, NextAction, save_response, try_processing},
+    issue_authoring::IssueContent,
+    issue_rendering::{RecipientContext, get_recipient_context, render_for_recipient},
+    routes::helpers::{e400, e500, get_message, render_html_template, see_other},
+    startup::ApplicationBaseURL,
+};
+
+const DRAFTS_PATH: &str = "/admin/newsletters/drafts";
+
+/// Every field is optional, a draft can be saved at any point while it is being written.
+#[derive(Deserialize, Serialize, Default)]
+pub struct DraftFormData {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    html: String,
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    markdown: String,
+    #[serde(default)]
+    segment_attribute: String,
+    #[serde(default)]
+    segment_value: String,
+    #[serde(default)]
+    email_layout: String,
+}
+
+impl DraftFormData {
+    // Markdown is rendered on every save, so previews and test sends show the real thing.
+    fn content(&self) -> Result<IssueContent, actix_web::Error> {
+        let email_layout_id = match self.email_layout.trim() {
+            "" => None,
+            id => Some(id.parse().map_err(e400)?),
+        };
+        Ok(IssueContent::new(
+            self.title.clone(),
+            self.html.clone(),
+            self.text.clone(),
+            self.markdown.clone(),
+            email_layout_id,
+        ))
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PublishDraftFormData {
+    idempotency_key: String,
+}
+
+#[derive(Deserialize)]
+pub struct PreviewParameters {
+    /// Renders the draft as this subscriber would receive it.
+    #[serde(default)]
+    email: String,
+    #[serde(default)]
+    format: String,
+}
+
+#[derive(Serialize)]
+struct DraftRow {
+    id: String,
+    title: String,
+    author: String,
+    updated_at: String,
+}
+
+#[derive(Serialize)]
+struct Draft {
+    id: String,
+    title: String,
+    html: String,
+    text: String,
+    markdown: String,
+    segment_attribute: String,
+    segment_value: String,
+    email_layout_id: Option<Uuid>,
+    updated_at: String,
+}
+
+fn format_time(t: DateTime<Utc>) -> String {
+    t.format("%Y-%m-%d %H:%M").to_string()
+}
+
+pub async fn list_newsletter_drafts(
+    db_pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let drafts = sqlx::query!(
+        r#"
+        SELECT d.draft_id, d.title, d.updated_at, u.username
+        FROM newsletter_drafts d
+        JOIN users u ON u.user_id = d.created_by
+        ORDER BY d.updated_at DESC
+        "#
+    )
+    .fetch_all(&**db_pool)
+    .await
+    .context("Failed to read the newsletter drafts.")
+    .map_err(e500)?
+    .into_iter()
+    .map(|r| DraftRow {
+        id: r.draft_id.to_string(),
+        title: r.title,
+        author: r.username,
+        updated_at: format_time(r.updated_at),
+    })
+    .collect::<Vec<_>>();
+
+    let mut ctx = tera::Context::new();
+    ctx.insert("message", &get_message(flash_messages, None));
+    ctx.insert("drafts", &drafts);
+    let page = render_html_template(&ctx, "newsletter_drafts.html");
+
+    let mut response = HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(page);
+    response
+        .add_removal_cookie(&Cookie::new("_flash", ""))
+        .unwrap();
+    Ok(response)
+}
+
+#[tracing::instrument(
+    name = "Creating a newsletter draft",
+    skip(form, db_pool, user_id),
+    fields(user_id=%&*user_id)
+)]
+pub async fn create_newsletter_draft(
+    form: web::Form<DraftFormData>,
+    db_pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let draft_id = Uuid::new_v4();
+    let content = form.content()?;
+    sqlx::query!(
+        r#"
+        INSERT INTO newsletter_drafts (
+            draft_id, title, html_content, text_content, markdown_content,
+            segment_attribute, segment_value, email_layout_id, created_by
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        "#,
+        draft_id,
+        content.title,
+        content.html_content,
+        content.text_content,
+        content.markdown_content.unwrap_or_default(),
+        form.segment_attribute,
+        form.segment_value,
+        content.email_layout_id,
+        **user_id
+    )
+    .execute(&**db_pool)
+    .await
+    .context("Failed to create a newsletter draft.")
+    .map_err(e500)?;
+
+    FlashMessage::info("The draft has been saved.").send();
+    Ok(see_other(&format!("{DRAFTS_PATH}/{draft_id}")))
+}
+
+/// Published issues can't change, but they can be the starting point of a new one.
+#[tracing::instrument(
+    name = "Copying an issue into a draft",
+    skip(db_pool, user_id),
+    fields(user_id=%&*user_id)
+)]
+pub async fn draft_from_newsletter_issue(
+    issue_id: web::Path<Uuid>,
+    db_pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let draft_id = Uuid::new_v4();
+    let inserted = sqlx::query!(
+        r#"
+        INSERT INTO newsletter_drafts (
+            draft_id, title, html_content, text_content, markdown_content, email_layout_id,
+            created_by
+        )
+        SELECT
+            $1, title, html_content, text_content, coalesce(markdown_content, ''),
+            email_layout_id, $3
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $2
+        "#,
+        draft_id,
+        *issue_id,
+        **user_id
+    )
+    .execute(&**db_pool)
+    .await
+    .context("Failed to copy an issue into a draft.")
+    .map_err(e500)?;
+    if inserted.rows_affected() == 0 {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+
+    FlashMessage::info("A new draft has been created from the issue.").send();
+    Ok(see_other(&format!("{DRAFTS_PATH}/{draft_id}")))
+}
+
+pub async fn edit_newsletter_draft(
+    draft_id: web::Path<Uuid>,
+    db_pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let Some(draft) = get_draft(&**db_pool, *draft_id).await.map_err(e500)? else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+
+    let mut ctx = tera::Context::new();
+    ctx.insert("message", &get_message(flash_messages, None));
+    let test_sends = get_test_sends(&db_pool, TestSendSource::Draft(*draft_id))
+        .await
+        .map_err(e500)?;
+    let layouts = get_email_layouts(&**db_pool).await.map_err(e500)?;
+    ctx.insert("draft", &draft);
+    ctx.insert("layouts", &layouts);
+    ctx.insert("test_sends", &test_sends);
+    // A fresh key per page load, so publishing twice from the same page only sends once.
+    ctx.insert("idempotency_key", &Uuid::new_v4().to_string());
+    let page = render_html_template(&ctx, "newsletter_draft.html");
+
+    let mut response = HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(page);
+    response
+        .add_removal_cookie(&Cookie::new("_flash", ""))
+        .unwrap();
+    Ok(response)
+}
+
+pub async fn save_newsletter_draft(
+    draft_id: web::Path<Uuid>,
+    form: web::Form<DraftFormData>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let content = form.content()?;
+    if !update_draft(&db_pool, *draft_id, &content, &form)
+        .await
+        .map_err(e500)?
+    {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    FlashMessage::info("The draft has been saved.").send();
+    Ok(see_other(&format!("{DRAFTS_PATH}/{draft_id}")))
+}
+
+/// Called in the background by the edit page, so it answers without a redirect or a flash.
+pub async fn autosave_newsletter_draft(
+    draft_id: web::Path<Uuid>,
+    form: web::Form<DraftFormData>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let content = form.content()?;
+    if !update_draft(&db_pool, *draft_id, &content, &form)
+        .await
+        .map_err(e500)?
+    {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Renders the saved draft the way the delivery worker renders an issue.
+pub async fn preview_newsletter_draft(
+    draft_id: web::Path<Uuid>,
+    parameters: web::Query<PreviewParameters>,
+    db_pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseURL>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let Some(draft) = get_draft(&**db_pool, *draft_id).await.map_err(e500)? else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    let recipient = match parameters.email.trim() {
+        "" => RecipientContext::anonymous(""),
+        email => get_recipient_context(&**db_pool, email)
+            .await
+            .map_err(e500)?,
+    };
+    let rendered = render_for_recipient(&draft.html, &draft.text, &recipient);
+    let layout = match draft.email_layout_id {
+        Some(id) => get_email_layout(&**db_pool, id).await.map_err(e500)?,
+        None => None,
+    };
+    let rendered = lay_out_for_recipient(
+        &db_pool,
+        &base_url,
+        layout.as_ref().map(|l| l.template.as_str()),
+        &draft.title,
+        rendered,
+        &recipient,
+    )
+    .await
+    .map_err(e500)?;
+
+    let mut response = HttpResponse::Ok();
+    // The content is shown as it will be mailed, scripts included, so it gets no access to
+    // the admin session.
+    response.insert_header((CONTENT_SECURITY_POLICY, "sandbox"));
+    Ok(match parameters.format.as_str() {
+        "text" => response
+            .content_type(ContentType::plaintext())
+            .body(rendered.text_content),
+        "" | "html" => response
+            .content_type(ContentType::html())
+            .body(rendered.html_content),
+        other => return Err(e400(format!("{other} is not a preview format."))),
+    })
+}
+
+#[tracing::instrument(
+    name = "Publishing a newsletter draft",
+    skip(form, db_pool, user_id),
+    fields(user_id=%&*user_id)
+)]
+pub async fn publish_newsletter_draft(
+    draft_id: web::Path<Uuid>,
+    form: web::Form<PublishDraftFormData>,
+    db_pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let draft_id = draft_id.into_inner();
+    let idempotency_key: IdempotencyKey = form.0.idempotency_key.try_into().map_err(e400)?;
+    let mut transaction = match try_processing(&db_pool, &idempotency_key, **user_id)
+        .await
+        .map_err(e500)?
+    {
+        NextAction::StartProcessing(t) => t,
+        NextAction::ReturnSavedResponse(saved_response) => {
+            success_message().send();
+            return Ok(saved_response);
+        }
+    };
+
+    // The row lock makes a second publish with another key wait, then find nothing.
+    let Some(draft) = sqlx::query!(
+        r#"
+        SELECT
+            title, html_content, text_content, markdown_content,
+            segment_attribute, segment_value, email_layout_id
+        FROM newsletter_drafts
+        WHERE draft_id = $1
+        FOR UPDATE
+        "#,
+        draft_id
+    )
+    .fetch_optional(&mut *transaction)
+    .await
+    .map_err(e500)?
+    else {
+        FlashMessage::error("The draft doesn't exist or has already been published.").send();
+        return Ok(see_other(DRAFTS_PATH));
+    };
+    if draft.title.trim().is_empty() {
+        FlashMessage::error("The draft needs a title before it can be published.").send();
+        return Ok(see_other(&format!("{DRAFTS_PATH}/{draft_id}")));
+    }
+
+    let segment = match draft.segment_attribute.trim() {
+        "" => None,
+        attribute => Some((
+            attribute.to_string(),
+            draft.segment_value.trim().to_string(),
+        )),
+    };
+    // The bodies were generated when the draft was saved.
+    let content = IssueContent {
+        title: draft.title,
+        html_content: draft.html_content,
+        text_content: draft.text_content,
+        markdown_content: Some(draft.markdown_content).filter(|m| !m.trim().is_empty()),
+        email_layout_id: draft.email_layout_id,
+    };
+    let issue_id = publish_issue(&mut transaction, &content, **user_id, segment)
+        .await
+        .map_err(e500)?;
+    // Test sends of the draft stay visible on the published issue.
+    sqlx::query!(
+        "UPDATE test_sends SET newsletter_issue_id = $2 WHERE draft_id = $1",
+        draft_id,
+        issue_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(e500)?;
+    sqlx::query!(
+        "DELETE FROM newsletter_drafts WHERE draft_id = $1",
+        draft_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(e500)?;
+
+    success_message().send();
+    let response = see_other(DRAFTS_PATH);
+    let response = save_response(transaction, &idempotency_key, **user_id, response)
+        .await
+        .map_err(e500)?;
+    Ok(response)
+}
+
+fn success_message() -> FlashMessage {
+    FlashMessage::info("The newsletter issue has been accepted - emails will go out shortly.")
+}
+
+pub async fn delete_newsletter_draft(
+    draft_id: web::Path<Uuid>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    sqlx::query!(
+        "DELETE FROM newsletter_drafts WHERE draft_id = $1",
+        *draft_id
+    )
+    .execute(&**db_pool)
+    .await
+    .context("Failed to delete a newsletter draft.")
+    .map_err(e500)?;
+    FlashMessage::info("The draft has been deleted.").send();
+    Ok(see_other(DRAFTS_PATH))
+}
+
+#[tracing::instrument(name = "Getting a newsletter draft", skip(executor))]
+async fn get_draft(
+    executor: impl PgExecutor<'_>,
+    draft_id: Uuid,
+) -> Result<Option<Draft>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT
+            title, html_content, text_content, markdown_content,
+            segment_attribute, segment_value, email_layout_id, updated_at
+        FROM newsletter_drafts
+        WHERE draft_id = $1
+        "#,
+        draft_id
+    )
+    .fetch_optional(executor)
+    .await
+    .context("Failed to read a newsletter draft.")?;
+
+    Ok(row.map(|r| Draft {
+        id: draft_id.to_string(),
+        title: r.title,
+        html: r.html_content,
+        text: r.text_content,
+        markdown: r.markdown_content,
+        segment_attribute: r.segment_attribute,
+        segment_value: r.segment_value,
+        email_layout_id: r.email_layout_id,
+        updated_at: format_time(r.updated_at),
+    }))
+}
+
+#[tracing::instrument(name = "Saving a newsletter draft", skip(pool, content, form))]
+async fn update_draft(
+    pool: &PgPool,
+    draft_id: Uuid,
+    content: &IssueContent,
+    form: &DraftFormData,
+) -> Result<bool, anyhow::Error> {
+    let updated = sqlx::query!(
+        r#"
+        UPDATE newsletter_drafts
+        SET
+            title = $2, html_content = $3, text_content = $4, markdown_content = $5,
+            segment_attribute = $6, segment_value = $7, email_layout_id = $8,
+            updated_at = now()
+        WHERE draft_id = $1
+        "#,
+        draft_id,
+        content.title,
+        content.html_content,
+        content.text_content,
+        content.markdown_content.as_deref().unwrap_or_default(),
+        form.segment_attribute,
+        form.segment_value,
+        content.email_layout_id
+    )
+    .execute(pool)
+    .await
+    .context("Failed to save a newsletter draft.")?;
+    Ok(updated.rows_affected() > 0)
+}
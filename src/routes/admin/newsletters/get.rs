@@ -1,19 +1,24 @@
-use crate::routes::helpers::{get_message, prepare_html_template};
-use actix_web::{HttpResponse, http::header::ContentType};
+use crate::{
+    email_layouts::get_email_layouts,
+    routes::helpers::{e500, get_message, render_html_template},
+};
+use actix_web::{HttpResponse, http::header::ContentType, web};
 use actix_web_flash_messages::IncomingFlashMessages;
+use sqlx::PgPool;
 
 pub async fn send_newsletters_form(
+    db_pool: web::Data<PgPool>,
     flash_messages: IncomingFlashMessages,
 ) -> Result<HttpResponse, actix_web::Error> {
     let idempotency_key = uuid::Uuid::new_v4();
     let message = get_message(flash_messages, None);
-    let page = prepare_html_template(
-        &[
-            ("idempotency_key", &idempotency_key.to_string()),
-            ("message", &message),
-        ],
-        "send_newsletters_form.html",
-    );
+    let layouts = get_email_layouts(&**db_pool).await.map_err(e500)?;
+
+    let mut ctx = tera::Context::new();
+    ctx.insert("idempotency_key", &idempotency_key.to_string());
+    ctx.insert("message", &message);
+    ctx.insert("layouts", &layouts);
+    let page = render_html_template(&ctx, "send_newsletters_form.html");
     Ok(HttpResponse::Ok()
         .content_type(ContentType::html())
         .body(page))
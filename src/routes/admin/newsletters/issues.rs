@@ -0,0 +1,258 @@
+use actix_web::{HttpResponse, cookie::Cookie, http::header::ContentType, web};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{TestSendSource, get_test_sends};
+use crate::routes::helpers::{e400, e500, get_message, render_html_template, see_other};
+
+const PAGE_SIZE: i64 = 25;
+
+#[derive(Deserialize)]
+pub struct IssueListParameters {
+    page: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct VisibilityFormData {
+    visibility: String,
+}
+
+/// Counts come from the delivery log, plus whatever is still waiting in the queue.
+#[derive(Serialize)]
+struct DeliveryStats {
+    pending: i64,
+    sent: i64,
+    failed: i64,
+    skipped: i64,
+}
+
+#[derive(Serialize)]
+struct IssueRow {
+    id: String,
+    title: String,
+    is_private: bool,
+    published_at: String,
+    author: Option<String>,
+    imported_from: Option<String>,
+    stats: DeliveryStats,
+}
+
+#[derive(Serialize)]
+struct IssueDetail {
+    id: String,
+    title: String,
+    slug: String,
+    is_private: bool,
+    published_at: String,
+    author: Option<String>,
+    imported_from: Option<String>,
+    html_content: String,
+    text_content: String,
+    markdown_content: Option<String>,
+    stats: DeliveryStats,
+}
+
+fn format_time(t: DateTime<Utc>) -> String {
+    t.format("%Y-%m-%d %H:%M").to_string()
+}
+
+pub async fn list_newsletter_issues(
+    parameters: web::Query<IssueListParameters>,
+    db_pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let page = parameters.page.unwrap_or(1).max(1);
+    let (issues, total) = get_issues_page(&db_pool, page).await.map_err(e500)?;
+    let n_pages = ((total + PAGE_SIZE - 1) / PAGE_SIZE).max(1);
+
+    let mut ctx = tera::Context::new();
+    ctx.insert("message", &get_message(flash_messages, None));
+    ctx.insert("issues", &issues);
+    ctx.insert("total", &total);
+    ctx.insert("page", &page);
+    ctx.insert("n_pages", &n_pages);
+    let page = render_html_template(&ctx, "newsletter_issues.html");
+
+    let mut response = HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(page);
+    response
+        .add_removal_cookie(&Cookie::new("_flash", ""))
+        .unwrap();
+    Ok(response)
+}
+
+pub async fn newsletter_issue_detail(
+    issue_id: web::Path<Uuid>,
+    db_pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let issue_id = issue_id.into_inner();
+    let Some(issue) = get_issue_detail(&db_pool, issue_id).await.map_err(e500)? else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    let test_sends = get_test_sends(&db_pool, TestSendSource::Issue(issue_id))
+        .await
+        .map_err(e500)?;
+
+    let mut ctx = tera::Context::new();
+    ctx.insert("message", &get_message(flash_messages, None));
+    ctx.insert("issue", &issue);
+    ctx.insert("test_sends", &test_sends);
+    let page = render_html_template(&ctx, "newsletter_issue.html");
+
+    let mut response = HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(page);
+    response
+        .add_removal_cookie(&Cookie::new("_flash", ""))
+        .unwrap();
+    Ok(response)
+}
+
+/// Private issues stay in the admin archive but are left out of `/issues` and the feed.
+#[tracing::instrument(name = "Changing the visibility of an issue", skip(form, db_pool))]
+pub async fn set_newsletter_issue_visibility(
+    issue_id: web::Path<Uuid>,
+    form: web::Form<VisibilityFormData>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let issue_id = issue_id.into_inner();
+    let is_private = match form.visibility.as_str() {
+        "private" => true,
+        "public" => false,
+        other => return Err(e400(format!("{other} is not a valid visibility."))),
+    };
+    let updated = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET is_private = $2, updated_at = now()
+        WHERE newsletter_issue_id = $1 AND is_private <> $2
+        "#,
+        issue_id,
+        is_private
+    )
+    .execute(&**db_pool)
+    .await
+    .context("Failed to change the visibility of an issue.")
+    .map_err(e500)?;
+
+    if updated.rows_affected() > 0 {
+        FlashMessage::info(if is_private {
+            "The issue is now private."
+        } else {
+            "The issue is now public."
+        })
+        .send();
+    }
+    Ok(see_other(&format!("/admin/newsletters/issues/{issue_id}")))
+}
+
+// `published_at` is stored as text, so it is cast back for sorting and display.
+#[tracing::instrument(name = "Listing newsletter issues", skip(pool))]
+async fn get_issues_page(pool: &PgPool, page: i64) -> Result<(Vec<IssueRow>, i64), anyhow::Error> {
+    let total = sqlx::query_scalar!(r#"SELECT count(*) AS "count!" FROM newsletter_issues"#)
+        .fetch_one(pool)
+        .await
+        .context("Failed to count newsletter issues.")?;
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            i.newsletter_issue_id, i.title, i.is_private, i.imported_from,
+            i.published_at::timestamptz AS "published_at!",
+            u.username AS "author?",
+            (SELECT count(*) FROM issue_delivery_queue q
+                WHERE q.newsletter_issue_id = i.newsletter_issue_id) AS "pending!",
+            count(l.outcome) FILTER (WHERE l.outcome = 'sent') AS "sent!",
+            count(l.outcome) FILTER (WHERE l.outcome = 'failed') AS "failed!",
+            count(l.outcome) FILTER (WHERE l.outcome = 'skipped') AS "skipped!"
+        FROM newsletter_issues i
+        LEFT JOIN users u ON u.user_id = i.published_by
+        LEFT JOIN issue_delivery_log l USING (newsletter_issue_id)
+        GROUP BY i.newsletter_issue_id, u.username
+        ORDER BY i.published_at::timestamptz DESC, i.newsletter_issue_id
+        LIMIT $1 OFFSET $2
+        "#,
+        PAGE_SIZE,
+        (page - 1) * PAGE_SIZE
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to read the newsletter issues.")?;
+
+    let issues = rows
+        .into_iter()
+        .map(|r| IssueRow {
+            id: r.newsletter_issue_id.to_string(),
+            title: r.title,
+            is_private: r.is_private,
+            published_at: format_time(r.published_at),
+            author: r.author,
+            imported_from: r.imported_from,
+            stats: DeliveryStats {
+                pending: r.pending,
+                sent: r.sent,
+                failed: r.failed,
+                skipped: r.skipped,
+            },
+        })
+        .collect();
+    Ok((issues, total))
+}
+
+#[tracing::instrument(name = "Getting newsletter issue details", skip(pool))]
+async fn get_issue_detail(
+    pool: &PgPool,
+    issue_id: Uuid,
+) -> Result<Option<IssueDetail>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT
+            i.title, i.slug, i.is_private, i.html_content, i.text_content, i.markdown_content,
+            i.imported_from,
+            i.published_at::timestamptz AS "published_at!",
+            u.username AS "author?",
+            (SELECT count(*) FROM issue_delivery_queue q
+                WHERE q.newsletter_issue_id = i.newsletter_issue_id) AS "pending!",
+            (SELECT count(*) FROM issue_delivery_log l
+                WHERE l.newsletter_issue_id = i.newsletter_issue_id
+                AND l.outcome = 'sent') AS "sent!",
+            (SELECT count(*) FROM issue_delivery_log l
+                WHERE l.newsletter_issue_id = i.newsletter_issue_id
+                AND l.outcome = 'failed') AS "failed!",
+            (SELECT count(*) FROM issue_delivery_log l
+                WHERE l.newsletter_issue_id = i.newsletter_issue_id
+                AND l.outcome = 'skipped') AS "skipped!"
+        FROM newsletter_issues i
+        LEFT JOIN users u ON u.user_id = i.published_by
+        WHERE i.newsletter_issue_id = $1
+        "#,
+        issue_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to read the newsletter issue.")?;
+
+    Ok(row.map(|r| IssueDetail {
+        id: issue_id.to_string(),
+        title: r.title,
+        slug: r.slug,
+        is_private: r.is_private,
+        published_at: format_time(r.published_at),
+        author: r.author,
+        imported_from: r.imported_from,
+        html_content: r.html_content,
+        text_content: r.text_content,
+        markdown_content: r.markdown_content,
+        stats: DeliveryStats {
+            pending: r.pending,
+            sent: r.sent,
+            failed: r.failed,
+            skipped: r.skipped,
+        },
+    }))
+}
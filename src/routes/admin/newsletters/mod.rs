@@ -1,5 +1,11 @@
+mod drafts;
 mod get;
+mod issues;
 mod post;
+mod test_send;
 
+pub use drafts::*;
 pub use get::send_newsletters_form;
+pub use issues::*;
 pub use post::*;
+pub use test_send::*;
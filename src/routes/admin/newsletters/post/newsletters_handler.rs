@@ -1,7 +1,9 @@
 use super::{errors::PublishError, types::BodySchema};
 use crate::{
     authentication::UserId,
+    domain::IssueSlug,
     idempotency::{IdempotencyKey, NextAction, save_response, try_processing},
+    issue_authoring::IssueContent,
     routes::{e500, get_username, helpers::e400, see_other},
 };
 use actix_web::HttpResponse;
@@ -21,11 +23,17 @@ pub async fn publish_newsletter(
     user_id: actix_web::web::ReqData<UserId>,
 ) -> Result<HttpResponse, actix_web::Error> {
     let user_id = user_id.into_inner();
+    let segment = body
+        .segment()
+        .map(|(attribute, value)| (attribute.to_string(), value.to_string()));
+    let email_layout_id = body.email_layout_id().map_err(e400)?;
     let BodySchema {
         title,
         text,
         html,
+        markdown,
         idempotency_key,
+        ..
     } = body.0;
     let idempotency_key: IdempotencyKey = idempotency_key.try_into().map_err(e400)?;
     let mut transaction = match try_processing(&db_pool, &idempotency_key, *user_id)
@@ -45,13 +53,9 @@ pub async fn publish_newsletter(
     tracing::Span::current().record("username", tracing::field::display(&username));
     tracing::Span::current().record("user_id", tracing::field::display(&(*user_id)));
 
-    let issue_id = insert_newsletter_issue(&mut transaction, &title, &text, &html)
+    let content = IssueContent::new(title, html, text, markdown, email_layout_id);
+    publish_issue(&mut transaction, &content, *user_id, segment)
         .await
-        .context("Failed to store newsletter issue details")
-        .map_err(e500)?;
-    enqueue_delivery_tasks(&mut transaction, issue_id)
-        .await
-        .context("Failed to enqueue delivery tasks")
         .map_err(e500)?;
 
     success_message().send();
@@ -66,14 +70,31 @@ fn success_message() -> FlashMessage {
     FlashMessage::info("The newsletter issue has been accepted - emails will go out shortly.")
 }
 
+/// Stores the issue and queues it for every matching subscriber. Runs in the caller's
+/// transaction so it commits together with the idempotency record.
+pub async fn publish_issue(
+    transaction: &mut PgConnection,
+    content: &IssueContent,
+    published_by: Uuid,
+    segment: Option<(String, String)>,
+) -> Result<Uuid, anyhow::Error> {
+    let issue_id = insert_newsletter_issue(&mut *transaction, content, published_by)
+        .await
+        .context("Failed to store newsletter issue details")?;
+    enqueue_delivery_tasks(&mut *transaction, issue_id, segment)
+        .await
+        .context("Failed to enqueue delivery tasks")?;
+    Ok(issue_id)
+}
+
 #[tracing::instrument(skip_all)]
 async fn insert_newsletter_issue(
     transaction: &mut PgConnection,
-    title: &str,
-    text_content: &str,
-    html_content: &str,
+    content: &IssueContent,
+    published_by: Uuid,
 ) -> Result<Uuid, sqlx::Error> {
     let newsletter_issue_id = Uuid::new_v4();
+    let slug = IssueSlug::new(&content.title, newsletter_issue_id);
     sqlx::query!(
         r#"
         INSERT INTO newsletter_issues (
@@ -81,14 +102,22 @@ async fn insert_newsletter_issue(
             title,
             text_content,
             html_content,
-            published_at
+            markdown_content,
+            published_at,
+            published_by,
+            slug,
+            email_layout_id
         )
-        VALUES ($1, $2, $3, $4, now())
+        VALUES ($1, $2, $3, $4, $5, now(), $6, $7, $8)
         "#,
         newsletter_issue_id,
-        title,
-        text_content,
-        html_content
+        content.title,
+        content.text_content,
+        content.html_content,
+        content.markdown_content,
+        published_by,
+        slug.as_ref(),
+        content.email_layout_id
     )
     .execute(transaction)
     .await?;
@@ -99,7 +128,9 @@ async fn insert_newsletter_issue(
 async fn enqueue_delivery_tasks(
     transaction: &mut PgConnection,
     newsletter_issue_id: Uuid,
+    segment: Option<(String, String)>,
 ) -> Result<(), sqlx::Error> {
+    let (segment_attribute, segment_value) = segment.unzip();
     sqlx::query!(
         r#"
         INSERT INTO issue_delivery_queue (
@@ -109,8 +140,11 @@ async fn enqueue_delivery_tasks(
         SELECT $1, email
         FROM subscriptions
         WHERE status = 'confirmed'
+        AND ($2::text IS NULL OR attributes ->> $2 = $3)
         "#,
         newsletter_issue_id,
+        segment_attribute,
+        segment_value
     )
     .execute(transaction)
     .await?;
@@ -1,3 +1,5 @@
+use uuid::Uuid;
+
 use crate::domain::SubscriberEmail;
 
 #[derive(serde::Deserialize, serde::Serialize)]
@@ -5,7 +7,34 @@ pub struct BodySchema {
     pub title: String,
     pub text: String,
     pub html: String,
+    /// When filled in, the HTML and text bodies are generated from it.
+    #[serde(default)]
+    pub markdown: String,
     pub idempotency_key: String,
+    // Only subscribers whose attribute matches the value get the issue; blank sends to everyone.
+    #[serde(default)]
+    pub segment_attribute: String,
+    #[serde(default)]
+    pub segment_value: String,
+    /// The id of the email layout to send the issue in, blank for none.
+    #[serde(default)]
+    pub email_layout: String,
+}
+
+impl BodySchema {
+    pub fn segment(&self) -> Option<(&str, &str)> {
+        match self.segment_attribute.trim() {
+            "" => None,
+            attribute => Some((attribute, self.segment_value.trim())),
+        }
+    }
+
+    pub fn email_layout_id(&self) -> Result<Option<Uuid>, uuid::Error> {
+        match self.email_layout.trim() {
+            "" => Ok(None),
+            id => id.parse().map(Some),
+        }
+    }
 }
 
 pub struct ConfirmedSubscriber {
@@ -0,0 +1,296 @@
+use actix_web::{HttpResponse, web};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    authentication::UserId,
+    domain::SubscriberEmail,
+    email_client::EmailClient,
+    email_layouts::lay_out_for_recipient,
+    issue_rendering::{get_recipient_context, render_for_recipient},
+    routes::helpers::{e500, see_other},
+    startup::ApplicationBaseURL,
+};
+
+const MAX_RECIPIENTS: usize = 5;
+const HISTORY_LIMIT: i64 = 20;
+
+#[derive(Deserialize)]
+pub struct TestSendFormData {
+    /// Separated by commas, spaces or new lines.
+    recipients: String,
+}
+
+#[derive(Serialize)]
+pub struct TestSendRow {
+    recipient: String,
+    outcome: String,
+    sent_by: String,
+    sent_at: String,
+}
+
+/// What is being tested, the log keeps a reference to it.
+#[derive(Debug, Clone, Copy)]
+pub enum TestSendSource {
+    Draft(Uuid),
+    Issue(Uuid),
+}
+
+impl TestSendSource {
+    fn ids(&self) -> (Option<Uuid>, Option<Uuid>) {
+        match *self {
+            TestSendSource::Draft(id) => (Some(id), None),
+            TestSendSource::Issue(id) => (None, Some(id)),
+        }
+    }
+
+    fn page(&self) -> String {
+        match self {
+            TestSendSource::Draft(id) => format!("/admin/newsletters/drafts/{id}"),
+            TestSendSource::Issue(id) => format!("/admin/newsletters/issues/{id}"),
+        }
+    }
+}
+
+struct TestContent {
+    title: String,
+    html_content: String,
+    text_content: String,
+    layout: Option<String>,
+}
+
+pub async fn test_send_newsletter_draft(
+    draft_id: web::Path<Uuid>,
+    form: web::Form<TestSendFormData>,
+    db_pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseURL>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let content = sqlx::query_as!(
+        TestContent,
+        r#"
+        SELECT d.title, d.html_content, d.text_content, l.template AS "layout?"
+        FROM newsletter_drafts d
+        LEFT JOIN email_layouts l USING (email_layout_id)
+        WHERE d.draft_id = $1
+        "#,
+        *draft_id
+    )
+    .fetch_optional(&**db_pool)
+    .await
+    .map_err(e500)?;
+    test_send(
+        TestSendSource::Draft(*draft_id),
+        content,
+        &form.recipients,
+        &db_pool,
+        &email_client,
+        &base_url,
+        **user_id,
+    )
+    .await
+}
+
+pub async fn test_send_newsletter_issue(
+    issue_id: web::Path<Uuid>,
+    form: web::Form<TestSendFormData>,
+    db_pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseURL>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let content = sqlx::query_as!(
+        TestContent,
+        r#"
+        SELECT i.title, i.html_content, i.text_content, l.template AS "layout?"
+        FROM newsletter_issues i
+        LEFT JOIN email_layouts l USING (email_layout_id)
+        WHERE i.newsletter_issue_id = $1
+        "#,
+        *issue_id
+    )
+    .fetch_optional(&**db_pool)
+    .await
+    .map_err(e500)?;
+    test_send(
+        TestSendSource::Issue(*issue_id),
+        content,
+        &form.recipients,
+        &db_pool,
+        &email_client,
+        &base_url,
+        **user_id,
+    )
+    .await
+}
+
+fn parse_recipients(raw: &str) -> Result<Vec<SubscriberEmail>, String> {
+    let recipients = raw
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|r| !r.is_empty())
+        .map(|r| SubscriberEmail::parse(r.to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+    match recipients.len() {
+        0 => Err("Enter at least one address to send the test to.".into()),
+        n if n > MAX_RECIPIENTS => Err(format!(
+            "A test can go to at most {MAX_RECIPIENTS} addresses."
+        )),
+        _ => Ok(recipients),
+    }
+}
+
+// Goes straight through the email client, so the editor sees the result right away and real
+// subscribers' queue is never involved.
+#[tracing::instrument(
+    name = "Sending a test email",
+    skip(content, raw_recipients, pool, email_client, base_url),
+    fields(sent_by=%sent_by)
+)]
+async fn test_send(
+    source: TestSendSource,
+    content: Option<TestContent>,
+    raw_recipients: &str,
+    pool: &PgPool,
+    email_client: &EmailClient,
+    base_url: &ApplicationBaseURL,
+    sent_by: Uuid,
+) -> Result<HttpResponse, actix_web::Error> {
+    let Some(content) = content else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    let recipients = match parse_recipients(raw_recipients) {
+        Ok(recipients) => recipients,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other(&source.page()));
+        }
+    };
+
+    let subject = format!("[Test] {}", content.title);
+    let mut failed = vec![];
+    for recipient in &recipients {
+        let context = get_recipient_context(pool, recipient.as_ref())
+            .await
+            .map_err(e500)?;
+        let rendered = render_for_recipient(&content.html_content, &content.text_content, &context);
+        let rendered = lay_out_for_recipient(
+            pool,
+            base_url,
+            content.layout.as_deref(),
+            &content.title,
+            rendered,
+            &context,
+        )
+        .await
+        .map_err(e500)?;
+        let outcome = match email_client
+            .send_email(
+                vec![recipient],
+                &subject,
+                &rendered.html_content,
+                &rendered.text_content,
+            )
+            .await
+        {
+            Ok(_) => "sent",
+            Err(e) => {
+                tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to send a test email.",
+                );
+                failed.push(recipient.as_ref());
+                "failed"
+            }
+        };
+        log_test_send(pool, source, &subject, recipient.as_ref(), outcome, sent_by)
+            .await
+            .map_err(e500)?;
+    }
+
+    if failed.is_empty() {
+        FlashMessage::info(format!(
+            "The test email has been sent to {}.",
+            recipients
+                .iter()
+                .map(AsRef::as_ref)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))
+        .send();
+    } else {
+        FlashMessage::error(format!(
+            "The test email could not be sent to {}.",
+            failed.join(", ")
+        ))
+        .send();
+    }
+    Ok(see_other(&source.page()))
+}
+
+async fn log_test_send(
+    pool: &PgPool,
+    source: TestSendSource,
+    subject: &str,
+    recipient: &str,
+    outcome: &str,
+    sent_by: Uuid,
+) -> Result<(), sqlx::Error> {
+    let (draft_id, issue_id) = source.ids();
+    sqlx::query!(
+        r#"
+        INSERT INTO test_sends (
+            test_send_id, draft_id, newsletter_issue_id, subject, recipient, outcome, sent_by
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+        Uuid::new_v4(),
+        draft_id,
+        issue_id,
+        subject,
+        recipient,
+        outcome,
+        sent_by
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+#[tracing::instrument(name = "Getting test sends", skip(pool))]
+pub async fn get_test_sends(
+    pool: &PgPool,
+    source: TestSendSource,
+) -> Result<Vec<TestSendRow>, anyhow::Error> {
+    let (draft_id, issue_id) = source.ids();
+    let rows = sqlx::query!(
+        r#"
+        SELECT t.recipient, t.outcome, t.sent_at, u.username
+        FROM test_sends t
+        JOIN users u ON u.user_id = t.sent_by
+        WHERE t.draft_id = $1 OR t.newsletter_issue_id = $2
+        ORDER BY t.sent_at DESC
+        LIMIT $3
+        "#,
+        draft_id,
+        issue_id,
+        HISTORY_LIMIT
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to read the test sends.")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| TestSendRow {
+            recipient: r.recipient,
+            outcome: r.outcome,
+            sent_by: r.username,
+            sent_at: r.sent_at.format("%Y-%m-%d %H:%M").to_string(),
+        })
+        .collect())
+}
@@ -1,14 +1,30 @@
-use actix_web::{HttpResponse, http::header::ContentType};
+use actix_web::{HttpResponse, http::header::ContentType, web};
 use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use sqlx::PgPool;
 
-use crate::routes::helpers::{get_message, prepare_html_template};
+use crate::{
+    authentication::UserId,
+    routes::helpers::{e500, get_message, prepare_html_template},
+};
 
 pub async fn change_password_form(
     flash_messages: IncomingFlashMessages,
+    user_id: web::ReqData<UserId>,
+    pool: web::Data<PgPool>,
 ) -> Result<HttpResponse, actix_web::Error> {
     let message_string = get_message(flash_messages, None);
+    let email = sqlx::query_scalar!(r#"SELECT email FROM users WHERE user_id = $1"#, **user_id)
+        .fetch_one(&**pool)
+        .await
+        .context("Failed to read the recovery email.")
+        .map_err(e500)?
+        .unwrap_or_default();
 
-    let page = prepare_html_template(&[("message", &message_string)], "change_password_form.html");
+    let page = prepare_html_template(
+        &[("message", &message_string), ("email", &email)],
+        "change_password_form.html",
+    );
     Ok(HttpResponse::Ok()
         .content_type(ContentType::html())
         .body(page))
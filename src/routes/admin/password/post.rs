@@ -1,11 +1,13 @@
 use actix_web::{HttpResponse, web};
 use actix_web_flash_messages::FlashMessage;
-use secrecy::{ExposeSecret, SecretString};
+use anyhow::Context;
+use secrecy::SecretString;
 use serde::Deserialize;
 use sqlx::PgPool;
 
 use crate::{
-    authentication::{AuthError, Credentials, UserId, validate_credentials},
+    authentication::{AuthError, Credentials, UserId, validate_credentials, validate_new_password},
+    domain::SubscriberEmail,
     routes::{
         admin::dashboard::get_username,
         helpers::{e500, see_other},
@@ -24,20 +26,8 @@ pub async fn change_password(
     user_id: web::ReqData<UserId>,
     pool: web::Data<PgPool>,
 ) -> Result<HttpResponse, actix_web::Error> {
-    if form.new_password.expose_secret() != form.new_password_check.expose_secret() {
-        FlashMessage::error(
-            "You entered two different new passwords - the field values must match.",
-        )
-        .send();
-        return Ok(see_other("/admin/password"));
-    }
-
-    if form.new_password.expose_secret().len() < 12 || form.new_password.expose_secret().len() > 128
-    {
-        FlashMessage::error(
-            "The password should be longer than 12 characters but shorter than 128 characters.",
-        )
-        .send();
+    if let Err(e) = validate_new_password(&form.new_password, &form.new_password_check) {
+        FlashMessage::error(e).send();
         return Ok(see_other("/admin/password"));
     }
 
@@ -64,3 +54,65 @@ pub async fn change_password(
     FlashMessage::error("Your password has been changed.").send();
     Ok(see_other("/admin/password"))
 }
+
+#[derive(Deserialize)]
+pub struct RecoveryEmailFormData {
+    current_password: SecretString,
+    email: String,
+}
+
+/// Where password reset links go. Without one, the account can't be reset.
+#[tracing::instrument(name = "Changing the recovery email", skip(form, pool))]
+pub async fn change_recovery_email(
+    form: web::Form<RecoveryEmailFormData>,
+    user_id: web::ReqData<UserId>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let RecoveryEmailFormData {
+        current_password,
+        email,
+    } = form.into_inner();
+    let email = match email.trim() {
+        "" => None,
+        email => match SubscriberEmail::parse(email.to_string()) {
+            Ok(email) => Some(email),
+            Err(e) => {
+                FlashMessage::error(e).send();
+                return Ok(see_other("/admin/password"));
+            }
+        },
+    };
+
+    // Reset links go to this address, so whoever holds the session has to know the password
+    // too, or a stolen session could be turned into a way back in.
+    let credentials = Credentials {
+        username: get_username(**user_id, &pool).await.map_err(e500)?,
+        password: current_password,
+    };
+    if let Err(e) = validate_credentials(credentials, &pool).await {
+        return match e {
+            AuthError::InvalidCredentials(_) => {
+                FlashMessage::error("The current password is incorrect.").send();
+                Ok(see_other("/admin/password"))
+            }
+            AuthError::UnexpectedError(_) => Err(e500(e)),
+        };
+    }
+
+    sqlx::query!(
+        r#"UPDATE users SET email = $1 WHERE user_id = $2"#,
+        email.as_ref().map(AsRef::as_ref),
+        **user_id
+    )
+    .execute(&**pool)
+    .await
+    .context("Failed to store the recovery email.")
+    .map_err(e500)?;
+
+    FlashMessage::info(match email {
+        Some(_) => "Your recovery email has been saved.",
+        None => "Your recovery email has been removed.",
+    })
+    .send();
+    Ok(see_other("/admin/password"))
+}
@@ -0,0 +1,3 @@
+mod signups;
+
+pub use signups::*;
@@ -0,0 +1,106 @@
+use actix_web::{HttpResponse, http::header::ContentType, web};
+use anyhow::Context;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::routes::helpers::{e500, render_html_template};
+
+const DEFAULT_DAYS: i64 = 90;
+const MAX_DAYS: i64 = 3650;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportPeriod {
+    Day,
+    #[default]
+    Week,
+    Month,
+}
+
+impl ReportPeriod {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ReportPeriod::Day => "day",
+            ReportPeriod::Week => "week",
+            ReportPeriod::Month => "month",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SignupReportParameters {
+    #[serde(default)]
+    period: ReportPeriod,
+    days: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct SignupReportRow {
+    period_start: String,
+    source: String,
+    campaign: String,
+    signups: i64,
+    confirmed: i64,
+    confirmation_rate: String,
+}
+
+pub async fn signup_report(
+    parameters: web::Query<SignupReportParameters>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let days = parameters.days.unwrap_or(DEFAULT_DAYS).clamp(1, MAX_DAYS);
+    let since = Utc::now() - Duration::days(days);
+    let rows = get_signups_by_source(&db_pool, parameters.period, since)
+        .await
+        .map_err(e500)?;
+
+    let mut ctx = tera::Context::new();
+    ctx.insert("period", parameters.period.as_str());
+    ctx.insert("days", &days);
+    ctx.insert("rows", &rows);
+    let page = render_html_template(&ctx, "signup_report.html");
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(page))
+}
+
+// An explicit `source` wins over `utm_source`; signups with neither count as direct.
+#[tracing::instrument(name = "Building the signup report", skip(pool))]
+async fn get_signups_by_source(
+    pool: &PgPool,
+    period: ReportPeriod,
+    since: DateTime<Utc>,
+) -> Result<Vec<SignupReportRow>, anyhow::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            date_trunc($1, subscribed_at) AS "period_start!",
+            coalesce(signup_source, utm_source, 'direct') AS "source!",
+            coalesce(utm_campaign, '') AS "campaign!",
+            count(*) AS "signups!",
+            count(confirmed_at) AS "confirmed!"
+        FROM subscriptions
+        WHERE subscribed_at >= $2
+        GROUP BY 1, 2, 3
+        ORDER BY 1 DESC, 4 DESC, 2, 3
+        "#,
+        period.as_str(),
+        since
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to aggregate signups by source.")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| SignupReportRow {
+            period_start: r.period_start.format("%Y-%m-%d").to_string(),
+            source: r.source,
+            campaign: r.campaign,
+            signups: r.signups,
+            confirmed: r.confirmed,
+            confirmation_rate: format!("{:.0}%", 100.0 * r.confirmed as f64 / r.signups as f64),
+        })
+        .collect())
+}
@@ -0,0 +1,3 @@
+mod steps;
+
+pub use steps::*;
@@ -0,0 +1,127 @@
+use actix_web::{HttpResponse, cookie::Cookie, http::header::ContentType, web};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::routes::helpers::{e500, get_message, render_html_template, see_other};
+
+const SEQUENCE_PATH: &str = "/admin/sequence";
+
+#[derive(Serialize)]
+struct DripStepSummary {
+    drip_step_id: Uuid,
+    delay_hours: i32,
+    subject: String,
+    scheduled: i64,
+    sent: i64,
+}
+
+pub async fn drip_sequence_page(
+    db_pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let steps = sqlx::query_as!(
+        DripStepSummary,
+        r#"
+        SELECT
+            st.drip_step_id,
+            st.delay_hours,
+            st.subject,
+            count(*) FILTER (WHERE d.status = 'scheduled') AS "scheduled!",
+            count(*) FILTER (WHERE d.status = 'sent') AS "sent!"
+        FROM drip_steps st
+        LEFT JOIN subscriber_drip_emails d USING (drip_step_id)
+        GROUP BY st.drip_step_id
+        ORDER BY st.delay_hours, st.created_at
+        "#
+    )
+    .fetch_all(&**db_pool)
+    .await
+    .context("Failed to read the drip sequence.")
+    .map_err(e500)?;
+
+    let mut ctx = tera::Context::new();
+    ctx.insert("message", &get_message(flash_messages, None));
+    ctx.insert("steps", &steps);
+    let page = render_html_template(&ctx, "drip_sequence.html");
+
+    let mut response = HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(page);
+    response
+        .add_removal_cookie(&Cookie::new("_flash", ""))
+        .unwrap();
+    Ok(response)
+}
+
+#[derive(Deserialize)]
+pub struct DripStepFormData {
+    delay_hours: String,
+    subject: String,
+    html: String,
+    text: String,
+}
+
+#[tracing::instrument(name = "Adding a drip step", skip(form, db_pool))]
+pub async fn add_drip_step(
+    form: web::Form<DripStepFormData>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let DripStepFormData {
+        delay_hours,
+        subject,
+        html,
+        text,
+    } = form.0;
+    let Ok(delay_hours) = delay_hours.trim().parse::<i32>() else {
+        FlashMessage::error("The delay must be a whole number of hours.").send();
+        return Ok(see_other(SEQUENCE_PATH));
+    };
+    if delay_hours < 0 {
+        FlashMessage::error("The delay cannot be negative.").send();
+        return Ok(see_other(SEQUENCE_PATH));
+    }
+    if subject.trim().is_empty() || text.trim().is_empty() {
+        FlashMessage::error("A drip email needs a subject and a plain text body.").send();
+        return Ok(see_other(SEQUENCE_PATH));
+    }
+
+    sqlx::query!(
+        r#"
+        INSERT INTO drip_steps (drip_step_id, delay_hours, subject, html_content, text_content)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        Uuid::new_v4(),
+        delay_hours,
+        subject.trim(),
+        html,
+        text
+    )
+    .execute(&**db_pool)
+    .await
+    .context("Failed to store the drip step.")
+    .map_err(e500)?;
+
+    FlashMessage::info("The email has been added to the sequence.").send();
+    Ok(see_other(SEQUENCE_PATH))
+}
+
+#[tracing::instrument(name = "Removing a drip step", skip(db_pool))]
+pub async fn delete_drip_step(
+    drip_step_id: web::Path<Uuid>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    sqlx::query!(
+        r#"DELETE FROM drip_steps WHERE drip_step_id = $1"#,
+        drip_step_id.into_inner()
+    )
+    .execute(&**db_pool)
+    .await
+    .context("Failed to remove the drip step.")
+    .map_err(e500)?;
+
+    FlashMessage::info("The email has been removed from the sequence.").send();
+    Ok(see_other(SEQUENCE_PATH))
+}
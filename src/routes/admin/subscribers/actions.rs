@@ -0,0 +1,217 @@
+use actix_web::{HttpResponse, web};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use serde::Deserialize;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::{
+    authentication::UserId,
+    domain::{SubscriberEmail, SubscriberName, SubscriptionStatus},
+    routes::{
+        ErasureRequester, confirm_and_schedule_drips, erase_subscriber, get_subscription_status,
+        helpers::{e500, see_other},
+        unsubscribe_and_cancel_drips,
+    },
+};
+
+fn subscriber_path(subscriber_id: Uuid) -> String {
+    format!("/admin/subscribers/{subscriber_id}")
+}
+
+#[tracing::instrument(
+    name = "Manually confirming a subscriber",
+    skip(db_pool, user_id),
+    fields(user_id=%&*user_id)
+)]
+pub async fn admin_confirm_subscriber(
+    subscriber_id: web::Path<Uuid>,
+    db_pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    change_status(&db_pool, *subscriber_id, SubscriptionStatus::Confirmed).await
+}
+
+#[tracing::instrument(
+    name = "Manually unsubscribing a subscriber",
+    skip(db_pool, user_id),
+    fields(user_id=%&*user_id)
+)]
+pub async fn admin_unsubscribe_subscriber(
+    subscriber_id: web::Path<Uuid>,
+    db_pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    change_status(&db_pool, *subscriber_id, SubscriptionStatus::Unsubscribed).await
+}
+
+// Goes through the same state machine and side effects (drips) as the subscriber's own links.
+async fn change_status(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+    next: SubscriptionStatus,
+) -> Result<HttpResponse, actix_web::Error> {
+    let Some(status) = get_subscription_status(pool, subscriber_id)
+        .await
+        .map_err(e500)?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    if let Err(e) = status.transition_to(next) {
+        FlashMessage::error(e).send();
+        return Ok(see_other(&subscriber_path(subscriber_id)));
+    }
+
+    match next {
+        SubscriptionStatus::Confirmed => confirm_and_schedule_drips(pool, subscriber_id, status)
+            .await
+            .map_err(e500)?,
+        _ => unsubscribe_and_cancel_drips(pool, subscriber_id, status)
+            .await
+            .map_err(e500)?,
+    }
+    FlashMessage::info(format!("The subscriber is now {next}.")).send();
+    Ok(see_other(&subscriber_path(subscriber_id)))
+}
+
+#[derive(Deserialize)]
+pub struct EditSubscriberFormData {
+    name: String,
+    email: String,
+}
+
+#[tracing::instrument(
+    name = "Editing a subscriber",
+    skip(form, db_pool, user_id),
+    fields(user_id=%&*user_id)
+)]
+pub async fn admin_edit_subscriber(
+    subscriber_id: web::Path<Uuid>,
+    form: web::Form<EditSubscriberFormData>,
+    db_pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let subscriber_id = subscriber_id.into_inner();
+    let EditSubscriberFormData { name, email } = form.0;
+    let parsed = SubscriberName::parse(name).and_then(|name| {
+        let email = SubscriberEmail::parse(email)?;
+        Ok((name, email))
+    });
+    let (name, email) = match parsed {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other(&subscriber_path(subscriber_id)));
+        }
+    };
+
+    let mut transaction = db_pool.begin().await.map_err(e500)?;
+    match update_subscriber(&mut transaction, subscriber_id, &name, &email).await {
+        Ok(true) => {}
+        Ok(false) => return Ok(HttpResponse::NotFound().finish()),
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            FlashMessage::error(format!(
+                "Another subscriber already uses the address {}.",
+                email.as_ref()
+            ))
+            .send();
+            return Ok(see_other(&subscriber_path(subscriber_id)));
+        }
+        Err(e) => return Err(e500(e)),
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to edit a subscriber.")
+        .map_err(e500)?;
+
+    FlashMessage::info("The subscriber has been updated.").send();
+    Ok(see_other(&subscriber_path(subscriber_id)))
+}
+
+// Deliveries are keyed by address, so pending and past ones follow the subscriber to the new one,
+// as do emails still waiting in the outbox (confirmations, onboarding emails).
+async fn update_subscriber(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    name: &SubscriberName,
+    email: &SubscriberEmail,
+) -> Result<bool, sqlx::Error> {
+    let Some(previous) = sqlx::query!(
+        r#"
+        SELECT email FROM subscriptions WHERE id = $1 FOR UPDATE
+        "#,
+        subscriber_id
+    )
+    .fetch_optional(&mut **transaction)
+    .await?
+    else {
+        return Ok(false);
+    };
+
+    sqlx::query!(
+        r#"
+        UPDATE subscriptions SET name = $2, email = $3 WHERE id = $1
+        "#,
+        subscriber_id,
+        name.as_ref(),
+        email.as_ref()
+    )
+    .execute(&mut **transaction)
+    .await?;
+    sqlx::query!(
+        r#"
+        UPDATE issue_delivery_queue SET subscriber_email = $2
+        WHERE lower(subscriber_email) = lower($1)
+        "#,
+        previous.email,
+        email.as_ref()
+    )
+    .execute(&mut **transaction)
+    .await?;
+    sqlx::query!(
+        r#"
+        UPDATE issue_delivery_log SET subscriber_email = $2
+        WHERE lower(subscriber_email) = lower($1)
+        "#,
+        previous.email,
+        email.as_ref()
+    )
+    .execute(&mut **transaction)
+    .await?;
+    sqlx::query!(
+        r#"
+        UPDATE email_outbox SET recipient = $2
+        WHERE lower(recipient) = lower($1)
+        "#,
+        previous.email,
+        email.as_ref()
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    Ok(true)
+}
+
+#[tracing::instrument(
+    name = "Deleting a subscriber from the admin area",
+    skip(db_pool, user_id),
+    fields(user_id=%&*user_id)
+)]
+pub async fn admin_delete_subscriber(
+    subscriber_id: web::Path<Uuid>,
+    db_pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut transaction = db_pool.begin().await.map_err(e500)?;
+    let deleted = erase_subscriber(&mut transaction, *subscriber_id, ErasureRequester::Admin)
+        .await
+        .map_err(e500)?;
+    transaction.commit().await.map_err(e500)?;
+
+    if !deleted {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    FlashMessage::info("The subscriber and all of their data have been deleted.").send();
+    Ok(see_other("/admin/subscribers"))
+}
@@ -0,0 +1,141 @@
+use actix_web::{HttpResponse, cookie::Cookie, http::header::ContentType, web};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use crate::{
+    domain::{AttributeDefinition, AttributeKind},
+    routes::{
+        get_attribute_definitions,
+        helpers::{e500, get_message, render_html_template, see_other},
+    },
+};
+
+const ATTRIBUTES_PATH: &str = "/admin/subscribers/attributes";
+
+pub async fn subscriber_attributes_page(
+    db_pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let attributes = get_attribute_definitions(&**db_pool).await.map_err(e500)?;
+
+    let mut ctx = tera::Context::new();
+    ctx.insert("message", &get_message(flash_messages, None));
+    ctx.insert("attributes", &attributes);
+    let page = render_html_template(&ctx, "subscriber_attributes.html");
+
+    let mut response = HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(page);
+    response
+        .add_removal_cookie(&Cookie::new("_flash", ""))
+        .unwrap();
+    Ok(response)
+}
+
+#[derive(Deserialize)]
+pub struct AttributeFormData {
+    key: String,
+    label: String,
+    kind: String,
+    #[serde(default)]
+    required: Option<String>,
+    #[serde(default)]
+    max_length: String,
+    // Comma-separated, only used by choice attributes.
+    #[serde(default)]
+    choices: String,
+}
+
+impl TryFrom<AttributeFormData> for AttributeDefinition {
+    type Error = String;
+
+    fn try_from(form: AttributeFormData) -> Result<Self, Self::Error> {
+        let kind = AttributeKind::try_from(form.kind)?;
+        let max_length = match form.max_length.trim() {
+            "" => None,
+            l => Some(
+                l.parse()
+                    .map_err(|_| "The maximum length must be a positive number.".to_string())?,
+            ),
+        };
+        let choices = form.choices.split(',').map(str::to_string).collect();
+        AttributeDefinition::parse(
+            form.key,
+            form.label,
+            kind,
+            form.required.is_some(),
+            max_length,
+            choices,
+        )
+    }
+}
+
+#[tracing::instrument(name = "Adding a subscriber attribute", skip(form, db_pool))]
+pub async fn add_subscriber_attribute(
+    form: web::Form<AttributeFormData>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let definition: AttributeDefinition = match form.0.try_into() {
+        Ok(definition) => definition,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other(ATTRIBUTES_PATH));
+        }
+    };
+
+    let n_inserted = sqlx::query!(
+        r#"
+        INSERT INTO subscriber_attribute_definitions
+            (key, label, kind, required, max_length, choices)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT DO NOTHING
+        "#,
+        definition.key,
+        definition.label,
+        definition.kind.as_str(),
+        definition.required,
+        definition.max_length,
+        &definition.choices
+    )
+    .execute(&**db_pool)
+    .await
+    .context("Failed to store the attribute definition.")
+    .map_err(e500)?
+    .rows_affected();
+
+    if n_inserted == 0 {
+        FlashMessage::error(format!("The attribute {} already exists.", definition.key)).send();
+    } else {
+        FlashMessage::info(format!("The attribute {} has been added.", definition.key)).send();
+    }
+    Ok(see_other(ATTRIBUTES_PATH))
+}
+
+#[tracing::instrument(name = "Removing a subscriber attribute", skip(db_pool))]
+pub async fn delete_subscriber_attribute(
+    key: web::Path<String>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let key = key.into_inner();
+    let mut transaction = db_pool.begin().await.map_err(e500)?;
+    sqlx::query!(
+        r#"DELETE FROM subscriber_attribute_definitions WHERE key = $1"#,
+        key
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(e500)?;
+    sqlx::query!(
+        r#"UPDATE subscriptions SET attributes = attributes - $1 WHERE attributes ? $1"#,
+        key
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(e500)?;
+    transaction.commit().await.map_err(e500)?;
+
+    FlashMessage::info(format!("The attribute {key} has been removed.")).send();
+    Ok(see_other(ATTRIBUTES_PATH))
+}
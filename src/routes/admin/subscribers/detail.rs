@@ -0,0 +1,180 @@
+use actix_web::{HttpResponse, cookie::Cookie, http::header::ContentType, web};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::routes::helpers::{e500, get_message, render_html_template};
+
+const HISTORY_LIMIT: i64 = 100;
+
+#[derive(Serialize)]
+struct SubscriberDetail {
+    id: String,
+    email: String,
+    name: String,
+    status: String,
+    subscribed_at: String,
+    confirmed_at: Option<String>,
+    source: Option<String>,
+    signup_flag: Option<String>,
+    attributes: String,
+}
+
+#[derive(Serialize)]
+struct TokenRow {
+    kind: String,
+    token: String,
+    created_at: Option<String>,
+}
+
+#[derive(Serialize)]
+struct DeliveryRow {
+    title: String,
+    outcome: String,
+    at: Option<String>,
+}
+
+fn format_time(t: DateTime<Utc>) -> String {
+    t.format("%Y-%m-%d %H:%M").to_string()
+}
+
+pub async fn subscriber_detail(
+    subscriber_id: web::Path<Uuid>,
+    db_pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let subscriber_id = subscriber_id.into_inner();
+    let Some(subscriber) = get_subscriber(&db_pool, subscriber_id)
+        .await
+        .map_err(e500)?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    let tokens = get_tokens(&db_pool, subscriber_id).await.map_err(e500)?;
+    let deliveries = get_delivery_history(&db_pool, subscriber_id)
+        .await
+        .map_err(e500)?;
+
+    let mut ctx = tera::Context::new();
+    ctx.insert("message", &get_message(flash_messages, None));
+    ctx.insert("subscriber", &subscriber);
+    ctx.insert("tokens", &tokens);
+    ctx.insert("deliveries", &deliveries);
+    let page = render_html_template(&ctx, "subscriber_detail.html");
+
+    let mut response = HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(page);
+    response
+        .add_removal_cookie(&Cookie::new("_flash", ""))
+        .unwrap();
+    Ok(response)
+}
+
+#[tracing::instrument(name = "Getting subscriber details", skip(pool))]
+async fn get_subscriber(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+) -> Result<Option<SubscriberDetail>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT
+            id, email, name, status, subscribed_at, confirmed_at, attributes, signup_flag,
+            coalesce(signup_source, utm_source) AS source
+        FROM subscriptions
+        WHERE id = $1
+        "#,
+        subscriber_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to read the subscriber.")?;
+
+    Ok(row.map(|r| SubscriberDetail {
+        id: r.id.to_string(),
+        email: r.email,
+        name: r.name,
+        status: r.status,
+        subscribed_at: format_time(r.subscribed_at),
+        confirmed_at: r.confirmed_at.map(format_time),
+        source: r.source,
+        signup_flag: r.signup_flag,
+        attributes: r.attributes.to_string(),
+    }))
+}
+
+#[tracing::instrument(name = "Getting subscriber tokens", skip(pool))]
+async fn get_tokens(pool: &PgPool, subscriber_id: Uuid) -> Result<Vec<TokenRow>, anyhow::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT 'confirmation' AS "kind!", subscription_token AS "token!", NULL::timestamptz AS created_at
+        FROM subscription_tokens
+        WHERE subscriber_id = $1
+        UNION ALL
+        SELECT 'data ' || kind, data_request_token, created_at
+        FROM data_request_tokens
+        WHERE subscriber_id = $1
+        ORDER BY 3 DESC NULLS FIRST
+        "#,
+        subscriber_id
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to read the subscriber's tokens.")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| TokenRow {
+            kind: r.kind,
+            token: r.token,
+            created_at: r.created_at.map(format_time),
+        })
+        .collect())
+}
+
+// Newsletter issues (sent, failed, skipped or still queued) and onboarding emails, newest first.
+#[tracing::instrument(name = "Getting subscriber delivery history", skip(pool))]
+async fn get_delivery_history(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+) -> Result<Vec<DeliveryRow>, anyhow::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT i.title AS "title!", l.outcome AS "outcome!", l.attempted_at AS at
+        FROM issue_delivery_log l
+        JOIN newsletter_issues i USING (newsletter_issue_id)
+        JOIN subscriptions s ON lower(s.email) = lower(l.subscriber_email)
+        WHERE s.id = $1
+        UNION ALL
+        SELECT i.title, 'queued', NULL
+        FROM issue_delivery_queue q
+        JOIN newsletter_issues i USING (newsletter_issue_id)
+        JOIN subscriptions s ON lower(s.email) = lower(q.subscriber_email)
+        WHERE s.id = $1
+        UNION ALL
+        SELECT st.subject, 'onboarding ' || d.status, coalesce(d.sent_at, d.send_after)
+        FROM subscriber_drip_emails d
+        JOIN drip_steps st USING (drip_step_id)
+        WHERE d.subscriber_id = $1
+        ORDER BY 3 DESC NULLS FIRST
+        LIMIT $2
+        "#,
+        subscriber_id,
+        HISTORY_LIMIT
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to read the subscriber's delivery history.")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| DeliveryRow {
+            title: r.title,
+            outcome: r.outcome,
+            at: r.at.map(format_time),
+        })
+        .collect())
+}
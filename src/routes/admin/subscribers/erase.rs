@@ -0,0 +1,62 @@
+use actix_web::{HttpResponse, web};
+use actix_web_flash_messages::FlashMessage;
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use crate::{
+    authentication::UserId,
+    domain::SubscriberEmail,
+    routes::{
+        ErasureRequester, erase_subscriber, find_subscriber_id_by_email,
+        helpers::{e500, see_other},
+    },
+};
+
+#[derive(Deserialize)]
+pub struct EraseSubscriberFormData {
+    email: String,
+}
+
+#[tracing::instrument(
+    name = "Erasing a subscriber from the admin area",
+    skip(form, db_pool, user_id),
+    fields(user_id=%&*user_id)
+)]
+pub async fn admin_erase_subscriber(
+    form: web::Form<EraseSubscriberFormData>,
+    db_pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let email = match SubscriberEmail::parse(form.0.email) {
+        Ok(email) => email,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other("/admin/dashboard"));
+        }
+    };
+
+    let mut transaction = db_pool.begin().await.map_err(e500)?;
+    let erased = match find_subscriber_id_by_email(&mut *transaction, &email)
+        .await
+        .map_err(e500)?
+    {
+        Some(subscriber_id) => {
+            erase_subscriber(&mut transaction, subscriber_id, ErasureRequester::Admin)
+                .await
+                .map_err(e500)?
+        }
+        None => false,
+    };
+    transaction.commit().await.map_err(e500)?;
+
+    if erased {
+        FlashMessage::info(format!("{} has been erased.", email.as_ref())).send();
+    } else {
+        FlashMessage::error(format!(
+            "There is no subscriber with the address {}.",
+            email.as_ref()
+        ))
+        .send();
+    }
+    Ok(see_other("/admin/dashboard"))
+}
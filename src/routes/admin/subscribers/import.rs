@@ -0,0 +1,254 @@
+use actix_multipart::form::{MultipartForm, MultipartFormConfig, bytes::Bytes, text::Text};
+use actix_web::{HttpResponse, cookie::Cookie, http::header::ContentType, web};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    authentication::UserId,
+    routes::helpers::{e500, get_message, render_html_template, see_other},
+    subscriber_imports::{ImportMode, ImportSource, StoredImport, store_import},
+};
+
+const IMPORTS_PATH: &str = "/admin/subscribers/import";
+const MAX_UPLOAD_BYTES: usize = 50 * 1024 * 1024;
+const RECENT_IMPORTS: i64 = 20;
+const INVALID_ROWS_SHOWN: i64 = 500;
+
+/// The default limits are too small for a list of tens of thousands of addresses.
+pub fn import_upload_config() -> MultipartFormConfig {
+    MultipartFormConfig::default()
+        .total_limit(MAX_UPLOAD_BYTES)
+        .memory_limit(MAX_UPLOAD_BYTES)
+}
+
+#[derive(MultipartForm)]
+pub struct ImportFormData {
+    file: Bytes,
+    /// Missing on forms from before other platforms were supported.
+    source: Option<Text<String>>,
+    mode: Text<String>,
+    consent: Option<Text<String>>,
+}
+
+#[derive(Serialize)]
+struct ImportSummary {
+    import_id: String,
+    file_name: String,
+    source: String,
+    mode: String,
+    status: String,
+    created_at: String,
+    total: i64,
+    processed: i64,
+    imported: i64,
+    duplicates: i64,
+    invalid: i64,
+}
+
+#[derive(Serialize)]
+struct InvalidRow {
+    file_name: String,
+    line_number: i32,
+    email: String,
+    error: String,
+}
+
+fn format_time(t: DateTime<Utc>) -> String {
+    t.format("%Y-%m-%d %H:%M").to_string()
+}
+
+pub async fn subscriber_imports_page(
+    db_pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let imports = get_import_summaries(&db_pool, None).await.map_err(e500)?;
+
+    let mut ctx = tera::Context::new();
+    ctx.insert("message", &get_message(flash_messages, None));
+    ctx.insert("imports", &imports);
+    let page = render_html_template(&ctx, "subscriber_imports.html");
+
+    let mut response = HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(page);
+    response
+        .add_removal_cookie(&Cookie::new("_flash", ""))
+        .unwrap();
+    Ok(response)
+}
+
+// Only the file is read here, the subscribers themselves are created by the background worker.
+#[tracing::instrument(
+    name = "Uploading a subscriber import",
+    skip(form, db_pool, user_id),
+    fields(user_id=%&*user_id)
+)]
+pub async fn upload_subscriber_import(
+    MultipartForm(form): MultipartForm<ImportFormData>,
+    db_pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let source = form.source.map_or(Ok(ImportSource::Csv), |s| {
+        ImportSource::try_from(s.into_inner())
+    });
+    let mode = ImportMode::try_from(form.mode.into_inner());
+    let (source, mode) = match source.and_then(|source| Ok((source, mode?))) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other(IMPORTS_PATH));
+        }
+    };
+    if mode == ImportMode::Confirmed && form.consent.is_none() {
+        FlashMessage::error(
+            "Importing subscribers as confirmed requires attesting that they consented.",
+        )
+        .send();
+        return Ok(see_other(IMPORTS_PATH));
+    }
+    let file_name = form.file.file_name.as_deref().unwrap_or("upload.csv");
+    let parsed = match source.parse_file(file_name, &form.file.data) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other(IMPORTS_PATH));
+        }
+    };
+
+    let mut transaction = db_pool.begin().await.map_err(e500)?;
+    let StoredImport {
+        import_id,
+        added_issues,
+    } = store_import(
+        &mut transaction,
+        file_name,
+        source,
+        mode,
+        **user_id,
+        &parsed,
+    )
+    .await
+    .context("Failed to store the subscriber import.")
+    .map_err(e500)?;
+    transaction.commit().await.map_err(e500)?;
+
+    let mut queued = format!("{} rows have been queued for import.", parsed.rows.len());
+    if added_issues > 0 {
+        queued.push_str(&format!(
+            " {added_issues} past issues have been added to the archive."
+        ));
+    }
+    FlashMessage::info(queued).send();
+    Ok(see_other(&format!("{IMPORTS_PATH}/{import_id}")))
+}
+
+pub async fn subscriber_import_progress(
+    import_id: web::Path<Uuid>,
+    db_pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let import_id = import_id.into_inner();
+    let Some(import) = get_import_summaries(&db_pool, Some(import_id))
+        .await
+        .map_err(e500)?
+        .pop()
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    let invalid_rows = get_invalid_rows(&db_pool, import_id).await.map_err(e500)?;
+
+    let mut ctx = tera::Context::new();
+    ctx.insert("message", &get_message(flash_messages, None));
+    ctx.insert("import", &import);
+    ctx.insert("invalid_rows", &invalid_rows);
+    let page = render_html_template(&ctx, "subscriber_import.html");
+
+    let mut response = HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(page);
+    response
+        .add_removal_cookie(&Cookie::new("_flash", ""))
+        .unwrap();
+    Ok(response)
+}
+
+#[tracing::instrument(name = "Getting subscriber import progress", skip(pool))]
+async fn get_import_summaries(
+    pool: &PgPool,
+    import_id: Option<Uuid>,
+) -> Result<Vec<ImportSummary>, anyhow::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            i.import_id, i.file_name, i.source, i.mode, i.status, i.created_at,
+            count(r.line_number) AS "total!",
+            count(r.outcome) AS "processed!",
+            count(*) FILTER (WHERE r.outcome = 'imported') AS "imported!",
+            count(*) FILTER (WHERE r.outcome = 'duplicate') AS "duplicates!",
+            count(*) FILTER (WHERE r.outcome = 'invalid') AS "invalid!"
+        FROM subscriber_imports i
+        LEFT JOIN subscriber_import_rows r USING (import_id)
+        WHERE $1::uuid IS NULL OR i.import_id = $1
+        GROUP BY i.import_id
+        ORDER BY i.created_at DESC
+        LIMIT $2
+        "#,
+        import_id,
+        RECENT_IMPORTS
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to read subscriber imports.")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| ImportSummary {
+            import_id: r.import_id.to_string(),
+            file_name: r.file_name,
+            source: r.source,
+            mode: r.mode,
+            status: r.status,
+            created_at: format_time(r.created_at),
+            total: r.total,
+            processed: r.processed,
+            imported: r.imported,
+            duplicates: r.duplicates,
+            invalid: r.invalid,
+        })
+        .collect())
+}
+
+#[tracing::instrument(name = "Getting invalid import rows", skip(pool))]
+async fn get_invalid_rows(
+    pool: &PgPool,
+    import_id: Uuid,
+) -> Result<Vec<InvalidRow>, anyhow::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT file_name, line_number, email, error AS "error!"
+        FROM subscriber_import_rows
+        WHERE import_id = $1 AND outcome = 'invalid'
+        ORDER BY file_name, line_number
+        LIMIT $2
+        "#,
+        import_id,
+        INVALID_ROWS_SHOWN
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to read the invalid import rows.")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| InvalidRow {
+            file_name: r.file_name,
+            line_number: r.line_number,
+            email: r.email,
+            error: r.error,
+        })
+        .collect())
+}
@@ -0,0 +1,183 @@
+use actix_web::{HttpResponse, cookie::Cookie, http::header::ContentType, web};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use chrono::{Days, NaiveDate};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::{
+    domain::SubscriptionStatus,
+    routes::helpers::{e400, e500, get_message, render_html_template},
+};
+
+const PAGE_SIZE: i64 = 50;
+
+/// Every field comes straight from the filter form, so blank values mean "no filter".
+#[derive(Deserialize, Serialize, Default)]
+pub struct SubscriberListParameters {
+    #[serde(default)]
+    q: String,
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    from: String,
+    #[serde(default)]
+    to: String,
+    #[serde(default)]
+    flagged: String,
+    #[serde(default, skip_serializing)]
+    page: Option<i64>,
+}
+
+struct SubscriberFilter {
+    search: Option<String>,
+    status: Option<SubscriptionStatus>,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    flagged_only: bool,
+}
+
+impl TryFrom<&SubscriberListParameters> for SubscriberFilter {
+    type Error = String;
+
+    fn try_from(parameters: &SubscriberListParameters) -> Result<Self, Self::Error> {
+        let search = Some(parameters.q.trim())
+            .filter(|q| !q.is_empty())
+            .map(|q| format!("%{}%", escape_like(q)));
+        let status = match parameters.status.trim() {
+            "" => None,
+            s => Some(SubscriptionStatus::try_from(s.to_string())?),
+        };
+        Ok(Self {
+            search,
+            status,
+            from: parse_date(&parameters.from)?,
+            to: parse_date(&parameters.to)?,
+            flagged_only: !parameters.flagged.trim().is_empty(),
+        })
+    }
+}
+
+fn parse_date(raw: &str) -> Result<Option<NaiveDate>, String> {
+    match raw.trim() {
+        "" => Ok(None),
+        d => NaiveDate::parse_from_str(d, "%Y-%m-%d")
+            .map(Some)
+            .map_err(|_| format!("{d} is not a valid date, use YYYY-MM-DD.")),
+    }
+}
+
+fn escape_like(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+#[derive(Serialize)]
+struct SubscriberRow {
+    id: String,
+    email: String,
+    name: String,
+    status: String,
+    subscribed_at: String,
+    signup_flag: Option<String>,
+}
+
+pub async fn list_subscribers(
+    parameters: web::Query<SubscriberListParameters>,
+    db_pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let filter = SubscriberFilter::try_from(&*parameters).map_err(e400)?;
+    let page = parameters.page.unwrap_or(1).max(1);
+    let (subscribers, total) = get_subscribers_page(&db_pool, &filter, page)
+        .await
+        .map_err(e500)?;
+    let n_pages = ((total + PAGE_SIZE - 1) / PAGE_SIZE).max(1);
+    // Pagination links keep the current filters.
+    let filter_query = serde_urlencoded::to_string(&*parameters).map_err(e500)?;
+
+    let mut ctx = tera::Context::new();
+    ctx.insert("message", &get_message(flash_messages, None));
+    ctx.insert("subscribers", &subscribers);
+    ctx.insert("total", &total);
+    ctx.insert("page", &page);
+    ctx.insert("n_pages", &n_pages);
+    ctx.insert("filters", &*parameters);
+    ctx.insert("filter_query", &filter_query);
+    let page = render_html_template(&ctx, "subscribers.html");
+
+    let mut response = HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(page);
+    response
+        .add_removal_cookie(&Cookie::new("_flash", ""))
+        .unwrap();
+    Ok(response)
+}
+
+#[tracing::instrument(name = "Listing subscribers", skip(pool, filter))]
+async fn get_subscribers_page(
+    pool: &PgPool,
+    filter: &SubscriberFilter,
+    page: i64,
+) -> Result<(Vec<SubscriberRow>, i64), anyhow::Error> {
+    let status = filter.status.map(|s| s.as_str());
+    // `to` is inclusive, so the upper bound is the start of the following day.
+    let before = filter.to.and_then(|d| d.checked_add_days(Days::new(1)));
+    let total = sqlx::query_scalar!(
+        r#"
+        SELECT count(*) AS "count!"
+        FROM subscriptions
+        WHERE ($1::text IS NULL OR email ILIKE $1 OR name ILIKE $1)
+            AND ($2::text IS NULL OR status = $2)
+            AND ($3::date IS NULL OR subscribed_at >= $3)
+            AND ($4::date IS NULL OR subscribed_at < $4)
+            AND (NOT $5 OR signup_flag IS NOT NULL)
+        "#,
+        filter.search,
+        status,
+        filter.from,
+        before,
+        filter.flagged_only
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to count subscribers.")?;
+    let rows = sqlx::query!(
+        r#"
+        SELECT id, email, name, status, subscribed_at, signup_flag
+        FROM subscriptions
+        WHERE ($1::text IS NULL OR email ILIKE $1 OR name ILIKE $1)
+            AND ($2::text IS NULL OR status = $2)
+            AND ($3::date IS NULL OR subscribed_at >= $3)
+            AND ($4::date IS NULL OR subscribed_at < $4)
+            AND (NOT $5 OR signup_flag IS NOT NULL)
+        ORDER BY subscribed_at DESC, id
+        LIMIT $6 OFFSET $7
+        "#,
+        filter.search,
+        status,
+        filter.from,
+        before,
+        filter.flagged_only,
+        PAGE_SIZE,
+        (page - 1) * PAGE_SIZE
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to read the list of subscribers.")?;
+
+    let subscribers = rows
+        .into_iter()
+        .map(|r| SubscriberRow {
+            id: r.id.to_string(),
+            email: r.email,
+            name: r.name,
+            status: r.status,
+            subscribed_at: r.subscribed_at.format("%Y-%m-%d %H:%M").to_string(),
+            signup_flag: r.signup_flag,
+        })
+        .collect();
+    Ok((subscribers, total))
+}
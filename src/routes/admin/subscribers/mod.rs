@@ -0,0 +1,13 @@
+mod actions;
+mod attributes;
+mod detail;
+mod erase;
+mod import;
+mod list;
+
+pub use actions::*;
+pub use attributes::*;
+pub use detail::*;
+pub use erase::*;
+pub use import::*;
+pub use list::*;
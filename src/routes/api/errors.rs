@@ -0,0 +1,83 @@
+use actix_web::{
+    HttpRequest, HttpResponse, ResponseError,
+    error::JsonPayloadError,
+    http::{StatusCode, header::RETRY_AFTER},
+};
+
+use crate::routes::{SubscribeError, helpers::error_chain_fmt};
+
+#[derive(thiserror::Error)]
+pub enum ApiError {
+    #[error("{0}")]
+    InvalidPayload(String),
+    #[error("This email address is already subscribed.")]
+    AlreadySubscribed,
+    #[error(transparent)]
+    Subscribe(#[from] SubscribeError),
+}
+
+impl std::fmt::Debug for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ApiError {
+    fn code(&self) -> &'static str {
+        match self {
+            ApiError::InvalidPayload(_) => "invalid_payload",
+            ApiError::AlreadySubscribed => "already_subscribed",
+            ApiError::Subscribe(SubscribeError::ValidationError(_)) => "validation_error",
+            ApiError::Subscribe(SubscribeError::SuspectedBot) => "suspected_bot",
+            ApiError::Subscribe(SubscribeError::RateLimited { .. }) => "rate_limited",
+            ApiError::Subscribe(SubscribeError::UnexpectedError(_)) => "internal_error",
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            ApiError::Subscribe(SubscribeError::UnexpectedError(_)) => {
+                "Something went wrong.".to_string()
+            }
+            e => e.to_string(),
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct ErrorBody {
+    error: ErrorDetails,
+}
+
+#[derive(serde::Serialize)]
+struct ErrorDetails {
+    code: &'static str,
+    message: String,
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::InvalidPayload(_) => StatusCode::BAD_REQUEST,
+            ApiError::AlreadySubscribed => StatusCode::CONFLICT,
+            ApiError::Subscribe(e) => e.status_code(),
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        if let ApiError::Subscribe(SubscribeError::RateLimited { retry_after }) = self {
+            response.insert_header((RETRY_AFTER, retry_after.as_secs().to_string()));
+        }
+        response.json(ErrorBody {
+            error: ErrorDetails {
+                code: self.code(),
+                message: self.message(),
+            },
+        })
+    }
+}
+
+pub fn json_error_handler(e: JsonPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    ApiError::InvalidPayload(e.to_string()).into()
+}
@@ -0,0 +1,5 @@
+mod errors;
+mod subscriptions;
+
+pub use errors::*;
+pub use subscriptions::*;
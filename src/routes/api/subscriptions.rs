@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+
+use actix_web::{HttpRequest, HttpResponse, web};
+use sqlx::PgPool;
+
+use super::errors::ApiError;
+use crate::{
+    domain::{NewSubscriber, RawSignupAttribution, SignupAttribution, SignupPolicy},
+    rate_limiter::RateLimiter,
+    routes::{
+        SubscribeError, SubscribeOutcome, enforce_signup_policy, enforce_subscribe_rate_limits,
+        get_attribute_definitions, process_subscription, referer_header,
+    },
+    startup::ApplicationBaseURL,
+};
+
+#[derive(serde::Deserialize)]
+pub struct SubscribeRequest {
+    pub name: String,
+    pub email: String,
+    #[serde(default)]
+    pub attributes: HashMap<String, AttributeValue>,
+    #[serde(flatten)]
+    pub attribution: RawSignupAttribution,
+}
+
+// JSON clients may send numbers as numbers rather than strings.
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+pub enum AttributeValue {
+    Text(String),
+    Number(serde_json::Number),
+}
+
+impl From<AttributeValue> for String {
+    fn from(value: AttributeValue) -> Self {
+        match value {
+            AttributeValue::Text(s) => s,
+            AttributeValue::Number(n) => n.to_string(),
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct SubscribeResponse {
+    status: &'static str,
+    message: &'static str,
+}
+
+impl SubscribeResponse {
+    fn pending_confirmation() -> Self {
+        Self {
+            status: "pending_confirmation",
+            message: "Please check your inbox to confirm the subscription.",
+        }
+    }
+}
+
+#[tracing::instrument(
+    name = "Adding a new subscriber via the JSON API.",
+    skip(req, body, db_pool, base_url, rate_limiter, signup_policy),
+    fields(
+        subscriber_email = %body.email,
+        subscriber_name = %body.name
+    )
+)]
+pub async fn api_subscribe(
+    req: HttpRequest,
+    body: web::Json<SubscribeRequest>,
+    db_pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseURL>,
+    rate_limiter: web::Data<RateLimiter>,
+    signup_policy: web::Data<SignupPolicy>,
+) -> Result<HttpResponse, ApiError> {
+    let definitions = get_attribute_definitions(&**db_pool)
+        .await
+        .map_err(SubscribeError::UnexpectedError)?;
+    let SubscribeRequest {
+        name,
+        email,
+        attributes,
+        attribution,
+    } = body.0;
+    let attributes = attributes.into_iter().map(|(k, v)| (k, v.into())).collect();
+    let attribution = SignupAttribution::parse(attribution, referer_header(&req).as_deref());
+    let mut new_subscriber =
+        NewSubscriber::parse(name, email, attributes, &definitions, attribution)
+            .map_err(SubscribeError::ValidationError)?;
+    new_subscriber.signup_flag = enforce_signup_policy(&signup_policy, &new_subscriber.email)?;
+    let client_ip = rate_limiter.client_ip(&req);
+    enforce_subscribe_rate_limits(&rate_limiter, client_ip.as_deref(), &new_subscriber.email)
+        .await?;
+
+    match process_subscription(new_subscriber, &db_pool, &base_url).await? {
+        SubscribeOutcome::Created => {
+            Ok(HttpResponse::Created().json(SubscribeResponse::pending_confirmation()))
+        }
+        SubscribeOutcome::ConfirmationResent | SubscribeOutcome::Resubscribed => {
+            Ok(HttpResponse::Ok().json(SubscribeResponse::pending_confirmation()))
+        }
+        SubscribeOutcome::AlreadySubscribed => Err(ApiError::AlreadySubscribed),
+    }
+}
@@ -21,8 +21,12 @@ pub fn prepare_html_template(entries: &[(&str, &str)], template_name: &str) -> S
     for (key, value) in entries.iter().copied() {
         ctx.insert(key, value);
     }
+    render_html_template(&ctx, template_name)
+}
+
+pub fn render_html_template(ctx: &tera::Context, template_name: &str) -> String {
     let tera = tera::Tera::new("views/**/*").expect("Failed to initialize Tera templates");
-    tera.render(template_name, &ctx)
+    tera.render(template_name, ctx)
         .expect("Failed rendering email template")
 }
 
@@ -1,7 +1,35 @@
-use actix_web::{HttpResponse, http::header::ContentType};
+use actix_web::{HttpRequest, HttpResponse, http::header::ContentType, web};
+use sqlx::{PgPool, types::chrono::Utc};
 
-pub async fn home() -> HttpResponse {
-    HttpResponse::Ok()
+use crate::{
+    bot_protection::BotProtection,
+    domain::{RawSignupAttribution, SignupAttribution},
+    routes::{
+        get_attribute_definitions,
+        helpers::{e500, render_html_template},
+        referer_header,
+    },
+};
+
+pub async fn home(
+    req: HttpRequest,
+    query: web::Query<RawSignupAttribution>,
+    bot_protection: web::Data<BotProtection>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    // Carry the landing page's UTM parameters and referrer through to the subscribe form.
+    let attribution = SignupAttribution::parse(query.into_inner(), referer_header(&req).as_deref());
+    let challenge = bot_protection.issue_challenge(Utc::now().timestamp());
+    let attributes = get_attribute_definitions(&**db_pool).await.map_err(e500)?;
+
+    let mut ctx = tera::Context::new();
+    ctx.insert("form_token", &challenge.token);
+    ctx.insert("pow_challenge", &challenge.challenge);
+    ctx.insert("pow_difficulty", &challenge.difficulty);
+    ctx.insert("attributes", &attributes);
+    ctx.insert("attribution", &attribution);
+    let page = render_html_template(&ctx, "home.html");
+    Ok(HttpResponse::Ok()
         .content_type(ContentType::html())
-        .body(include_str!("../../../views/home.html"))
+        .body(page))
 }
@@ -0,0 +1,166 @@
+use actix_web::{HttpRequest, HttpResponse, web};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use super::caching::Validators;
+use crate::{
+    issue_authoring::sanitize_html,
+    issue_rendering::{RecipientContext, render_for_recipient},
+    routes::helpers::{e500, render_html_template},
+};
+
+const PAGE_SIZE: i64 = 20;
+const HTML: &str = "text/html; charset=utf-8";
+
+#[derive(Deserialize)]
+pub struct ArchiveParameters {
+    page: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct ArchiveEntry {
+    slug: String,
+    title: String,
+    published_on: String,
+}
+
+#[derive(Serialize)]
+struct PublicIssue {
+    title: String,
+    published_on: String,
+    html_content: String,
+}
+
+/// The newest change to any issue, private ones included, so hiding an issue invalidates
+/// cached pages too.
+pub(super) struct ArchiveVersion {
+    pub n_public: i64,
+    pub last_modified: DateTime<Utc>,
+}
+
+pub(super) async fn get_archive_version(pool: &PgPool) -> Result<ArchiveVersion, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT
+            count(*) FILTER (WHERE NOT is_private) AS "n_public!",
+            coalesce(max(updated_at), 'epoch') AS "last_modified!"
+        FROM newsletter_issues
+        "#
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to read the archive version.")?;
+    Ok(ArchiveVersion {
+        n_public: row.n_public,
+        last_modified: row.last_modified,
+    })
+}
+
+pub async fn issue_archive(
+    req: HttpRequest,
+    parameters: web::Query<ArchiveParameters>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let page = parameters.page.unwrap_or(1).max(1);
+    let version = get_archive_version(&db_pool).await.map_err(e500)?;
+    let validators = Validators::new(
+        &format!(
+            "issues-{page}-{}-{}",
+            version.n_public,
+            version.last_modified.timestamp_micros()
+        ),
+        version.last_modified,
+    );
+    // Clients that already have this version skip the query and the rendering.
+    let issues = if validators.is_fresh(&req) {
+        vec![]
+    } else {
+        get_archive_page(&db_pool, page).await.map_err(e500)?
+    };
+    let n_pages = ((version.n_public + PAGE_SIZE - 1) / PAGE_SIZE).max(1);
+
+    Ok(validators.respond(&req, HTML, || {
+        let mut ctx = tera::Context::new();
+        ctx.insert("issues", &issues);
+        ctx.insert("page", &page);
+        ctx.insert("n_pages", &n_pages);
+        render_html_template(&ctx, "issues.html")
+    }))
+}
+
+pub async fn public_issue(
+    req: HttpRequest,
+    slug: web::Path<String>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let Some(issue) = sqlx::query!(
+        r#"
+        SELECT
+            title, html_content, text_content, updated_at,
+            published_at::timestamptz AS "published_at!"
+        FROM newsletter_issues
+        WHERE slug = $1 AND NOT is_private
+        "#,
+        slug.as_str()
+    )
+    .fetch_optional(&**db_pool)
+    .await
+    .context("Failed to read a public issue.")
+    .map_err(e500)?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+
+    let validators = Validators::new(
+        &format!("issue-{}-{}", slug, issue.updated_at.timestamp_micros()),
+        issue.updated_at,
+    );
+    Ok(validators.respond(&req, HTML, || {
+        // Personalisation falls back to the template defaults, there is no subscriber here.
+        let rendered = render_for_recipient(
+            &issue.html_content,
+            &issue.text_content,
+            &RecipientContext::anonymous(""),
+        );
+        let mut ctx = tera::Context::new();
+        ctx.insert(
+            "issue",
+            &PublicIssue {
+                title: issue.title,
+                published_on: issue.published_at.format("%Y-%m-%d").to_string(),
+                // Issues stored before sanitising was introduced are cleaned on the way out.
+                html_content: sanitize_html(&rendered.html_content),
+            },
+        );
+        render_html_template(&ctx, "issue.html")
+    }))
+}
+
+#[tracing::instrument(name = "Listing public issues", skip(pool))]
+async fn get_archive_page(pool: &PgPool, page: i64) -> Result<Vec<ArchiveEntry>, anyhow::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT slug, title, published_at::timestamptz AS "published_at!"
+        FROM newsletter_issues
+        WHERE NOT is_private
+        ORDER BY published_at::timestamptz DESC, newsletter_issue_id
+        LIMIT $1 OFFSET $2
+        "#,
+        PAGE_SIZE,
+        (page - 1) * PAGE_SIZE
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to read the public issues.")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| ArchiveEntry {
+            slug: r.slug,
+            title: r.title,
+            published_on: r.published_at.format("%Y-%m-%d").to_string(),
+        })
+        .collect())
+}
@@ -0,0 +1,69 @@
+use std::time::{Duration, SystemTime};
+
+use actix_web::{
+    HttpRequest, HttpResponse, HttpResponseBuilder,
+    http::header::{
+        CacheControl, CacheDirective, ETag, EntityTag, Header, HttpDate, IF_NONE_MATCH,
+        IfModifiedSince, IfNoneMatch, LastModified,
+    },
+};
+use chrono::{DateTime, Utc};
+
+/// Archive pages are public, so browsers and proxies can keep them as long as they check back.
+pub struct Validators {
+    etag: EntityTag,
+    last_modified: HttpDate,
+}
+
+impl Validators {
+    /// `version` must change whenever the content does, e.g. the latest `updated_at`.
+    pub fn new(version: &str, last_modified: DateTime<Utc>) -> Self {
+        Self {
+            etag: EntityTag::new_strong(version.to_string()),
+            // HTTP dates only have whole seconds, a finer value would never compare as fresh.
+            last_modified: HttpDate::from(
+                SystemTime::UNIX_EPOCH
+                    + Duration::from_secs(last_modified.timestamp().max(0) as u64),
+            ),
+        }
+    }
+
+    // If-None-Match wins when both headers are sent, as RFC 9110 asks.
+    pub fn is_fresh(&self, req: &HttpRequest) -> bool {
+        if req.headers().contains_key(IF_NONE_MATCH) {
+            return match IfNoneMatch::parse(req) {
+                Ok(IfNoneMatch::Any) => true,
+                Ok(IfNoneMatch::Items(tags)) => tags.iter().any(|t| t.weak_eq(&self.etag)),
+                Err(_) => false,
+            };
+        }
+        IfModifiedSince::parse(req).is_ok_and(|since| self.last_modified <= since.0)
+    }
+
+    /// Answers with 304 when the client's copy is still current, otherwise with `body`.
+    pub fn respond(
+        &self,
+        req: &HttpRequest,
+        content_type: &str,
+        body: impl FnOnce() -> String,
+    ) -> HttpResponse {
+        if self.is_fresh(req) {
+            let mut response = HttpResponse::NotModified();
+            self.add_headers(&mut response);
+            return response.finish();
+        }
+        let mut response = HttpResponse::Ok();
+        self.add_headers(&mut response);
+        response.content_type(content_type).body(body())
+    }
+
+    fn add_headers(&self, response: &mut HttpResponseBuilder) {
+        response
+            .insert_header(ETag(self.etag.clone()))
+            .insert_header(LastModified(self.last_modified))
+            .insert_header(CacheControl(vec![
+                CacheDirective::Public,
+                CacheDirective::NoCache,
+            ]));
+    }
+}
@@ -0,0 +1,93 @@
+use actix_web::{HttpRequest, HttpResponse, web};
+use anyhow::Context;
+use serde::Serialize;
+use sqlx::PgPool;
+
+use super::{archive::get_archive_version, caching::Validators};
+use crate::{
+    issue_authoring::sanitize_html,
+    issue_rendering::{RecipientContext, render_for_recipient},
+    routes::helpers::{e500, render_html_template},
+    startup::ApplicationBaseURL,
+};
+
+const FEED_LENGTH: i64 = 20;
+const ATOM: &str = "application/atom+xml; charset=utf-8";
+
+#[derive(Serialize)]
+struct FeedEntry {
+    url: String,
+    title: String,
+    published: String,
+    updated: String,
+    html_content: String,
+}
+
+/// An Atom feed of the latest public issues.
+pub async fn issue_feed(
+    req: HttpRequest,
+    db_pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseURL>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let version = get_archive_version(&db_pool).await.map_err(e500)?;
+    let validators = Validators::new(
+        &format!(
+            "feed-{}-{}",
+            version.n_public,
+            version.last_modified.timestamp_micros()
+        ),
+        version.last_modified,
+    );
+    let entries = if validators.is_fresh(&req) {
+        vec![]
+    } else {
+        get_feed_entries(&db_pool, &base_url.0)
+            .await
+            .map_err(e500)?
+    };
+
+    Ok(validators.respond(&req, ATOM, || {
+        let mut ctx = tera::Context::new();
+        ctx.insert("base_url", &base_url.0);
+        ctx.insert("updated", &version.last_modified.to_rfc3339());
+        ctx.insert("entries", &entries);
+        render_html_template(&ctx, "feed.xml")
+    }))
+}
+
+#[tracing::instrument(name = "Building the issue feed", skip(pool))]
+async fn get_feed_entries(pool: &PgPool, base_url: &str) -> Result<Vec<FeedEntry>, anyhow::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            slug, title, html_content, text_content, updated_at,
+            published_at::timestamptz AS "published_at!"
+        FROM newsletter_issues
+        WHERE NOT is_private
+        ORDER BY published_at::timestamptz DESC, newsletter_issue_id
+        LIMIT $1
+        "#,
+        FEED_LENGTH
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to read the issues for the feed.")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| FeedEntry {
+            url: format!("{base_url}/issues/{}", r.slug),
+            title: r.title,
+            published: r.published_at.to_rfc3339(),
+            updated: r.updated_at.to_rfc3339(),
+            html_content: sanitize_html(
+                &render_for_recipient(
+                    &r.html_content,
+                    &r.text_content,
+                    &RecipientContext::anonymous(""),
+                )
+                .html_content,
+            ),
+        })
+        .collect())
+}
@@ -0,0 +1,6 @@
+mod archive;
+mod caching;
+mod feed;
+
+pub use archive::*;
+pub use feed::*;
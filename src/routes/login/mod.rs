@@ -1,5 +1,7 @@
 mod get;
+mod password_reset;
 mod post;
 
 pub use get::login_form;
+pub use password_reset::*;
 pub use post::login;
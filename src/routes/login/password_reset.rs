@@ -0,0 +1,269 @@
+use actix_web::{HttpResponse, cookie::Cookie, http::header::ContentType, web};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use chrono::{Duration, Utc};
+use secrecy::SecretString;
+use serde::Deserialize;
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+use crate::{
+    authentication::{change_password, end_all_sessions, validate_new_password},
+    domain::{ConfirmationToken, SubscriberEmail},
+    email_outbox::enqueue_email,
+    rate_limiter::{RateLimitDecision, RateLimiter},
+    routes::helpers::{e500, get_message, prepare_html_template, see_other},
+    startup::ApplicationBaseURL,
+    transactional_emails::{TransactionalEmail, render_transactional_email},
+};
+
+const PASSWORD_RESET_PATH: &str = "/login/password-reset";
+const PASSWORD_RESET_TOKEN_TTL: Duration = Duration::hours(1);
+
+#[derive(Deserialize)]
+pub struct PasswordResetRequestFormData {
+    username: String,
+}
+
+#[derive(Deserialize)]
+pub struct PasswordResetParameters {
+    token: ConfirmationToken,
+}
+
+#[derive(Deserialize)]
+pub struct PasswordResetFormData {
+    token: ConfirmationToken,
+    new_password: SecretString,
+    new_password_check: SecretString,
+}
+
+fn reset_form_path(token: &ConfirmationToken) -> String {
+    format!("{PASSWORD_RESET_PATH}/confirm?token={}", token.as_ref())
+}
+
+pub async fn password_reset_request_form(flash_messages: IncomingFlashMessages) -> HttpResponse {
+    let message_string = get_message(flash_messages, None);
+    let page_string = prepare_html_template(
+        &[("message", &message_string)],
+        "password_reset_request_form.html",
+    );
+
+    let mut response = HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(page_string);
+    response
+        .add_removal_cookie(&Cookie::new("_flash", ""))
+        .unwrap();
+    response
+}
+
+#[tracing::instrument(
+    name = "Requesting a password reset",
+    skip(form, db_pool, base_url, rate_limiter),
+    fields(username = %form.username)
+)]
+pub async fn request_password_reset(
+    form: web::Form<PasswordResetRequestFormData>,
+    db_pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseURL>,
+    rate_limiter: web::Data<RateLimiter>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let username = form.0.username.trim();
+
+    match rate_limiter.check_password_reset(username).await {
+        Ok(RateLimitDecision::Allowed) => {
+            send_password_reset_link(&db_pool, &base_url, username)
+                .await
+                .map_err(e500)?;
+        }
+        Ok(RateLimitDecision::Throttled { .. }) => {
+            tracing::warn!("Dropping a throttled password reset request.");
+        }
+        Err(e) => {
+            tracing::error!(
+            error.cause_chain = ?e,
+            error.message = %e,
+            "Failed to check password reset rate limits. Letting the request through.",
+            );
+            send_password_reset_link(&db_pool, &base_url, username)
+                .await
+                .map_err(e500)?;
+        }
+    }
+
+    // The reply doesn't say whether the account exists or has a recovery email.
+    FlashMessage::info(
+        "If the account has a recovery email, we have sent it a link to reset the password.",
+    )
+    .send();
+    Ok(see_other(PASSWORD_RESET_PATH))
+}
+
+async fn send_password_reset_link(
+    db_pool: &PgPool,
+    base_url: &ApplicationBaseURL,
+    username: &str,
+) -> Result<(), anyhow::Error> {
+    let Some(user) = sqlx::query!(
+        r#"
+        SELECT user_id, username, email AS "email!"
+        FROM users
+        WHERE username = $1 AND email IS NOT NULL
+        "#,
+        username
+    )
+    .fetch_optional(db_pool)
+    .await
+    .context("Failed to look up the user.")?
+    else {
+        return Ok(());
+    };
+    let email = SubscriberEmail::parse(user.email).map_err(anyhow::Error::msg)?;
+
+    let mut transaction = db_pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+
+    let token = ConfirmationToken::new();
+    sqlx::query!(
+        r#"
+        INSERT INTO password_reset_tokens (password_reset_token, user_id)
+        VALUES ($1, $2)
+        "#,
+        token.as_ref(),
+        user.user_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to store the password reset token.")?;
+
+    let link = format!("{}{}", base_url.0, reset_form_path(&token));
+    let rendered = render_transactional_email(
+        &mut *transaction,
+        TransactionalEmail::PasswordReset,
+        &[("username", &user.username), ("link", &link)],
+    )
+    .await
+    .context("Failed to render the password reset email.")?;
+    enqueue_email(
+        &mut *transaction,
+        &email,
+        &rendered.subject,
+        &rendered.html,
+        &rendered.text,
+    )
+    .await
+    .context("Failed to queue the password reset email.")?;
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to store a password reset request.")?;
+    Ok(())
+}
+
+#[tracing::instrument(name = "Resolving a password reset token", skip(executor, token))]
+async fn get_user_id_from_reset_token(
+    executor: impl PgExecutor<'_>,
+    token: &ConfirmationToken,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let user_id = sqlx::query_scalar!(
+        r#"
+        SELECT user_id
+        FROM password_reset_tokens
+        WHERE password_reset_token = $1 AND created_at > $2
+        "#,
+        token.as_ref(),
+        Utc::now() - PASSWORD_RESET_TOKEN_TTL
+    )
+    .fetch_optional(executor)
+    .await?;
+    Ok(user_id)
+}
+
+// Every link the user was sent stops working, not just this one. Deleting is what claims the
+// token, so a link submitted twice at once resets the password once.
+#[tracing::instrument(name = "Using up a password reset token", skip(executor, token))]
+async fn consume_reset_token(
+    executor: impl PgExecutor<'_>,
+    token: &ConfirmationToken,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let user_ids = sqlx::query_scalar!(
+        r#"
+        DELETE FROM password_reset_tokens
+        WHERE user_id = (
+            SELECT user_id
+            FROM password_reset_tokens
+            WHERE password_reset_token = $1 AND created_at > $2
+        )
+        RETURNING user_id
+        "#,
+        token.as_ref(),
+        Utc::now() - PASSWORD_RESET_TOKEN_TTL
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(user_ids.into_iter().next())
+}
+
+// The emailed link only renders the form, so mail scanners that follow links don't use it up.
+pub async fn password_reset_form(
+    parameters: web::Query<PasswordResetParameters>,
+    db_pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let token = &parameters.token;
+    if get_user_id_from_reset_token(&**db_pool, token)
+        .await
+        .map_err(e500)?
+        .is_none()
+    {
+        return Ok(HttpResponse::Unauthorized().finish());
+    }
+
+    let message_string = get_message(flash_messages, None);
+    let page_string = prepare_html_template(
+        &[("message", &message_string), ("token", token.as_ref())],
+        "password_reset_form.html",
+    );
+    let mut response = HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(page_string);
+    response
+        .add_removal_cookie(&Cookie::new("_flash", ""))
+        .unwrap();
+    Ok(response)
+}
+
+#[tracing::instrument(name = "Resetting a password", skip(form, db_pool))]
+pub async fn reset_password(
+    form: web::Form<PasswordResetFormData>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let PasswordResetFormData {
+        token,
+        new_password,
+        new_password_check,
+    } = form.into_inner();
+    if let Err(e) = validate_new_password(&new_password, &new_password_check) {
+        FlashMessage::error(e).send();
+        return Ok(see_other(&reset_form_path(&token)));
+    }
+
+    let Some(user_id) = consume_reset_token(&**db_pool, &token)
+        .await
+        .context("Failed to use up the password reset token.")
+        .map_err(e500)?
+    else {
+        return Ok(HttpResponse::Unauthorized().finish());
+    };
+    change_password(user_id, new_password, &db_pool)
+        .await
+        .map_err(e500)?;
+    // Whoever made the reset necessary may still be logged in.
+    end_all_sessions(user_id, &db_pool).await.map_err(e500)?;
+
+    FlashMessage::info("Your password has been reset. You can now log in.").send();
+    Ok(see_other("/login"))
+}
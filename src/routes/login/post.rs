@@ -5,7 +5,7 @@ use serde::Deserialize;
 use sqlx::PgPool;
 
 use crate::{
-    authentication::{AuthError, Credentials, validate_credentials},
+    authentication::{AuthError, Credentials, get_session_generation, validate_credentials},
     routes::helpers::error_chain_fmt,
     session_state::TypedSession,
 };
@@ -48,10 +48,17 @@ pub async fn login(
     match validate_credentials(creds, &pool).await {
         Ok(user_id) => {
             tracing::Span::current().record("user_id", tracing::field::display(&user_id));
+            let generation = get_session_generation(user_id, &pool)
+                .await
+                .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?
+                .unwrap_or_default();
             session.renew();
             session
                 .insert_user_id(user_id)
                 .map_err(|e| login_redirect(LoginError::UnexpectedError(e.into())))?;
+            session
+                .insert_session_generation(generation)
+                .map_err(|e| login_redirect(LoginError::UnexpectedError(e.into())))?;
             Ok(HttpResponse::SeeOther()
                 .insert_header((LOCATION, "/admin/dashboard"))
                 .finish())
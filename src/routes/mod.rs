@@ -1,17 +1,25 @@
 mod admin;
+mod api;
 mod health_check;
 mod helpers;
 mod home;
+mod issues;
 mod login;
+mod subscriber_data;
 // mod newsletters;
 mod subscriptions;
 mod subscriptions_confirm;
+mod subscriptions_unsubscribe;
 
 pub use admin::*;
+pub use api::*;
 pub use health_check::*;
-pub use helpers::{e500, see_other};
+pub use helpers::{e500, render_html_template, see_other};
 pub use home::*;
+pub use issues::*;
 pub use login::*;
+pub use subscriber_data::*;
 // pub use newsletters::*;
 pub use subscriptions::*;
 pub use subscriptions_confirm::*;
+pub use subscriptions_unsubscribe::*;
@@ -0,0 +1,71 @@
+use actix_web::{HttpResponse, http::header::ContentType, web};
+use sqlx::PgPool;
+
+use crate::routes::helpers::{e500, prepare_html_template};
+
+use super::{
+    persistence::{ErasureRequester, erase_subscriber, get_subscriber_id_from_data_request_token},
+    types::{DataRequestKind, DataRequestParameters},
+};
+
+// The emailed link only renders a confirmation form, so mail scanners that follow links
+// can't erase anyone by accident.
+pub async fn erase_subscriber_data_form(
+    parameters: web::Query<DataRequestParameters>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let token = parameters.token.as_ref();
+    if get_subscriber_id_from_data_request_token(&db_pool, token, DataRequestKind::Erase)
+        .await
+        .map_err(e500)?
+        .is_none()
+    {
+        return Ok(HttpResponse::Unauthorized().finish());
+    }
+
+    let page_string = prepare_html_template(&[("token", token)], "erase_data_form.html");
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(page_string))
+}
+
+#[tracing::instrument(name = "Erasing subscriber data on request", skip(form, db_pool))]
+pub async fn erase_subscriber_data(
+    form: web::Form<DataRequestParameters>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let Some(subscriber_id) = get_subscriber_id_from_data_request_token(
+        &db_pool,
+        form.token.as_ref(),
+        DataRequestKind::Erase,
+    )
+    .await
+    .map_err(e500)?
+    else {
+        return Ok(HttpResponse::Unauthorized().finish());
+    };
+
+    let mut transaction = db_pool.begin().await.map_err(e500)?;
+    erase_subscriber(
+        &mut transaction,
+        subscriber_id,
+        ErasureRequester::Subscriber,
+    )
+    .await
+    .map_err(e500)?;
+    transaction.commit().await.map_err(e500)?;
+
+    let page_string = prepare_html_template(
+        &[
+            ("title", "Your data has been erased"),
+            (
+                "message",
+                "We no longer hold your subscription or any of the emails we sent you.",
+            ),
+        ],
+        "subscriber_message.html",
+    );
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(page_string))
+}
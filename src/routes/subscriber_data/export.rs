@@ -0,0 +1,46 @@
+use actix_web::{
+    HttpResponse,
+    http::header::{ContentDisposition, DispositionParam, DispositionType},
+    web,
+};
+use sqlx::PgPool;
+
+use crate::routes::helpers::e500;
+
+use super::{
+    persistence::{get_subscriber_data, get_subscriber_id_from_data_request_token},
+    types::{DataRequestKind, DataRequestParameters},
+};
+
+#[tracing::instrument(name = "Exporting subscriber data", skip(parameters, db_pool))]
+pub async fn export_subscriber_data(
+    parameters: web::Query<DataRequestParameters>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let Some(subscriber_id) = get_subscriber_id_from_data_request_token(
+        &db_pool,
+        parameters.token.as_ref(),
+        DataRequestKind::Export,
+    )
+    .await
+    .map_err(e500)?
+    else {
+        return Ok(HttpResponse::Unauthorized().finish());
+    };
+
+    let Some(data) = get_subscriber_data(&db_pool, subscriber_id)
+        .await
+        .map_err(e500)?
+    else {
+        return Ok(HttpResponse::Unauthorized().finish());
+    };
+
+    Ok(HttpResponse::Ok()
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(
+                "subscriber-data.json".to_string(),
+            )],
+        })
+        .json(data))
+}
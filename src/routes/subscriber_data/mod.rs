@@ -0,0 +1,11 @@
+mod erase;
+mod export;
+mod persistence;
+mod request;
+mod types;
+
+pub use erase::*;
+pub use export::*;
+pub use persistence::{ErasureRequester, erase_subscriber, find_subscriber_id_by_email};
+pub use request::*;
+pub use types::DataRequestKind;
@@ -0,0 +1,338 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use sqlx::{PgExecutor, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::domain::SubscriberEmail;
+
+use super::types::DataRequestKind;
+
+// Links in data request emails stop working after a day.
+const DATA_REQUEST_TOKEN_TTL: Duration = Duration::hours(24);
+
+#[derive(Debug, Clone, Copy)]
+pub enum ErasureRequester {
+    Subscriber,
+    Admin,
+}
+
+impl ErasureRequester {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ErasureRequester::Subscriber => "subscriber",
+            ErasureRequester::Admin => "admin",
+        }
+    }
+}
+
+#[tracing::instrument(name = "Looking up a subscriber by email", skip(executor))]
+pub async fn find_subscriber_id_by_email(
+    executor: impl PgExecutor<'_>,
+    email: &SubscriberEmail,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT id FROM subscriptions WHERE lower(email) = lower($1)
+        "#,
+        email.as_ref()
+    )
+    .fetch_optional(executor)
+    .await?;
+    Ok(row.map(|r| r.id))
+}
+
+#[tracing::instrument(name = "Saving a data request token", skip(executor, token))]
+pub async fn store_data_request_token(
+    executor: impl PgExecutor<'_>,
+    subscriber_id: Uuid,
+    kind: DataRequestKind,
+    token: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO data_request_tokens (data_request_token, subscriber_id, kind)
+        VALUES ($1, $2, $3)
+        "#,
+        token,
+        subscriber_id,
+        kind.as_str()
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+#[tracing::instrument(name = "Resolving a data request token", skip(pool, token))]
+pub async fn get_subscriber_id_from_data_request_token(
+    pool: &PgPool,
+    token: &str,
+    kind: DataRequestKind,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT subscriber_id
+        FROM data_request_tokens
+        WHERE data_request_token = $1 AND kind = $2 AND created_at > $3
+        "#,
+        token,
+        kind.as_str(),
+        Utc::now() - DATA_REQUEST_TOKEN_TTL
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|r| r.subscriber_id))
+}
+
+#[derive(Serialize)]
+pub struct SubscriberDataExport {
+    pub subscriber: ExportedSubscriber,
+    pub subscription_tokens: Vec<String>,
+    pub data_requests: Vec<ExportedDataRequest>,
+    pub deliveries: Vec<ExportedDelivery>,
+    pub pending_deliveries: Vec<Uuid>,
+    pub pending_emails: Vec<ExportedEmail>,
+    pub drip_emails: Vec<ExportedDripEmail>,
+}
+
+#[derive(Serialize)]
+pub struct ExportedSubscriber {
+    pub id: Uuid,
+    pub email: String,
+    pub name: String,
+    pub status: String,
+    pub subscribed_at: DateTime<Utc>,
+    pub confirmed_at: Option<DateTime<Utc>>,
+    pub attributes: serde_json::Value,
+    pub signup_source: Option<String>,
+    pub referrer: Option<String>,
+    pub utm_source: Option<String>,
+    pub utm_medium: Option<String>,
+    pub utm_campaign: Option<String>,
+    pub utm_term: Option<String>,
+    pub utm_content: Option<String>,
+    pub signup_flag: Option<String>,
+    pub pending_since: Option<DateTime<Utc>>,
+    pub confirmation_reminder_sent_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+pub struct ExportedDataRequest {
+    pub kind: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct ExportedDelivery {
+    pub newsletter_issue_id: Uuid,
+    pub title: String,
+    pub outcome: String,
+    pub attempted_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct ExportedDripEmail {
+    pub subject: String,
+    pub status: String,
+    pub send_after: DateTime<Utc>,
+    pub sent_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+pub struct ExportedEmail {
+    pub subject: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(name = "Collecting subscriber data for export", skip(pool))]
+pub async fn get_subscriber_data(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+) -> Result<Option<SubscriberDataExport>, sqlx::Error> {
+    let Some(subscriber) = sqlx::query_as!(
+        ExportedSubscriber,
+        r#"
+        SELECT
+            id, email, name, status, subscribed_at, confirmed_at, attributes,
+            signup_source, referrer, utm_source, utm_medium, utm_campaign, utm_term, utm_content,
+            signup_flag, pending_since, confirmation_reminder_sent_at
+        FROM subscriptions
+        WHERE id = $1
+        "#,
+        subscriber_id
+    )
+    .fetch_optional(pool)
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    let subscription_tokens = sqlx::query!(
+        r#"
+        SELECT subscription_token FROM subscription_tokens WHERE subscriber_id = $1
+        "#,
+        subscriber_id
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|r| r.subscription_token)
+    .collect();
+
+    let data_requests = sqlx::query_as!(
+        ExportedDataRequest,
+        r#"
+        SELECT kind, created_at
+        FROM data_request_tokens
+        WHERE subscriber_id = $1
+        ORDER BY created_at
+        "#,
+        subscriber_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let deliveries = sqlx::query_as!(
+        ExportedDelivery,
+        r#"
+        SELECT l.newsletter_issue_id, i.title, l.outcome, l.attempted_at
+        FROM issue_delivery_log l
+        JOIN newsletter_issues i USING (newsletter_issue_id)
+        WHERE lower(l.subscriber_email) = lower($1)
+        ORDER BY l.attempted_at
+        "#,
+        subscriber.email
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let pending_deliveries = sqlx::query!(
+        r#"
+        SELECT newsletter_issue_id
+        FROM issue_delivery_queue
+        WHERE lower(subscriber_email) = lower($1)
+        "#,
+        subscriber.email
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|r| r.newsletter_issue_id)
+    .collect();
+
+    let pending_emails = sqlx::query_as!(
+        ExportedEmail,
+        r#"
+        SELECT subject, created_at
+        FROM email_outbox
+        WHERE lower(recipient) = lower($1)
+        ORDER BY created_at
+        "#,
+        subscriber.email
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let drip_emails = sqlx::query_as!(
+        ExportedDripEmail,
+        r#"
+        SELECT st.subject, d.status, d.send_after, d.sent_at
+        FROM subscriber_drip_emails d
+        JOIN drip_steps st USING (drip_step_id)
+        WHERE d.subscriber_id = $1
+        ORDER BY d.send_after
+        "#,
+        subscriber_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(Some(SubscriberDataExport {
+        subscriber,
+        subscription_tokens,
+        data_requests,
+        deliveries,
+        pending_deliveries,
+        pending_emails,
+        drip_emails,
+    }))
+}
+
+/// Removes every row that refers to the subscriber, including queued emails and the
+/// delivery history keyed by their address. Returns `false` if there was nothing to erase.
+#[tracing::instrument(name = "Erasing a subscriber", skip(transaction))]
+pub async fn erase_subscriber(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    requested_by: ErasureRequester,
+) -> Result<bool, sqlx::Error> {
+    let Some(row) = sqlx::query!(
+        r#"
+        SELECT email FROM subscriptions WHERE id = $1 FOR UPDATE
+        "#,
+        subscriber_id
+    )
+    .fetch_optional(&mut **transaction)
+    .await?
+    else {
+        return Ok(false);
+    };
+
+    sqlx::query!(
+        r#"DELETE FROM data_request_tokens WHERE subscriber_id = $1"#,
+        subscriber_id
+    )
+    .execute(&mut **transaction)
+    .await?;
+    sqlx::query!(
+        r#"DELETE FROM subscriber_drip_emails WHERE subscriber_id = $1"#,
+        subscriber_id
+    )
+    .execute(&mut **transaction)
+    .await?;
+    sqlx::query!(
+        r#"DELETE FROM subscription_tokens WHERE subscriber_id = $1"#,
+        subscriber_id
+    )
+    .execute(&mut **transaction)
+    .await?;
+    sqlx::query!(
+        r#"DELETE FROM issue_delivery_queue WHERE lower(subscriber_email) = lower($1)"#,
+        row.email
+    )
+    .execute(&mut **transaction)
+    .await?;
+    sqlx::query!(
+        r#"DELETE FROM issue_delivery_log WHERE lower(subscriber_email) = lower($1)"#,
+        row.email
+    )
+    .execute(&mut **transaction)
+    .await?;
+    sqlx::query!(
+        r#"DELETE FROM email_outbox WHERE lower(recipient) = lower($1)"#,
+        row.email
+    )
+    .execute(&mut **transaction)
+    .await?;
+    sqlx::query!(
+        r#"DELETE FROM subscriber_import_rows WHERE lower(email) = lower($1)"#,
+        row.email
+    )
+    .execute(&mut **transaction)
+    .await?;
+    sqlx::query!(r#"DELETE FROM subscriptions WHERE id = $1"#, subscriber_id)
+        .execute(&mut **transaction)
+        .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO data_erasure_log (erasure_id, requested_by)
+        VALUES ($1, $2)
+        "#,
+        Uuid::new_v4(),
+        requested_by.as_str()
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    Ok(true)
+}
@@ -0,0 +1,145 @@
+use actix_web::{HttpResponse, cookie::Cookie, http::header::ContentType, web};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use sqlx::PgPool;
+
+use crate::{
+    domain::{ConfirmationToken, SubscriberEmail},
+    email_outbox::enqueue_email,
+    rate_limiter::{RateLimitDecision, RateLimiter},
+    routes::helpers::{e500, get_message, prepare_html_template, see_other},
+    startup::ApplicationBaseURL,
+    transactional_emails::{TransactionalEmail, render_transactional_email},
+};
+
+use super::{
+    persistence::{find_subscriber_id_by_email, store_data_request_token},
+    types::{DataRequestFormData, DataRequestKind},
+};
+
+const DATA_REQUEST_PATH: &str = "/subscriptions/data-requests";
+
+pub async fn data_request_form(flash_messages: IncomingFlashMessages) -> HttpResponse {
+    let message_string = get_message(flash_messages, None);
+    let page_string =
+        prepare_html_template(&[("message", &message_string)], "data_request_form.html");
+
+    let mut response = HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(page_string);
+    response
+        .add_removal_cookie(&Cookie::new("_flash", ""))
+        .unwrap();
+    response
+}
+
+#[tracing::instrument(
+    name = "Requesting a copy or erasure of subscriber data",
+    skip(form, db_pool, base_url, rate_limiter),
+    fields(kind = ?form.kind)
+)]
+pub async fn request_subscriber_data(
+    form: web::Form<DataRequestFormData>,
+    db_pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseURL>,
+    rate_limiter: web::Data<RateLimiter>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let DataRequestFormData { email, kind } = form.0;
+    let email = match SubscriberEmail::parse(email) {
+        Ok(email) => email,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other(DATA_REQUEST_PATH));
+        }
+    };
+
+    match rate_limiter.check_data_request_email(&email).await {
+        Ok(RateLimitDecision::Allowed) => {
+            send_data_request_link(&db_pool, &base_url, &email, kind)
+                .await
+                .map_err(e500)?;
+        }
+        Ok(RateLimitDecision::Throttled { .. }) => {
+            tracing::warn!("Dropping a throttled data request.");
+        }
+        Err(e) => {
+            tracing::error!(
+            error.cause_chain = ?e,
+            error.message = %e,
+            "Failed to check data request rate limits. Letting the request through.",
+            );
+            send_data_request_link(&db_pool, &base_url, &email, kind)
+                .await
+                .map_err(e500)?;
+        }
+    }
+
+    // The reply is the same whether or not the address is subscribed, so the form can't be
+    // used to find out who is on the list.
+    FlashMessage::info(format!(
+        "If {} is subscribed, we have emailed it a link to continue.",
+        email.as_ref()
+    ))
+    .send();
+    Ok(see_other(DATA_REQUEST_PATH))
+}
+
+async fn send_data_request_link(
+    db_pool: &PgPool,
+    base_url: &ApplicationBaseURL,
+    email: &SubscriberEmail,
+    kind: DataRequestKind,
+) -> Result<(), anyhow::Error> {
+    let mut transaction = db_pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+
+    let Some(subscriber_id) = find_subscriber_id_by_email(&mut *transaction, email)
+        .await
+        .context("Failed to look up the subscriber.")?
+    else {
+        return Ok(());
+    };
+
+    let token = ConfirmationToken::new();
+    store_data_request_token(&mut *transaction, subscriber_id, kind, token.as_ref())
+        .await
+        .context("Failed to store the data request token.")?;
+
+    let (template, action, path) = match kind {
+        DataRequestKind::Export => (
+            TransactionalEmail::DataExport,
+            "download a copy of the data we hold about you",
+            "/subscriptions/data/export",
+        ),
+        DataRequestKind::Erase => (
+            TransactionalEmail::DataErasure,
+            "permanently erase the data we hold about you",
+            "/subscriptions/data/erase",
+        ),
+    };
+    let link = format!("{}{}?token={}", base_url.0, path, token.as_ref());
+    let rendered = render_transactional_email(
+        &mut *transaction,
+        template,
+        &[("action", action), ("link", &link)],
+    )
+    .await
+    .context("Failed to render the data request email.")?;
+    enqueue_email(
+        &mut *transaction,
+        email,
+        &rendered.subject,
+        &rendered.html,
+        &rendered.text,
+    )
+    .await
+    .context("Failed to queue the data request email.")?;
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to store a data request.")?;
+    Ok(())
+}
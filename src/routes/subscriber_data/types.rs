@@ -0,0 +1,42 @@
+use serde::Deserialize;
+
+use crate::domain::ConfirmationToken;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DataRequestKind {
+    Export,
+    Erase,
+}
+
+impl DataRequestKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DataRequestKind::Export => "export",
+            DataRequestKind::Erase => "erase",
+        }
+    }
+}
+
+impl TryFrom<String> for DataRequestKind {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "export" => Ok(DataRequestKind::Export),
+            "erase" => Ok(DataRequestKind::Erase),
+            other => Err(format!("{other} is not a valid data request kind.")),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct DataRequestFormData {
+    pub email: String,
+    pub kind: DataRequestKind,
+}
+
+#[derive(Deserialize)]
+pub struct DataRequestParameters {
+    pub token: ConfirmationToken,
+}
@@ -1,14 +1,25 @@
 use super::super::helpers::error_chain_fmt;
-use actix_web::{ResponseError, http::StatusCode};
+use actix_web::{
+    HttpResponse, ResponseError,
+    http::{
+        StatusCode,
+        header::{ContentType, RETRY_AFTER},
+    },
+};
 use std::{
     error::Error,
     fmt::{Debug, Display},
+    time::Duration,
 };
 
 #[derive(thiserror::Error)]
 pub enum SubscribeError {
     #[error("{0}")]
     ValidationError(String),
+    #[error("We could not verify this submission. Please reload the page and try again.")]
+    SuspectedBot,
+    #[error("Too many subscription attempts. Please try again later.")]
+    RateLimited { retry_after: Duration },
     #[error(transparent)]
     UnexpectedError(#[from] anyhow::Error),
 }
@@ -23,9 +34,21 @@ impl ResponseError for SubscribeError {
     fn status_code(&self) -> actix_web::http::StatusCode {
         match self {
             SubscribeError::ValidationError(_) => StatusCode::BAD_REQUEST,
+            SubscribeError::SuspectedBot => StatusCode::BAD_REQUEST,
+            SubscribeError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        if let SubscribeError::RateLimited { retry_after } = self {
+            response.insert_header((RETRY_AFTER, retry_after.as_secs().to_string()));
+        }
+        response
+            .content_type(ContentType::plaintext())
+            .body(self.to_string())
+    }
 }
 
 pub struct StoreTokenError(pub sqlx::Error);
@@ -1,7 +1,11 @@
 mod errors;
-mod helpers;
+mod persistence;
 mod subscriptions_handler;
 mod types;
 
+pub use errors::SubscribeError;
+pub use persistence::{
+    get_attribute_definitions, get_subscription_status, update_subscription_status,
+};
 pub use subscriptions_handler::*;
 pub use types::FormData;
@@ -0,0 +1,91 @@
+use anyhow::Context;
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::domain::{AttributeDefinition, AttributeKind, SubscriptionStatus};
+
+#[tracing::instrument(name = "Getting subscription status", skip(executor))]
+pub async fn get_subscription_status(
+    executor: impl PgExecutor<'_>,
+    subscriber_id: Uuid,
+) -> Result<Option<SubscriptionStatus>, anyhow::Error> {
+    let record = sqlx::query!(
+        r#"
+        SELECT status FROM subscriptions WHERE id = $1
+        "#,
+        subscriber_id
+    )
+    .fetch_optional(executor)
+    .await
+    .context("Failed to read the subscription status.")?;
+
+    record
+        .map(|r| SubscriptionStatus::try_from(r.status).map_err(anyhow::Error::msg))
+        .transpose()
+}
+
+#[tracing::instrument(name = "Changing subscription status", skip(executor))]
+pub async fn update_subscription_status(
+    executor: impl PgExecutor<'_>,
+    subscriber_id: Uuid,
+    current: SubscriptionStatus,
+    next: SubscriptionStatus,
+) -> Result<(), anyhow::Error> {
+    current.transition_to(next).map_err(anyhow::Error::msg)?;
+
+    let n_updated_rows = sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET
+            status = $1,
+            confirmed_at = CASE
+                WHEN $1 = 'confirmed' THEN coalesce(confirmed_at, now())
+                ELSE confirmed_at
+            END,
+            pending_since = CASE WHEN $1 = 'pending_confirmation' THEN now() END,
+            confirmation_reminder_sent_at = NULL
+        WHERE id = $2 AND status = $3
+        "#,
+        next.as_str(),
+        subscriber_id,
+        current.as_str()
+    )
+    .execute(executor)
+    .await
+    .context("Failed to update the subscription status.")?
+    .rows_affected();
+
+    if n_updated_rows == 0 {
+        anyhow::bail!("The subscription status has been changed concurrently.");
+    }
+    Ok(())
+}
+
+#[tracing::instrument(name = "Getting subscriber attribute definitions", skip(executor))]
+pub async fn get_attribute_definitions(
+    executor: impl PgExecutor<'_>,
+) -> Result<Vec<AttributeDefinition>, anyhow::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT key, label, kind, required, max_length, choices
+        FROM subscriber_attribute_definitions
+        ORDER BY created_at, key
+        "#
+    )
+    .fetch_all(executor)
+    .await
+    .context("Failed to read the subscriber attribute definitions.")?;
+
+    rows.into_iter()
+        .map(|r| {
+            Ok(AttributeDefinition {
+                key: r.key,
+                label: r.label,
+                kind: AttributeKind::try_from(r.kind).map_err(anyhow::Error::msg)?,
+                required: r.required,
+                max_length: r.max_length,
+                choices: r.choices,
+            })
+        })
+        .collect()
+}
@@ -1,65 +1,258 @@
 use std::fmt::Debug;
 
-use actix_web::{HttpResponse, web};
+use actix_web::{HttpRequest, HttpResponse, http::header::REFERER, web};
 use anyhow::Context;
-use sqlx::{PgPool, Postgres, Transaction, types::chrono::Utc};
+use sqlx::{PgConnection, PgPool, Postgres, Transaction, types::chrono::Utc};
 use uuid::Uuid;
 
 use crate::{
-    domain::{ConfirmationToken, NewSubscriber, SubscriberEmail},
-    email_client::EmailClient,
+    bot_protection::{BotProtection, FormSubmission, VerifiedToken},
+    domain::{
+        ConfirmationToken, NewSubscriber, PolicyVerdict, SignupAttribution, SignupPolicy,
+        SubscriberEmail, SubscriptionStatus,
+    },
+    email_outbox::enqueue_email,
+    rate_limiter::{RateLimitDecision, RateLimiter},
     startup::ApplicationBaseURL,
+    transactional_emails::{TransactionalEmail, render_transactional_email},
 };
 
 use super::{
     errors::{StoreTokenError, SubscribeError},
-    helpers::{get_email_html, get_email_text},
+    persistence::{get_attribute_definitions, update_subscription_status},
     types::FormData,
 };
 
 #[tracing::instrument(
     name = "Adding a new subscriber.",
-    skip(form, db_pool, email_client, base_url),
+    skip(req, form, db_pool, base_url, rate_limiter, signup_policy, bot_protection),
     fields(
         subscriber_email = %form.email,
         subscriber_name = %form.name
     )
 )]
 pub async fn subscribe(
+    req: HttpRequest,
     form: web::Form<FormData>,
     db_pool: web::Data<PgPool>,
-    email_client: web::Data<EmailClient>,
     base_url: web::Data<ApplicationBaseURL>,
+    rate_limiter: web::Data<RateLimiter>,
+    signup_policy: web::Data<SignupPolicy>,
+    bot_protection: web::Data<BotProtection>,
 ) -> Result<HttpResponse, SubscribeError> {
-    let new_subscriber: NewSubscriber =
-        form.0.try_into().map_err(SubscribeError::ValidationError)?;
+    let FormData {
+        name,
+        email,
+        website,
+        form_token,
+        pow_nonce,
+        attributes,
+        attribution,
+    } = form.0;
+    let submission = FormSubmission {
+        honeypot: &website,
+        form_token: form_token.as_deref(),
+        pow_nonce: pow_nonce.as_deref(),
+    };
+    let verified_token = match bot_protection.verify(&submission, Utc::now().timestamp()) {
+        Ok(verified_token) => verified_token,
+        Err(reason) => {
+            tracing::warn!(%reason, "Rejecting a suspected bot submission.");
+            return Err(SubscribeError::SuspectedBot);
+        }
+    };
+
+    let definitions = get_attribute_definitions(&**db_pool).await?;
+    let attribution = SignupAttribution::parse(attribution, referer_header(&req).as_deref());
+    let mut new_subscriber =
+        NewSubscriber::parse(name, email, attributes, &definitions, attribution)
+            .map_err(SubscribeError::ValidationError)?;
+    new_subscriber.signup_flag = enforce_signup_policy(&signup_policy, &new_subscriber.email)?;
+    let client_ip = rate_limiter.client_ip(&req);
+    enforce_subscribe_rate_limits(&rate_limiter, client_ip.as_deref(), &new_subscriber.email)
+        .await?;
+    // Claimed only now, so a submission that failed validation can be corrected and resent.
+    if let Some(token) = verified_token {
+        claim_form_token(&rate_limiter, &token).await?;
+    }
+
+    match process_subscription(new_subscriber, &db_pool, &base_url).await? {
+        SubscribeOutcome::AlreadySubscribed => Ok(HttpResponse::Conflict().finish()),
+        _ => Ok(HttpResponse::Ok().finish()),
+    }
+}
+
+async fn claim_form_token(
+    rate_limiter: &RateLimiter,
+    token: &VerifiedToken<'_>,
+) -> Result<(), SubscribeError> {
+    match rate_limiter
+        .claim_form_challenge(token.challenge, token.expires_in_secs)
+        .await
+    {
+        Ok(true) => Ok(()),
+        Ok(false) => {
+            tracing::warn!("Rejecting a replayed form token.");
+            Err(SubscribeError::SuspectedBot)
+        }
+        Err(e) => {
+            tracing::error!(
+            error.cause_chain = ?e,
+            error.message = %e,
+            "Failed to claim the form token. Letting the request through.",
+            );
+            Ok(())
+        }
+    }
+}
+
+pub fn referer_header(req: &HttpRequest) -> Option<String> {
+    req.headers()
+        .get(REFERER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned)
+}
+
+/// Returns why an accepted address was flagged, so it can be stored with the subscriber.
+pub fn enforce_signup_policy(
+    signup_policy: &SignupPolicy,
+    email: &SubscriberEmail,
+) -> Result<Option<String>, SubscribeError> {
+    match signup_policy
+        .check(email)
+        .map_err(SubscribeError::ValidationError)?
+    {
+        PolicyVerdict::Accepted => Ok(None),
+        PolicyVerdict::Flagged(reason) => {
+            tracing::warn!(%reason, "Accepting a flagged sign-up.");
+            Ok(Some(reason))
+        }
+    }
+}
+
+#[tracing::instrument(name = "Enforcing subscribe rate limits", skip(rate_limiter))]
+pub async fn enforce_subscribe_rate_limits(
+    rate_limiter: &RateLimiter,
+    ip: Option<&str>,
+    email: &SubscriberEmail,
+) -> Result<(), SubscribeError> {
+    let decision = match ip {
+        Some(ip) => rate_limiter.check_subscribe_ip(ip).await,
+        None => Ok(RateLimitDecision::Allowed),
+    };
+    let decision = match decision {
+        Ok(RateLimitDecision::Allowed) => rate_limiter.check_subscribe_email(email).await,
+        other => other,
+    };
+
+    match decision {
+        Ok(RateLimitDecision::Allowed) => Ok(()),
+        Ok(RateLimitDecision::Throttled { retry_after }) => {
+            Err(SubscribeError::RateLimited { retry_after })
+        }
+        Err(e) => {
+            tracing::error!(
+            error.cause_chain = ?e,
+            error.message = %e,
+            "Failed to check subscribe rate limits. Letting the request through.",
+            );
+            Ok(())
+        }
+    }
+}
+
+pub enum SubscribeOutcome {
+    Created,
+    ConfirmationResent,
+    Resubscribed,
+    AlreadySubscribed,
+}
 
-    let existing_subscriber = try_find_subscriber_by_email(&db_pool, &new_subscriber.email)
+#[tracing::instrument(
+    name = "Processing a subscription request",
+    skip(new_subscriber, db_pool, base_url)
+)]
+pub async fn process_subscription(
+    new_subscriber: NewSubscriber,
+    db_pool: &PgPool,
+    base_url: &ApplicationBaseURL,
+) -> Result<SubscribeOutcome, SubscribeError> {
+    let existing_subscriber = try_find_subscriber_by_email(db_pool, &new_subscriber.email)
         .await
         .context("Failed to read data from database.")?;
 
     if let Some((id, status)) = existing_subscriber {
-        if status != "pending_confirmation" {
-            return Ok(HttpResponse::Conflict().finish());
+        let status = SubscriptionStatus::try_from(status).map_err(anyhow::Error::msg)?;
+
+        if status == SubscriptionStatus::PendingConfirmation {
+            let token_string = get_stored_confirmation_token(db_pool, id, &new_subscriber.email)
+                .await
+                .context("Failed to read data from database.")?;
+
+            let confirmation_token =
+                ConfirmationToken::parse(token_string).map_err(SubscribeError::ValidationError)?;
+
+            let mut connection = db_pool
+                .acquire()
+                .await
+                .context("Failed to acquire a Postgres connection from the pool.")?;
+            enqueue_confirmation_email(
+                &mut connection,
+                &new_subscriber,
+                base_url,
+                confirmation_token.as_ref(),
+            )
+            .await
+            .context("Failed to queue a confirmation email.")?;
+
+            return Ok(SubscribeOutcome::ConfirmationResent);
+        }
+
+        if !status.can_transition_to(SubscriptionStatus::PendingConfirmation) {
+            return Ok(SubscribeOutcome::AlreadySubscribed);
         }
 
-        let token_string = get_stored_confirmation_token(&db_pool, id, &new_subscriber.email)
+        let mut transaction = db_pool
+            .begin()
+            .await
+            .context("Failed to acquire a Postgres connection from the pool.")?;
+
+        update_subscription_status(
+            &mut *transaction,
+            id,
+            status,
+            SubscriptionStatus::PendingConfirmation,
+        )
+        .await
+        .context("Failed to restart the subscription.")?;
+
+        update_returning_subscriber(&mut transaction, id, &new_subscriber)
+            .await
+            .context("Failed to update the attributes of a returning subscriber.")?;
+
+        delete_tokens(&mut transaction, id)
             .await
-            .context("Failed to read data from database.")?;
+            .context("Failed to remove stale confirmation tokens.")?;
 
-        let confirmation_token =
-            ConfirmationToken::parse(token_string).map_err(SubscribeError::ValidationError)?;
+        let confirmation_token = ConfirmationToken::new();
+        store_token(&mut transaction, id, confirmation_token.as_ref())
+            .await
+            .context("Failed to store the confirmation token for a returning subscriber.")?;
 
-        send_email(
-            email_client,
+        enqueue_confirmation_email(
+            &mut transaction,
             &new_subscriber,
             base_url,
             confirmation_token.as_ref(),
         )
         .await
-        .context("Failed to send a confirmation email.")?;
+        .context("Failed to queue a confirmation email.")?;
 
-        return Ok(HttpResponse::Ok().finish());
+        transaction
+            .commit()
+            .await
+            .context("Failed to commit SQL transaction to restart a subscription.")?;
+        return Ok(SubscribeOutcome::Resubscribed);
     }
 
     let mut transaction = db_pool
@@ -76,45 +269,60 @@ pub async fn subscribe(
         .await
         .context("Failed to store the confirmation token for a new subscriber.")?;
 
-    send_email(
-        email_client,
+    enqueue_confirmation_email(
+        &mut transaction,
         &new_subscriber,
         base_url,
         confirmation_token.as_ref(),
     )
     .await
-    .context("Failed to send a confirmation email.")?;
+    .context("Failed to queue a confirmation email.")?;
 
     transaction
         .commit()
         .await
         .context("Failed to commit SQL transaction to store a new subscriber.")?;
-    Ok(HttpResponse::Ok().finish())
+    Ok(SubscribeOutcome::Created)
 }
 
 #[tracing::instrument(
-    name = "Sending a confirmation email to a new subscriber",
-    skip(email_client, subscriber, base_url)
+    name = "Queueing a confirmation email for a new subscriber",
+    skip(connection, subscriber, base_url)
 )]
-pub async fn send_email(
-    email_client: web::Data<EmailClient>,
+pub async fn enqueue_confirmation_email(
+    connection: &mut PgConnection,
     subscriber: &NewSubscriber,
-    base_url: web::Data<ApplicationBaseURL>,
+    base_url: &ApplicationBaseURL,
     confirmation_token: &str,
-) -> Result<(), reqwest::Error> {
-    let confirmation_link = format!(
+) -> Result<(), anyhow::Error> {
+    let confirmation_link = confirmation_link(base_url, confirmation_token);
+    let email = render_transactional_email(
+        &mut *connection,
+        TransactionalEmail::Confirmation,
+        &[
+            ("name", subscriber.name.as_ref()),
+            ("link", &confirmation_link),
+        ],
+    )
+    .await
+    .context("Failed to render the confirmation email.")?;
+
+    enqueue_email(
+        connection,
+        &subscriber.email,
+        &email.subject,
+        &email.html,
+        &email.text,
+    )
+    .await?;
+    Ok(())
+}
+
+pub fn confirmation_link(base_url: &ApplicationBaseURL, confirmation_token: &str) -> String {
+    format!(
         "{}/subscriptions/confirm?subscription_token={}",
         base_url.0, confirmation_token
-    );
-
-    email_client
-        .send_email(
-            vec![&subscriber.email],
-            "HELLO!",
-            &get_email_html(subscriber.name.as_ref(), &confirmation_link),
-            &get_email_text(subscriber.name.as_ref(), &confirmation_link),
-        )
-        .await
+    )
 }
 
 #[tracing::instrument(name = "Trying to find existing subscriber by email")]
@@ -124,7 +332,7 @@ async fn try_find_subscriber_by_email(
 ) -> Result<Option<(Uuid, String)>, sqlx::Error> {
     let result = sqlx::query!(
         r#"
-            SELECT id, status FROM subscriptions WHERE email = $1
+            SELECT id, status FROM subscriptions WHERE lower(email) = lower($1)
         "#,
         email.as_ref()
     )
@@ -161,15 +369,30 @@ pub async fn insert_subscriber(
     transaction: &mut Transaction<'_, Postgres>,
 ) -> Result<Uuid, sqlx::Error> {
     let id = Uuid::new_v4();
+    let attribution = &new_subscriber.attribution;
     sqlx::query!(
         r#"
-        INSERT INTO subscriptions (id, email, name, subscribed_at, status)
-        VALUES ($1, $2, $3, $4, 'pending_confirmation')
+        INSERT INTO subscriptions (
+            id, email, name, subscribed_at, pending_since, status, attributes,
+            signup_source, referrer, utm_source, utm_medium, utm_campaign, utm_term, utm_content,
+            signup_flag
+        )
+        VALUES ($1, $2, $3, $4, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
         "#,
         id,
         new_subscriber.email.as_ref(),
         new_subscriber.name.as_ref(),
-        Utc::now()
+        Utc::now(),
+        SubscriptionStatus::PendingConfirmation.as_str(),
+        new_subscriber.attributes.as_json(),
+        attribution.source,
+        attribution.referrer,
+        attribution.utm_source,
+        attribution.utm_medium,
+        attribution.utm_campaign,
+        attribution.utm_term,
+        attribution.utm_content,
+        new_subscriber.signup_flag
     )
     .execute(&mut **transaction)
     .await?;
@@ -177,8 +400,31 @@ pub async fn insert_subscriber(
     Ok(id)
 }
 
+#[tracing::instrument(
+    name = "Updating a returning subscriber",
+    skip(transaction, new_subscriber)
+)]
+async fn update_returning_subscriber(
+    transaction: &mut Transaction<'_, Postgres>,
+    id: Uuid,
+    new_subscriber: &NewSubscriber,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE subscriptions SET attributes = attributes || $1, signup_flag = $2 WHERE id = $3
+        "#,
+        new_subscriber.attributes.as_json(),
+        new_subscriber.signup_flag,
+        id
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    Ok(())
+}
+
 #[tracing::instrument(name = "Saving new confirmation token", skip(transaction))]
-async fn store_token(
+pub async fn store_token(
     transaction: &mut Transaction<'_, Postgres>,
     id: Uuid,
     token: &str,
@@ -197,3 +443,21 @@ async fn store_token(
 
     Ok(())
 }
+
+#[tracing::instrument(name = "Removing confirmation tokens", skip(transaction))]
+pub async fn delete_tokens(
+    transaction: &mut Transaction<'_, Postgres>,
+    id: Uuid,
+) -> Result<(), StoreTokenError> {
+    sqlx::query!(
+        r#"
+        DELETE FROM subscription_tokens WHERE subscriber_id = $1
+        "#,
+        id
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(StoreTokenError)?;
+
+    Ok(())
+}
@@ -1,5 +1,18 @@
+use std::collections::HashMap;
+
+use crate::domain::RawSignupAttribution;
+
 #[derive(serde::Deserialize)]
 pub struct FormData {
     pub name: String,
     pub email: String,
+    #[serde(default)]
+    pub website: String,
+    pub form_token: Option<String>,
+    pub pow_nonce: Option<String>,
+    #[serde(flatten)]
+    pub attribution: RawSignupAttribution,
+    // Any other field is a custom subscriber attribute.
+    #[serde(flatten)]
+    pub attributes: HashMap<String, String>,
 }
@@ -1,44 +1,174 @@
-use actix_web::{HttpResponse, Responder, web};
+use actix_web::{
+    HttpRequest, HttpResponse,
+    error::{InternalError, QueryPayloadError},
+    http::{StatusCode, header::ContentType},
+    web,
+};
+use anyhow::Context;
 use serde::Deserialize;
 use sqlx::PgPool;
 use uuid::Uuid;
 
-use crate::domain::ConfirmationToken;
+use crate::{
+    configuration::ConfirmationPageSettings,
+    domain::{ConfirmationToken, SubscriptionStatus},
+    drip_sequences::schedule_drip_sequence,
+    routes::{
+        get_subscription_status,
+        helpers::{prepare_html_template, see_other},
+        update_subscription_status,
+    },
+};
 
 #[derive(Deserialize)]
 pub struct Parameters {
     subscription_token: ConfirmationToken,
 }
 
-#[tracing::instrument(name = "Confirm a pending subscriber", skip(parameters, db_pool))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfirmationOutcome {
+    Confirmed,
+    AlreadyConfirmed,
+    InvalidToken,
+    Error,
+}
+
+impl ConfirmationOutcome {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ConfirmationOutcome::Confirmed => "confirmed",
+            ConfirmationOutcome::AlreadyConfirmed => "already_confirmed",
+            ConfirmationOutcome::InvalidToken => "invalid_token",
+            ConfirmationOutcome::Error => "error",
+        }
+    }
+
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ConfirmationOutcome::Confirmed | ConfirmationOutcome::AlreadyConfirmed => {
+                StatusCode::OK
+            }
+            ConfirmationOutcome::InvalidToken => StatusCode::UNAUTHORIZED,
+            ConfirmationOutcome::Error => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn landing_url<'a>(&self, pages: &'a ConfirmationPageSettings) -> Option<&'a str> {
+        match self {
+            ConfirmationOutcome::Confirmed => pages.confirmed_url.as_deref(),
+            ConfirmationOutcome::AlreadyConfirmed => pages.already_confirmed_url.as_deref(),
+            ConfirmationOutcome::InvalidToken => pages.invalid_token_url.as_deref(),
+            ConfirmationOutcome::Error => pages.error_url.as_deref(),
+        }
+    }
+
+    pub fn into_response(
+        self,
+        pages: &ConfirmationPageSettings,
+        status_code: StatusCode,
+    ) -> HttpResponse {
+        if let Some(url) = self.landing_url(pages) {
+            return see_other(url);
+        }
+        let page = prepare_html_template(
+            &[("outcome", self.as_str())],
+            "subscription_confirmation.html",
+        );
+        HttpResponse::build(status_code)
+            .content_type(ContentType::html())
+            .body(page)
+    }
+}
+
+#[tracing::instrument(
+    name = "Confirm a pending subscriber",
+    skip(parameters, db_pool, pages)
+)]
 pub async fn confirm(
     parameters: web::Query<Parameters>,
     db_pool: web::Data<PgPool>,
-) -> impl Responder {
-    let stored_id = match get_subscriber_id_from_token(
-        &db_pool,
-        parameters.subscription_token.as_ref(),
-    )
-    .await
-    {
+    pages: web::Data<ConfirmationPageSettings>,
+) -> HttpResponse {
+    let outcome = confirm_subscriber(&db_pool, parameters.subscription_token.as_ref()).await;
+    outcome.into_response(&pages, outcome.status_code())
+}
+
+async fn confirm_subscriber(db_pool: &PgPool, token: &str) -> ConfirmationOutcome {
+    let stored_id = match get_subscriber_id_from_token(db_pool, token).await {
         Ok(id) => id,
-        Err(_) => return HttpResponse::InternalServerError().finish(),
+        Err(_) => return ConfirmationOutcome::Error,
+    };
+    let Some(id) = stored_id else {
+        return ConfirmationOutcome::InvalidToken;
     };
 
-    match stored_id {
-        None => HttpResponse::Unauthorized().finish(),
-        Some(id) => {
-            if confirm_subscriber(&db_pool, id).await.is_err() {
-                return HttpResponse::InternalServerError().finish();
-            }
+    let status = match get_subscription_status(db_pool, id).await {
+        Ok(Some(status)) => status,
+        Ok(None) => return ConfirmationOutcome::InvalidToken,
+        Err(_) => return ConfirmationOutcome::Error,
+    };
 
-            HttpResponse::Ok().finish()
+    if status == SubscriptionStatus::Confirmed {
+        return ConfirmationOutcome::AlreadyConfirmed;
+    }
+    if !status.can_transition_to(SubscriptionStatus::Confirmed) {
+        return ConfirmationOutcome::InvalidToken;
+    }
+    match confirm_and_schedule_drips(db_pool, id, status).await {
+        Ok(()) => ConfirmationOutcome::Confirmed,
+        Err(e) => {
+            tracing::error!(
+            error.cause_chain = ?e,
+            error.message = %e,
+            "Failed to confirm a subscriber.",
+            );
+            ConfirmationOutcome::Error
         }
     }
 }
 
+/// Missing or malformed tokens never reach `confirm`, so they get the invalid-token page here.
+pub fn confirmation_query_error_handler(
+    err: QueryPayloadError,
+    req: &HttpRequest,
+) -> actix_web::Error {
+    let pages = req
+        .app_data::<web::Data<ConfirmationPageSettings>>()
+        .map(|pages| pages.get_ref().clone())
+        .unwrap_or_default();
+    let response = ConfirmationOutcome::InvalidToken.into_response(&pages, StatusCode::BAD_REQUEST);
+    InternalError::from_response(err, response).into()
+}
+
+#[tracing::instrument(name = "Confirming a subscriber", skip(pool))]
+pub async fn confirm_and_schedule_drips(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+    status: SubscriptionStatus,
+) -> Result<(), anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+    update_subscription_status(
+        &mut *transaction,
+        subscriber_id,
+        status,
+        SubscriptionStatus::Confirmed,
+    )
+    .await?;
+    schedule_drip_sequence(&mut *transaction, subscriber_id)
+        .await
+        .context("Failed to schedule the drip sequence.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to confirm a subscriber.")?;
+    Ok(())
+}
+
 #[tracing::instrument(name = "Getting subscriber token stored in db.", skip(pool))]
-async fn get_subscriber_id_from_token(
+pub(crate) async fn get_subscriber_id_from_token(
     pool: &PgPool,
     token: &str,
 ) -> Result<Option<Uuid>, sqlx::Error> {
@@ -57,21 +187,3 @@ async fn get_subscriber_id_from_token(
 
     Ok(result.map(|r| r.subscriber_id))
 }
-
-#[tracing::instrument(name = "Change subscriber status", skip(pool))]
-async fn confirm_subscriber(pool: &PgPool, id: Uuid) -> Result<(), sqlx::Error> {
-    sqlx::query!(
-        r#"
-        UPDATE subscriptions SET status = 'confirmed' WHERE id = $1
-        "#,
-        id
-    )
-    .execute(pool)
-    .await
-    .map_err(|err| {
-        tracing::error!("Failed to execute query: {:?}", err);
-        err
-    })?;
-
-    Ok(())
-}
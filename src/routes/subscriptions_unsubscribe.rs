@@ -0,0 +1,184 @@
+use actix_web::{
+    HttpResponse,
+    http::{StatusCode, header::ContentType},
+    web,
+};
+use anyhow::Context;
+use serde::Deserialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::subscriptions_confirm::get_subscriber_id_from_token;
+use crate::{
+    domain::{ConfirmationToken, SubscriptionStatus},
+    drip_sequences::cancel_drip_sequence,
+    routes::{get_subscription_status, helpers::prepare_html_template, update_subscription_status},
+    startup::ApplicationBaseURL,
+};
+
+#[derive(Deserialize)]
+pub struct UnsubscribeParameters {
+    subscription_token: ConfirmationToken,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UnsubscribeOutcome {
+    Confirm,
+    Unsubscribed,
+    InvalidToken,
+    Error,
+}
+
+impl UnsubscribeOutcome {
+    fn as_str(&self) -> &'static str {
+        match self {
+            UnsubscribeOutcome::Confirm => "confirm",
+            UnsubscribeOutcome::Unsubscribed => "unsubscribed",
+            UnsubscribeOutcome::InvalidToken => "invalid_token",
+            UnsubscribeOutcome::Error => "error",
+        }
+    }
+
+    fn status_code(&self) -> StatusCode {
+        match self {
+            UnsubscribeOutcome::Confirm | UnsubscribeOutcome::Unsubscribed => StatusCode::OK,
+            UnsubscribeOutcome::InvalidToken => StatusCode::UNAUTHORIZED,
+            UnsubscribeOutcome::Error => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn into_response(self, token: &ConfirmationToken) -> HttpResponse {
+        let page = prepare_html_template(
+            &[("outcome", self.as_str()), ("token", token.as_ref())],
+            "unsubscribe.html",
+        );
+        HttpResponse::build(self.status_code())
+            .content_type(ContentType::html())
+            .body(page)
+    }
+}
+
+// The emailed link only asks for confirmation, so mail scanners that follow links don't
+// unsubscribe anyone.
+#[tracing::instrument(name = "Showing the unsubscribe page", skip(parameters, db_pool))]
+pub async fn unsubscribe_form(
+    parameters: web::Query<UnsubscribeParameters>,
+    db_pool: web::Data<PgPool>,
+) -> HttpResponse {
+    let token = &parameters.subscription_token;
+    let outcome = match get_subscriber_id_from_token(&db_pool, token.as_ref()).await {
+        Ok(Some(_)) => UnsubscribeOutcome::Confirm,
+        Ok(None) => UnsubscribeOutcome::InvalidToken,
+        Err(_) => UnsubscribeOutcome::Error,
+    };
+    outcome.into_response(token)
+}
+
+#[tracing::instrument(name = "Unsubscribe a subscriber", skip(form, db_pool))]
+pub async fn unsubscribe(
+    form: web::Form<UnsubscribeParameters>,
+    db_pool: web::Data<PgPool>,
+) -> HttpResponse {
+    let token = &form.subscription_token;
+    unsubscribe_subscriber(&db_pool, token.as_ref())
+        .await
+        .into_response(token)
+}
+
+async fn unsubscribe_subscriber(db_pool: &PgPool, token: &str) -> UnsubscribeOutcome {
+    let stored_id = match get_subscriber_id_from_token(db_pool, token).await {
+        Ok(id) => id,
+        Err(_) => return UnsubscribeOutcome::Error,
+    };
+    let Some(id) = stored_id else {
+        return UnsubscribeOutcome::InvalidToken;
+    };
+
+    let status = match get_subscription_status(db_pool, id).await {
+        Ok(Some(status)) => status,
+        Ok(None) => return UnsubscribeOutcome::InvalidToken,
+        Err(_) => return UnsubscribeOutcome::Error,
+    };
+
+    if !status.can_transition_to(SubscriptionStatus::Unsubscribed) {
+        return UnsubscribeOutcome::Unsubscribed;
+    }
+    match unsubscribe_and_cancel_drips(db_pool, id, status).await {
+        Ok(()) => UnsubscribeOutcome::Unsubscribed,
+        Err(e) => {
+            tracing::error!(
+            error.cause_chain = ?e,
+            error.message = %e,
+            "Failed to unsubscribe a subscriber.",
+            );
+            UnsubscribeOutcome::Error
+        }
+    }
+}
+
+#[tracing::instrument(name = "Unsubscribing a subscriber", skip(pool))]
+pub async fn unsubscribe_and_cancel_drips(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+    status: SubscriptionStatus,
+) -> Result<(), anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+    update_subscription_status(
+        &mut *transaction,
+        subscriber_id,
+        status,
+        SubscriptionStatus::Unsubscribed,
+    )
+    .await?;
+    cancel_drip_sequence(&mut *transaction, subscriber_id)
+        .await
+        .context("Failed to cancel the drip sequence.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to unsubscribe a subscriber.")?;
+    Ok(())
+}
+
+pub fn unsubscribe_link(base_url: &ApplicationBaseURL, subscription_token: &str) -> String {
+    format!(
+        "{}/subscriptions/unsubscribe?subscription_token={}",
+        base_url.0, subscription_token
+    )
+}
+
+/// The token behind a subscriber's unsubscribe link. Subscribers who never had one, e.g.
+/// imported ones, get a new one.
+#[tracing::instrument(name = "Getting an unsubscribe token", skip(pool))]
+pub async fn get_or_create_unsubscribe_token(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+) -> Result<String, sqlx::Error> {
+    let stored = sqlx::query!(
+        r#"
+        SELECT subscription_token FROM subscription_tokens WHERE subscriber_id = $1 LIMIT 1
+        "#,
+        subscriber_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    if let Some(stored) = stored {
+        return Ok(stored.subscription_token);
+    }
+
+    let token = ConfirmationToken::new();
+    sqlx::query!(
+        r#"
+        INSERT INTO subscription_tokens (subscriber_id, subscription_token)
+        VALUES ($1, $2)
+        "#,
+        subscriber_id,
+        token.as_ref()
+    )
+    .execute(pool)
+    .await?;
+    Ok(token.as_ref().to_string())
+}
@@ -8,6 +8,7 @@ pub struct TypedSession(Session);
 
 impl TypedSession {
     const USER_ID_KEY: &'static str = "user_id";
+    const SESSION_GENERATION_KEY: &'static str = "session_generation";
 
     pub fn logout(&self) {
         self.0.purge();
@@ -24,6 +25,18 @@ impl TypedSession {
     pub fn get_user_id(&self) -> Result<Option<Uuid>, SessionGetError> {
         self.0.get(Self::USER_ID_KEY)
     }
+
+    pub fn insert_session_generation(&self, generation: i32) -> Result<(), SessionInsertError> {
+        self.0.insert(Self::SESSION_GENERATION_KEY, generation)
+    }
+
+    /// Sessions from before generations were stored count as the first one.
+    pub fn get_session_generation(&self) -> Result<i32, SessionGetError> {
+        Ok(self
+            .0
+            .get(Self::SESSION_GENERATION_KEY)?
+            .unwrap_or_default())
+    }
 }
 
 impl FromRequest for TypedSession {
@@ -1,9 +1,28 @@
 use crate::authentication::reject_anonymous_users;
+use crate::bot_protection::BotProtection;
 use crate::configuration::{DatabaseSettings, Settings};
 use crate::email_client::EmailClient;
+use crate::rate_limiter::RateLimiter;
 use crate::routes::{
-    admin_dashboard, change_password, change_password_form, confirm, health_check, home, login,
-    login_form, logout, publish_newsletter, send_newsletters_form, subscribe,
+    add_drip_step, add_subscriber_attribute, admin_confirm_subscriber, admin_dashboard,
+    admin_delete_subscriber, admin_edit_subscriber, admin_erase_subscriber,
+    admin_unsubscribe_subscriber, api_subscribe, autosave_newsletter_draft, change_password,
+    change_password_form, change_recovery_email, confirm, confirmation_query_error_handler,
+    create_email_layout, create_newsletter_draft, data_request_form, delete_drip_step,
+    delete_email_layout, delete_newsletter_draft, delete_subscriber_attribute,
+    draft_from_newsletter_issue, drip_sequence_page, edit_email_layout, edit_email_template,
+    edit_newsletter_draft, erase_subscriber_data, erase_subscriber_data_form, export_issues,
+    export_subscriber_data, export_subscribers, health_check, home, import_upload_config,
+    issue_archive, issue_feed, json_error_handler, list_email_layouts, list_email_templates,
+    list_newsletter_drafts, list_newsletter_issues, list_subscribers, login, login_form, logout,
+    newsletter_issue_detail, password_reset_form, password_reset_request_form,
+    preview_email_layout, preview_email_template, preview_newsletter_draft, public_issue,
+    publish_newsletter, publish_newsletter_draft, request_password_reset, request_subscriber_data,
+    reset_email_template, reset_password, save_email_layout, save_email_template,
+    save_newsletter_draft, send_newsletters_form, set_newsletter_issue_visibility, signup_report,
+    subscribe, subscriber_attributes_page, subscriber_detail, subscriber_import_progress,
+    subscriber_imports_page, test_send_newsletter_draft, test_send_newsletter_issue, unsubscribe,
+    unsubscribe_form, upload_subscriber_import,
 };
 use actix_session::SessionMiddleware;
 use actix_session::storage::RedisSessionStore;
@@ -13,8 +32,7 @@ use actix_web::middleware::from_fn;
 use actix_web::{App, HttpServer, web};
 use actix_web_flash_messages::FlashMessagesFramework;
 use actix_web_flash_messages::storage::CookieMessageStore;
-use secrecy::ExposeSecret;
-use secrecy::SecretString;
+use secrecy::{ExposeSecret, SecretString};
 use sqlx::PgPool;
 use sqlx::postgres::PgPoolOptions;
 use std::net::TcpListener;
@@ -30,21 +48,13 @@ pub struct ApplicationBaseURL(pub String);
 
 impl Application {
     pub async fn build(config: Settings) -> Result<Self, anyhow::Error> {
-        let email_client = config.email_client.client();
+        let email_client = config.email_client.clone().client();
         let address = format!("{}:{}", config.app.host, config.app.port);
         let connection_pool = get_connection_pull(&config.database);
 
         let listener = TcpListener::bind(address).unwrap();
         let port = listener.local_addr().unwrap().port();
-        let server = run(
-            listener,
-            connection_pool,
-            email_client,
-            config.app.base_url,
-            config.app.hmac_secret,
-            config.redis_uri,
-        )
-        .await?;
+        let server = run(listener, connection_pool, email_client, config).await?;
 
         Ok(Self { port, server })
     }
@@ -62,17 +72,31 @@ pub async fn run(
     listener: TcpListener,
     db_pool: PgPool,
     email_client: EmailClient,
-    base_url: String,
-    hmac_secret: SecretString,
-    redis_uri: SecretString,
+    config: Settings,
 ) -> Result<Server, anyhow::Error> {
+    let Settings {
+        app: app_settings,
+        redis_uri,
+        rate_limits,
+        signup_policy,
+        bot_protection,
+        confirmation_pages,
+        ..
+    } = config;
     let db_pool = web::Data::new(db_pool);
     let email_client = web::Data::new(email_client);
-    let base_url = web::Data::new(ApplicationBaseURL(base_url));
-    let secret_key = Key::from(hmac_secret.expose_secret().as_bytes());
+    let base_url = web::Data::new(ApplicationBaseURL(app_settings.base_url));
+    let signup_policy = web::Data::new(signup_policy.policy());
+    let confirmation_pages = web::Data::new(confirmation_pages);
+    let bot_protection = web::Data::new(BotProtection::new(
+        app_settings.hmac_secret.clone(),
+        bot_protection,
+    ));
+    let secret_key = Key::from(app_settings.hmac_secret.expose_secret().as_bytes());
     let message_store = CookieMessageStore::builder(secret_key.clone()).build();
     let message_framework = FlashMessagesFramework::builder(message_store).build();
     let redis_store = RedisSessionStore::new(redis_uri.expose_secret()).await?;
+    let rate_limiter = web::Data::new(RateLimiter::new(&redis_uri, rate_limits).await?);
 
     let server = HttpServer::new(move || {
         App::new()
@@ -84,23 +108,215 @@ pub async fn run(
             ))
             .route("/health_check", web::get().to(health_check))
             .route("/subscriptions", web::post().to(subscribe))
-            .route("/subscriptions/confirm", web::get().to(confirm))
+            .service(
+                web::resource("/subscriptions/confirm")
+                    .app_data(
+                        web::QueryConfig::default().error_handler(confirmation_query_error_handler),
+                    )
+                    .route(web::get().to(confirm)),
+            )
+            .route(
+                "/subscriptions/unsubscribe",
+                web::get().to(unsubscribe_form),
+            )
+            .route("/subscriptions/unsubscribe", web::post().to(unsubscribe))
+            .route(
+                "/subscriptions/data-requests",
+                web::get().to(data_request_form),
+            )
+            .route(
+                "/subscriptions/data-requests",
+                web::post().to(request_subscriber_data),
+            )
+            .route(
+                "/subscriptions/data/export",
+                web::get().to(export_subscriber_data),
+            )
+            .route(
+                "/subscriptions/data/erase",
+                web::get().to(erase_subscriber_data_form),
+            )
+            .route(
+                "/subscriptions/data/erase",
+                web::post().to(erase_subscriber_data),
+            )
             .route("/login", web::get().to(login_form))
             .route("/login", web::post().to(login))
+            .route(
+                "/login/password-reset",
+                web::get().to(password_reset_request_form),
+            )
+            .route(
+                "/login/password-reset",
+                web::post().to(request_password_reset),
+            )
+            .route(
+                "/login/password-reset/confirm",
+                web::get().to(password_reset_form),
+            )
+            .route(
+                "/login/password-reset/confirm",
+                web::post().to(reset_password),
+            )
             .route("/", web::get().to(home))
+            .route("/issues", web::get().to(issue_archive))
+            .route("/issues/{slug}", web::get().to(public_issue))
+            .route("/feed.xml", web::get().to(issue_feed))
+            .service(
+                web::scope("/api/v1")
+                    .app_data(web::JsonConfig::default().error_handler(json_error_handler))
+                    .route("/subscriptions", web::post().to(api_subscribe)),
+            )
             .service(
                 web::scope("/admin")
                     .wrap(from_fn(reject_anonymous_users))
+                    .app_data(import_upload_config())
                     .route("/newsletters", web::post().to(publish_newsletter))
+                    .route("/newsletters/drafts", web::get().to(list_newsletter_drafts))
+                    .route(
+                        "/newsletters/drafts",
+                        web::post().to(create_newsletter_draft),
+                    )
+                    .route(
+                        "/newsletters/drafts/{draft_id}",
+                        web::get().to(edit_newsletter_draft),
+                    )
+                    .route(
+                        "/newsletters/drafts/{draft_id}",
+                        web::post().to(save_newsletter_draft),
+                    )
+                    .route(
+                        "/newsletters/drafts/{draft_id}/autosave",
+                        web::post().to(autosave_newsletter_draft),
+                    )
+                    .route(
+                        "/newsletters/drafts/{draft_id}/preview",
+                        web::get().to(preview_newsletter_draft),
+                    )
+                    .route(
+                        "/newsletters/drafts/{draft_id}/publish",
+                        web::post().to(publish_newsletter_draft),
+                    )
+                    .route(
+                        "/newsletters/drafts/{draft_id}/delete",
+                        web::post().to(delete_newsletter_draft),
+                    )
+                    .route(
+                        "/newsletters/drafts/{draft_id}/test",
+                        web::post().to(test_send_newsletter_draft),
+                    )
+                    .route(
+                        "/newsletters/issues/{issue_id}/test",
+                        web::post().to(test_send_newsletter_issue),
+                    )
+                    .route(
+                        "/newsletters/issues/{issue_id}/draft",
+                        web::post().to(draft_from_newsletter_issue),
+                    )
+                    .route("/newsletters/issues", web::get().to(list_newsletter_issues))
+                    .route(
+                        "/newsletters/issues/{issue_id}",
+                        web::get().to(newsletter_issue_detail),
+                    )
+                    .route(
+                        "/newsletters/issues/{issue_id}/visibility",
+                        web::post().to(set_newsletter_issue_visibility),
+                    )
                     .route("/logout", web::post().to(logout))
                     .route("/password", web::get().to(change_password_form))
                     .route("/password", web::post().to(change_password))
+                    .route("/password/email", web::post().to(change_recovery_email))
                     .route("/dashboard", web::get().to(admin_dashboard))
+                    .route("/subscribers/erase", web::post().to(admin_erase_subscriber))
+                    .route("/reports/signups", web::get().to(signup_report))
+                    .route("/exports/subscribers", web::get().to(export_subscribers))
+                    .route("/exports/issues", web::get().to(export_issues))
+                    .route("/emails", web::get().to(list_email_templates))
+                    .route("/emails/{kind}", web::get().to(edit_email_template))
+                    .route("/emails/{kind}", web::post().to(save_email_template))
+                    .route(
+                        "/emails/{kind}/preview",
+                        web::get().to(preview_email_template),
+                    )
+                    .route("/emails/{kind}/reset", web::post().to(reset_email_template))
+                    .route("/layouts", web::get().to(list_email_layouts))
+                    .route("/layouts", web::post().to(create_email_layout))
+                    .route(
+                        "/layouts/{email_layout_id}",
+                        web::get().to(edit_email_layout),
+                    )
+                    .route(
+                        "/layouts/{email_layout_id}",
+                        web::post().to(save_email_layout),
+                    )
+                    .route(
+                        "/layouts/{email_layout_id}/preview",
+                        web::get().to(preview_email_layout),
+                    )
+                    .route(
+                        "/layouts/{email_layout_id}/delete",
+                        web::post().to(delete_email_layout),
+                    )
+                    .route("/sequence", web::get().to(drip_sequence_page))
+                    .route("/sequence", web::post().to(add_drip_step))
+                    .route(
+                        "/sequence/{drip_step_id}/delete",
+                        web::post().to(delete_drip_step),
+                    )
+                    .route(
+                        "/subscribers/attributes",
+                        web::get().to(subscriber_attributes_page),
+                    )
+                    .route(
+                        "/subscribers/attributes",
+                        web::post().to(add_subscriber_attribute),
+                    )
+                    .route(
+                        "/subscribers/attributes/{key}/delete",
+                        web::post().to(delete_subscriber_attribute),
+                    )
+                    .route("/subscribers", web::get().to(list_subscribers))
+                    .route(
+                        "/subscribers/import",
+                        web::get().to(subscriber_imports_page),
+                    )
+                    .route(
+                        "/subscribers/import",
+                        web::post().to(upload_subscriber_import),
+                    )
+                    .route(
+                        "/subscribers/import/{import_id}",
+                        web::get().to(subscriber_import_progress),
+                    )
+                    .route(
+                        "/subscribers/{subscriber_id}",
+                        web::get().to(subscriber_detail),
+                    )
+                    .route(
+                        "/subscribers/{subscriber_id}/confirm",
+                        web::post().to(admin_confirm_subscriber),
+                    )
+                    .route(
+                        "/subscribers/{subscriber_id}/unsubscribe",
+                        web::post().to(admin_unsubscribe_subscriber),
+                    )
+                    .route(
+                        "/subscribers/{subscriber_id}/edit",
+                        web::post().to(admin_edit_subscriber),
+                    )
+                    .route(
+                        "/subscribers/{subscriber_id}/delete",
+                        web::post().to(admin_delete_subscriber),
+                    )
                     .route("/newsletters", web::get().to(send_newsletters_form)), // .route("/newsletters", web::post().to(change_password)),
             )
             .app_data(db_pool.clone())
             .app_data(email_client.clone())
             .app_data(base_url.clone())
+            .app_data(rate_limiter.clone())
+            .app_data(signup_policy.clone())
+            .app_data(bot_protection.clone())
+            .app_data(confirmation_pages.clone())
     })
     .listen(listener)?
     .run();
@@ -0,0 +1,203 @@
+use std::io::Cursor;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+use super::{
+    MAX_UNPACKED_BYTES, ParsedImport, ParsedRow, csv_line, name_or_local_part, read_archive_entry,
+};
+use crate::domain::SubscriptionStatus;
+
+const DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Mailchimp puts each audience segment in its own file, named after the members' status.
+/// Anything else in the archive (e.g. merge field definitions) is skipped.
+fn status_from_file_name(file_name: &str) -> Option<SubscriptionStatus> {
+    let base_name = file_name.rsplit('/').next().unwrap_or(file_name);
+    if base_name.starts_with("subscribed") {
+        Some(SubscriptionStatus::Confirmed)
+    } else if base_name.starts_with("unsubscribed") {
+        Some(SubscriptionStatus::Unsubscribed)
+    } else if base_name.starts_with("cleaned") {
+        Some(SubscriptionStatus::Bounced)
+    } else {
+        None
+    }
+}
+
+pub fn parse(file_name: &str, data: &[u8]) -> Result<ParsedImport, String> {
+    let mut parsed = ParsedImport::default();
+    if !data.starts_with(b"PK") {
+        // A single CSV that was taken out of the archive, members are subscribed unless the
+        // file name says otherwise.
+        let status = status_from_file_name(file_name).unwrap_or(SubscriptionStatus::Confirmed);
+        parse_members(data, "", status, &mut parsed)?;
+        return Ok(parsed);
+    }
+
+    let mut archive = zip::ZipArchive::new(Cursor::new(data))
+        .map_err(|e| format!("The file could not be read as a zip archive: {e}"))?;
+    let mut remaining = MAX_UNPACKED_BYTES;
+    for i in 0..archive.len() {
+        let entry = archive
+            .by_index(i)
+            .map_err(|e| format!("The zip archive is damaged: {e}"))?;
+        let entry_name = entry.name().to_string();
+        let Some(status) =
+            status_from_file_name(&entry_name).filter(|_| entry_name.ends_with(".csv"))
+        else {
+            continue;
+        };
+        let contents = read_archive_entry(entry, &mut remaining)?;
+        parse_members(&contents, &entry_name, status, &mut parsed)?;
+    }
+    Ok(parsed)
+}
+
+fn parse_members(
+    data: &[u8],
+    file_name: &str,
+    status: SubscriptionStatus,
+    parsed: &mut ParsedImport,
+) -> Result<(), String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(data);
+    let headers = reader
+        .headers()
+        .map_err(|e| format!("{file_name} could not be read as CSV: {e}"))?
+        .clone();
+    let column = |name: &str| headers.iter().position(|h| h.eq_ignore_ascii_case(name));
+    let email_column = column("Email Address")
+        .ok_or_else(|| format!("{file_name} is missing the `Email Address` column."))?;
+    let first_name_column = column("First Name");
+    let last_name_column = column("Last Name");
+    // Double opt-in audiences have a confirmation time, single opt-in ones only the opt-in.
+    let date_columns = [column("CONFIRM_TIME"), column("OPTIN_TIME")];
+
+    for record in reader.records() {
+        let row = match record {
+            Ok(record) => {
+                let field = |i: Option<usize>| i.and_then(|i| record.get(i)).unwrap_or_default();
+                let email = field(Some(email_column)).to_string();
+                let full_name = format!("{} {}", field(first_name_column), field(last_name_column));
+                let subscribed_at = date_columns
+                    .iter()
+                    .filter_map(|&c| parse_date(field(c)))
+                    .next();
+                ParsedRow {
+                    file_name: file_name.to_string(),
+                    line_number: csv_line(record.position())?,
+                    name: name_or_local_part(&full_name, &email),
+                    email,
+                    status: Some(status),
+                    subscribed_at,
+                    error: None,
+                }
+            }
+            Err(e) => ParsedRow {
+                file_name: file_name.to_string(),
+                line_number: csv_line(e.position())?,
+                error: Some(e.to_string()),
+                ..Default::default()
+            },
+        };
+        parsed.rows.push(row);
+    }
+    Ok(())
+}
+
+// Mailchimp exports times in UTC without an offset.
+fn parse_date(raw: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(raw, DATE_FORMAT)
+        .ok()
+        .map(|d| d.and_utc())
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+
+    use chrono::{TimeZone, Utc};
+
+    use super::parse;
+    use crate::{domain::SubscriptionStatus, subscriber_imports::read_archive_entry};
+
+    const MEMBERS: &str = "Email Address,First Name,Last Name,OPTIN_TIME,CONFIRM_TIME\n\
+        ursula@example.com,Ursula,Le Guin,2020-01-02 03:04:05,2020-01-02 04:05:06\n\
+        octavia@example.com,,,2021-05-06 07:08:09,\n";
+
+    #[test]
+    fn statuses_come_from_the_file_names_in_the_archive() {
+        let mut archive = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for name in [
+            "subscribed_members_export_abc.csv",
+            "unsubscribed_members_export_abc.csv",
+            "cleaned_members_export_abc.csv",
+        ] {
+            archive
+                .start_file(name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            archive.write_all(MEMBERS.as_bytes()).unwrap();
+        }
+        let data = archive.finish().unwrap().into_inner();
+
+        let parsed = parse("members_export.zip", &data).unwrap();
+
+        let statuses: Vec<_> = parsed.rows.iter().map(|r| r.status.unwrap()).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                SubscriptionStatus::Confirmed,
+                SubscriptionStatus::Confirmed,
+                SubscriptionStatus::Unsubscribed,
+                SubscriptionStatus::Unsubscribed,
+                SubscriptionStatus::Bounced,
+                SubscriptionStatus::Bounced,
+            ]
+        );
+        assert_eq!(
+            parsed.rows[2].file_name,
+            "unsubscribed_members_export_abc.csv"
+        );
+    }
+
+    #[test]
+    fn names_and_dates_are_carried_over() {
+        let parsed = parse("subscribed_members_export_abc.csv", MEMBERS.as_bytes()).unwrap();
+
+        assert_eq!(parsed.rows[0].name, "Ursula Le Guin");
+        assert_eq!(
+            parsed.rows[0].subscribed_at,
+            Some(Utc.with_ymd_and_hms(2020, 1, 2, 4, 5, 6).unwrap())
+        );
+        assert_eq!(parsed.rows[1].name, "octavia");
+        assert_eq!(
+            parsed.rows[1].subscribed_at,
+            Some(Utc.with_ymd_and_hms(2021, 5, 6, 7, 8, 9).unwrap())
+        );
+    }
+
+    #[test]
+    fn archives_cannot_unpack_past_the_limit() {
+        let mut archive = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        archive
+            .start_file("subscribed.csv", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        archive.write_all(MEMBERS.as_bytes()).unwrap();
+        let mut archive = zip::ZipArchive::new(archive.finish().unwrap()).unwrap();
+        let size = MEMBERS.len() as u64;
+
+        let mut remaining = size - 1;
+        let entry = archive.by_index(0).unwrap();
+        assert!(read_archive_entry(entry, &mut remaining).is_err());
+
+        let mut remaining = size;
+        let entry = archive.by_index(0).unwrap();
+        assert_eq!(
+            read_archive_entry(entry, &mut remaining).unwrap(),
+            MEMBERS.as_bytes()
+        );
+        assert_eq!(remaining, 0);
+    }
+}
@@ -0,0 +1,503 @@
+mod mailchimp;
+mod plain_csv;
+mod substack;
+
+use std::io::Read;
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use tracing::{Span, field::display};
+use uuid::Uuid;
+
+use crate::{
+    domain::{
+        ConfirmationToken, IssueSlug, NewSubscriber, SignupAttribution, SubscriberAttributes,
+        SubscriberEmail, SubscriberName, SubscriptionStatus,
+    },
+    issue_delivery_worker::ExecutionOutcome,
+    routes::{enqueue_confirmation_email, store_token},
+    startup::ApplicationBaseURL,
+};
+
+const BATCH_SIZE: i64 = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportMode {
+    /// The admin attests that everyone on the list already opted in.
+    Confirmed,
+    /// Everyone gets a confirmation email, as if they had signed up through the form.
+    Pending,
+}
+
+impl ImportMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImportMode::Confirmed => "confirmed",
+            ImportMode::Pending => "pending",
+        }
+    }
+}
+
+impl TryFrom<String> for ImportMode {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "confirmed" => Ok(ImportMode::Confirmed),
+            "pending" => Ok(ImportMode::Pending),
+            other => Err(format!("{other} is not a valid import mode.")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportSource {
+    /// Any CSV with an `email` and an optional `name` column.
+    Csv,
+    /// A Mailchimp audience export, either the zip or one of the CSVs inside it.
+    Mailchimp,
+    /// A Substack export zip, including past posts.
+    Substack,
+}
+
+impl ImportSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImportSource::Csv => "csv",
+            ImportSource::Mailchimp => "mailchimp",
+            ImportSource::Substack => "substack",
+        }
+    }
+
+    pub fn parse_file(&self, file_name: &str, data: &[u8]) -> Result<ParsedImport, String> {
+        let parsed = match self {
+            ImportSource::Csv => plain_csv::parse(data)?,
+            ImportSource::Mailchimp => mailchimp::parse(file_name, data)?,
+            ImportSource::Substack => substack::parse(data)?,
+        };
+        if parsed.rows.is_empty() {
+            return Err("The file doesn't contain any subscribers.".into());
+        }
+        Ok(parsed)
+    }
+
+    fn signup_source(&self) -> &'static str {
+        match self {
+            ImportSource::Csv => "import",
+            ImportSource::Mailchimp => "mailchimp",
+            ImportSource::Substack => "substack",
+        }
+    }
+}
+
+impl TryFrom<String> for ImportSource {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "csv" => Ok(ImportSource::Csv),
+            "mailchimp" => Ok(ImportSource::Mailchimp),
+            "substack" => Ok(ImportSource::Substack),
+            other => Err(format!("{other} is not a supported import format.")),
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ParsedImport {
+    pub rows: Vec<ParsedRow>,
+    pub issues: Vec<ImportedIssue>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ParsedRow {
+    /// Empty unless the upload was an archive with several files.
+    pub file_name: String,
+    pub line_number: i32,
+    pub email: String,
+    pub name: String,
+    /// Where the source platform has one. Only `Confirmed` rows follow the import mode.
+    pub status: Option<SubscriptionStatus>,
+    pub subscribed_at: Option<DateTime<Utc>>,
+    /// Set for rows that couldn't be read at all, they are stored as invalid right away.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ImportedIssue {
+    /// The post's id on the platform, importing it again is a no-op.
+    pub source_id: String,
+    pub title: String,
+    pub html_content: String,
+    pub text_content: String,
+    pub published_at: DateTime<Utc>,
+}
+
+// Platforms don't require a name, but we do.
+fn name_or_local_part(name: &str, email: &str) -> String {
+    match name.trim() {
+        "" => email
+            .split('@')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+        name => name.to_string(),
+    }
+}
+
+/// What all the files in an uploaded archive may unpack to together.
+const MAX_UNPACKED_BYTES: u64 = 200 * 1024 * 1024;
+
+// The size an entry claims is checked first, but it comes from the archive, so the read
+// stops at the limit too.
+fn read_archive_entry(
+    entry: zip::read::ZipFile<'_>,
+    remaining: &mut u64,
+) -> Result<Vec<u8>, String> {
+    let name = entry.name().to_string();
+    let too_large = || {
+        format!(
+            "The archive unpacks to more than {} MB.",
+            MAX_UNPACKED_BYTES / 1024 / 1024
+        )
+    };
+    if entry.size() > *remaining {
+        return Err(too_large());
+    }
+    let mut contents = Vec::new();
+    entry
+        .take(*remaining + 1)
+        .read_to_end(&mut contents)
+        .map_err(|e| format!("{name} could not be read: {e}"))?;
+    *remaining = remaining
+        .checked_sub(contents.len() as u64)
+        .ok_or_else(too_large)?;
+    Ok(contents)
+}
+
+fn csv_line(position: Option<&csv::Position>) -> Result<i32, String> {
+    i32::try_from(position.map_or(0, |p| p.line()))
+        .map_err(|_| "The file has too many lines.".to_string())
+}
+
+enum RowOutcome {
+    Imported,
+    Duplicate,
+    Invalid(String),
+}
+
+/// Imports the next batch of rows of the oldest unfinished import. Progress is kept per row,
+/// so an import picks up where it left off if the worker restarts.
+#[tracing::instrument(skip_all, fields(import_id=tracing::field::Empty), err)]
+pub async fn try_import_subscriber_batch(
+    pool: &PgPool,
+    base_url: &ApplicationBaseURL,
+) -> Result<ExecutionOutcome, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    let Some(import) = sqlx::query!(
+        r#"
+        SELECT import_id, mode, source
+        FROM subscriber_imports
+        WHERE status = 'processing'
+        ORDER BY created_at
+        FOR UPDATE
+        SKIP LOCKED
+        LIMIT 1
+        "#
+    )
+    .fetch_optional(&mut *transaction)
+    .await?
+    else {
+        return Ok(ExecutionOutcome::EmptyQueue);
+    };
+    Span::current().record("import_id", display(import.import_id));
+    let mode = ImportMode::try_from(import.mode).map_err(anyhow::Error::msg)?;
+    let source = ImportSource::try_from(import.source).map_err(anyhow::Error::msg)?;
+
+    let rows = sqlx::query!(
+        r#"
+        SELECT file_name, line_number, email, name, status, subscribed_at
+        FROM subscriber_import_rows
+        WHERE import_id = $1 AND outcome IS NULL
+        ORDER BY file_name, line_number
+        LIMIT $2
+        "#,
+        import.import_id,
+        BATCH_SIZE
+    )
+    .fetch_all(&mut *transaction)
+    .await?;
+
+    if rows.is_empty() {
+        sqlx::query!(
+            r#"
+            UPDATE subscriber_imports
+            SET status = 'completed', finished_at = now()
+            WHERE import_id = $1
+            "#,
+            import.import_id
+        )
+        .execute(&mut *transaction)
+        .await?;
+    }
+
+    for row in rows {
+        let status = row
+            .status
+            .map(SubscriptionStatus::try_from)
+            .transpose()
+            .map_err(anyhow::Error::msg)?;
+        let outcome = import_row(
+            &mut transaction,
+            ImportTarget {
+                mode,
+                source,
+                status,
+                subscribed_at: row.subscribed_at,
+            },
+            row.email,
+            row.name,
+            base_url,
+        )
+        .await?;
+        let (outcome, error) = match outcome {
+            RowOutcome::Imported => ("imported", None),
+            RowOutcome::Duplicate => ("duplicate", None),
+            RowOutcome::Invalid(e) => ("invalid", Some(e)),
+        };
+        sqlx::query!(
+            r#"
+            UPDATE subscriber_import_rows
+            SET outcome = $4, error = $5
+            WHERE import_id = $1 AND file_name = $2 AND line_number = $3
+            "#,
+            import.import_id,
+            row.file_name,
+            row.line_number,
+            outcome,
+            error
+        )
+        .execute(&mut *transaction)
+        .await?;
+    }
+    transaction.commit().await?;
+
+    Ok(ExecutionOutcome::TaskCompleted)
+}
+
+struct ImportTarget {
+    mode: ImportMode,
+    source: ImportSource,
+    status: Option<SubscriptionStatus>,
+    subscribed_at: Option<DateTime<Utc>>,
+}
+
+impl ImportTarget {
+    fn status(&self) -> SubscriptionStatus {
+        match (self.status, self.mode) {
+            (Some(status), _) if status != SubscriptionStatus::Confirmed => status,
+            (_, ImportMode::Confirmed) => SubscriptionStatus::Confirmed,
+            (_, ImportMode::Pending) => SubscriptionStatus::PendingConfirmation,
+        }
+    }
+}
+
+// Existing subscribers are left untouched, whatever their status. The same goes for an address
+// that appears twice in the file: the second row finds the first one.
+async fn import_row(
+    transaction: &mut Transaction<'_, Postgres>,
+    target: ImportTarget,
+    email: String,
+    name: String,
+    base_url: &ApplicationBaseURL,
+) -> Result<RowOutcome, anyhow::Error> {
+    let parsed = SubscriberEmail::parse(email).and_then(|email| {
+        let name = SubscriberName::parse(name)?;
+        Ok((email, name))
+    });
+    let (email, name) = match parsed {
+        Ok(parsed) => parsed,
+        Err(e) => return Ok(RowOutcome::Invalid(e)),
+    };
+
+    let exists = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS (SELECT 1 FROM subscriptions WHERE lower(email) = lower($1)) AS "exists!"
+        "#,
+        email.as_ref()
+    )
+    .fetch_one(&mut **transaction)
+    .await?;
+    if exists {
+        return Ok(RowOutcome::Duplicate);
+    }
+
+    let new_subscriber = NewSubscriber {
+        email,
+        name,
+        attributes: SubscriberAttributes::default(),
+        attribution: SignupAttribution {
+            source: Some(target.source.signup_source().into()),
+            ..Default::default()
+        },
+        signup_flag: None,
+    };
+    let status = target.status();
+    let subscriber_id =
+        insert_imported_subscriber(transaction, &new_subscriber, status, target.subscribed_at)
+            .await
+            .context("Failed to insert an imported subscriber.")?;
+
+    // Imported subscribers aren't new to the list, so they don't get the onboarding sequence.
+    if status == SubscriptionStatus::PendingConfirmation {
+        let confirmation_token = ConfirmationToken::new();
+        store_token(transaction, subscriber_id, confirmation_token.as_ref())
+            .await
+            .context("Failed to store the confirmation token for an imported subscriber.")?;
+        enqueue_confirmation_email(
+            transaction,
+            &new_subscriber,
+            base_url,
+            confirmation_token.as_ref(),
+        )
+        .await
+        .context("Failed to queue a confirmation email.")?;
+    }
+
+    Ok(RowOutcome::Imported)
+}
+
+// Statuses come from another platform, so they are taken as they are instead of being walked
+// through the state machine.
+async fn insert_imported_subscriber(
+    transaction: &mut Transaction<'_, Postgres>,
+    new_subscriber: &NewSubscriber,
+    status: SubscriptionStatus,
+    subscribed_at: Option<DateTime<Utc>>,
+) -> Result<Uuid, sqlx::Error> {
+    let id = Uuid::new_v4();
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriptions (
+            id, email, name, status, attributes, signup_source,
+            subscribed_at, pending_since, confirmed_at
+        )
+        VALUES (
+            $1, $2, $3, $4, $5, $6,
+            coalesce($7, now()),
+            CASE WHEN $4 = 'pending_confirmation' THEN now() END,
+            CASE WHEN $4 = 'confirmed' THEN coalesce($7, now()) END
+        )
+        "#,
+        id,
+        new_subscriber.email.as_ref(),
+        new_subscriber.name.as_ref(),
+        status.as_str(),
+        new_subscriber.attributes.as_json(),
+        new_subscriber.attribution.source,
+        subscribed_at
+    )
+    .execute(&mut **transaction)
+    .await?;
+    Ok(id)
+}
+
+pub struct StoredImport {
+    pub import_id: Uuid,
+    /// Issues already in the archive from an earlier import aren't counted.
+    pub added_issues: u64,
+}
+
+#[tracing::instrument(name = "Storing a subscriber import", skip(transaction, parsed))]
+pub async fn store_import(
+    transaction: &mut Transaction<'_, Postgres>,
+    file_name: &str,
+    source: ImportSource,
+    mode: ImportMode,
+    created_by: Uuid,
+    parsed: &ParsedImport,
+) -> Result<StoredImport, sqlx::Error> {
+    let import_id = Uuid::new_v4();
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriber_imports (
+            import_id, file_name, source, mode, status, created_by, consent_attested_at
+        )
+        VALUES ($1, $2, $3, $4, 'processing', $5, CASE WHEN $4 = 'confirmed' THEN now() END)
+        "#,
+        import_id,
+        file_name,
+        source.as_str(),
+        mode.as_str(),
+        created_by
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    let rows = &parsed.rows;
+    let file_names: Vec<&str> = rows.iter().map(|r| r.file_name.as_str()).collect();
+    let line_numbers: Vec<i32> = rows.iter().map(|r| r.line_number).collect();
+    let emails: Vec<&str> = rows.iter().map(|r| r.email.as_str()).collect();
+    let names: Vec<&str> = rows.iter().map(|r| r.name.as_str()).collect();
+    let statuses: Vec<Option<&str>> = rows.iter().map(|r| r.status.map(|s| s.as_str())).collect();
+    let subscribed_ats: Vec<Option<DateTime<Utc>>> = rows.iter().map(|r| r.subscribed_at).collect();
+    let errors: Vec<Option<&str>> = rows.iter().map(|r| r.error.as_deref()).collect();
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriber_import_rows (
+            import_id, file_name, line_number, email, name, status, subscribed_at, outcome, error
+        )
+        SELECT
+            $1, file_name, line_number, email, name, status, subscribed_at,
+            CASE WHEN error IS NULL THEN NULL ELSE 'invalid' END, error
+        FROM UNNEST(
+            $2::text[], $3::int4[], $4::text[], $5::text[], $6::text[], $7::timestamptz[],
+            $8::text[]
+        ) AS r(file_name, line_number, email, name, status, subscribed_at, error)
+        "#,
+        import_id,
+        &file_names as &[&str],
+        &line_numbers,
+        &emails as &[&str],
+        &names as &[&str],
+        &statuses as &[Option<&str>],
+        &subscribed_ats as &[Option<DateTime<Utc>>],
+        &errors as &[Option<&str>]
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    let mut added_issues = 0;
+    for issue in &parsed.issues {
+        let issue_id = Uuid::new_v4();
+        let slug = IssueSlug::new(&issue.title, issue_id);
+        added_issues += sqlx::query!(
+            r#"
+            INSERT INTO newsletter_issues (
+                newsletter_issue_id, title, text_content, html_content, published_at,
+                imported_from, imported_post_id, slug, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5::timestamptz::text, $6, $7, $8, $5)
+            ON CONFLICT (imported_from, imported_post_id) DO NOTHING
+            "#,
+            issue_id,
+            issue.title,
+            issue.text_content,
+            issue.html_content,
+            issue.published_at,
+            source.as_str(),
+            issue.source_id,
+            slug.as_ref()
+        )
+        .execute(&mut **transaction)
+        .await?
+        .rows_affected();
+    }
+
+    Ok(StoredImport {
+        import_id,
+        added_issues,
+    })
+}
@@ -0,0 +1,98 @@
+use super::{ParsedImport, ParsedRow, csv_line, name_or_local_part};
+
+/// The file needs a header row with an `email` column; a `name` column is optional and other
+/// columns are ignored. Rows without a name use the local part of the address.
+pub fn parse(data: &[u8]) -> Result<ParsedImport, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(data);
+    let headers = reader
+        .headers()
+        .map_err(|e| format!("The file could not be read as CSV: {e}"))?
+        .clone();
+    let column = |name: &str| headers.iter().position(|h| h.eq_ignore_ascii_case(name));
+    let email_column = column("email").ok_or("The file needs an `email` column.")?;
+    let name_column = column("name");
+
+    let mut parsed = ParsedImport::default();
+    for record in reader.records() {
+        let row = match record {
+            Ok(record) => {
+                let field = |i: Option<usize>| {
+                    i.and_then(|i| record.get(i))
+                        .unwrap_or_default()
+                        .to_string()
+                };
+                let email = field(Some(email_column));
+                ParsedRow {
+                    line_number: csv_line(record.position())?,
+                    name: name_or_local_part(&field(name_column), &email),
+                    email,
+                    ..Default::default()
+                }
+            }
+            Err(e) => ParsedRow {
+                line_number: csv_line(e.position())?,
+                error: Some(e.to_string()),
+                ..Default::default()
+            },
+        };
+        parsed.rows.push(row);
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod test {
+    use claims::assert_err;
+
+    use super::parse;
+
+    #[test]
+    fn columns_are_found_by_header_in_any_order() {
+        let parsed =
+            parse(b"Name,Company,Email\nUrsula,Acme, ursula@example.com \n,,octavia@example.com\n")
+                .unwrap();
+
+        let rows: Vec<_> = parsed
+            .rows
+            .iter()
+            .map(|r| {
+                (
+                    r.line_number,
+                    r.email.as_str(),
+                    r.name.as_str(),
+                    r.error.is_some(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                (2, "ursula@example.com", "Ursula", false),
+                (3, "octavia@example.com", "octavia", false)
+            ]
+        );
+    }
+
+    #[test]
+    fn the_name_column_is_optional() {
+        let parsed = parse(b"email\nursula@example.com\n").unwrap();
+
+        assert_eq!(parsed.rows[0].name, "ursula");
+        assert!(parsed.rows[0].error.is_none());
+    }
+
+    #[test]
+    fn a_file_without_an_email_column_is_rejected() {
+        assert_err!(parse(b"name,address\nUrsula,ursula@example.com\n"));
+    }
+
+    #[test]
+    fn unreadable_rows_are_kept_as_errors() {
+        let parsed = parse(b"email,name\nursula@example.com,\xff\xfe\n").unwrap();
+        assert_eq!(parsed.rows[0].line_number, 2);
+        assert!(parsed.rows[0].error.is_some());
+    }
+}
@@ -0,0 +1,209 @@
+use std::io::Cursor;
+
+use chrono::{DateTime, Utc};
+
+use super::{
+    ImportedIssue, MAX_UNPACKED_BYTES, ParsedImport, ParsedRow, csv_line, name_or_local_part,
+    read_archive_entry,
+};
+use crate::domain::SubscriptionStatus;
+
+const TEXT_WIDTH: usize = 80;
+
+/// A Substack export zip holds `email_list.<publication>.csv`, `posts.csv` and one
+/// `posts/<post_id>.html` file per post.
+pub fn parse(data: &[u8]) -> Result<ParsedImport, String> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(data))
+        .map_err(|e| format!("The file could not be read as a zip archive: {e}"))?;
+    let email_list = archive
+        .file_names()
+        .find(|name| name.starts_with("email_list") && name.ends_with(".csv"))
+        .map(str::to_string)
+        .ok_or("The archive doesn't contain a Substack email list.")?;
+    let mut remaining = MAX_UNPACKED_BYTES;
+    let mut read_entry = |name: &str| -> Result<Option<Vec<u8>>, String> {
+        let entry = match archive.by_name(name) {
+            Ok(entry) => entry,
+            Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+            Err(e) => return Err(format!("The zip archive is damaged: {e}")),
+        };
+        read_archive_entry(entry, &mut remaining).map(Some)
+    };
+
+    let mut parsed = ParsedImport::default();
+    let members = read_entry(&email_list)?.unwrap_or_default();
+    parse_members(&members, &email_list, &mut parsed)?;
+
+    if let Some(posts) = read_entry("posts.csv")? {
+        parse_posts(&posts, &mut read_entry, &mut parsed)?;
+    }
+    Ok(parsed)
+}
+
+fn parse_members(data: &[u8], file_name: &str, parsed: &mut ParsedImport) -> Result<(), String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(data);
+    let headers = reader
+        .headers()
+        .map_err(|e| format!("{file_name} could not be read as CSV: {e}"))?
+        .clone();
+    let column = |name: &str| headers.iter().position(|h| h.eq_ignore_ascii_case(name));
+    let email_column =
+        column("email").ok_or_else(|| format!("{file_name} is missing the `email` column."))?;
+    let name_column = column("name");
+    let disabled_column = column("email_disabled");
+    let created_at_column = column("created_at");
+
+    for record in reader.records() {
+        let row = match record {
+            Ok(record) => {
+                let field = |i: Option<usize>| i.and_then(|i| record.get(i)).unwrap_or_default();
+                let email = field(Some(email_column)).to_string();
+                // Substack keeps people who turned off emails on the list, we don't.
+                let status = if field(disabled_column).eq_ignore_ascii_case("true") {
+                    SubscriptionStatus::Unsubscribed
+                } else {
+                    SubscriptionStatus::Confirmed
+                };
+                ParsedRow {
+                    file_name: file_name.to_string(),
+                    line_number: csv_line(record.position())?,
+                    name: name_or_local_part(field(name_column), &email),
+                    email,
+                    status: Some(status),
+                    subscribed_at: parse_date(field(created_at_column)),
+                    error: None,
+                }
+            }
+            Err(e) => ParsedRow {
+                file_name: file_name.to_string(),
+                line_number: csv_line(e.position())?,
+                error: Some(e.to_string()),
+                ..Default::default()
+            },
+        };
+        parsed.rows.push(row);
+    }
+    Ok(())
+}
+
+// Drafts and posts whose content is missing from the archive are skipped.
+fn parse_posts(
+    data: &[u8],
+    read_entry: &mut impl FnMut(&str) -> Result<Option<Vec<u8>>, String>,
+    parsed: &mut ParsedImport,
+) -> Result<(), String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(data);
+    let headers = reader
+        .headers()
+        .map_err(|e| format!("posts.csv could not be read as CSV: {e}"))?
+        .clone();
+    let column = |name: &str| headers.iter().position(|h| h.eq_ignore_ascii_case(name));
+    let (Some(id_column), Some(title_column)) = (column("post_id"), column("title")) else {
+        return Err("posts.csv is missing the `post_id` or `title` column.".into());
+    };
+    let published_column = column("is_published");
+    let date_column = column("post_date");
+
+    for record in reader.records() {
+        let record = record.map_err(|e| format!("posts.csv could not be read: {e}"))?;
+        let field = |i: Option<usize>| i.and_then(|i| record.get(i)).unwrap_or_default();
+        if field(published_column).eq_ignore_ascii_case("false") {
+            continue;
+        }
+        let Some(published_at) = parse_date(field(date_column)) else {
+            continue;
+        };
+        let source_id = field(Some(id_column));
+        let Some(html) = read_entry(&format!("posts/{source_id}.html"))? else {
+            continue;
+        };
+        let html_content = String::from_utf8_lossy(&html).into_owned();
+        let text_content =
+            html2text::from_read(html_content.as_bytes(), TEXT_WIDTH).unwrap_or_default();
+        parsed.issues.push(ImportedIssue {
+            source_id: source_id.to_string(),
+            title: field(Some(title_column)).to_string(),
+            html_content,
+            text_content,
+            published_at,
+        });
+    }
+    Ok(())
+}
+
+fn parse_date(raw: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(raw)
+        .ok()
+        .map(|d| d.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+
+    use chrono::{TimeZone, Utc};
+
+    use super::parse;
+    use crate::domain::SubscriptionStatus;
+
+    fn archive(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut archive = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for (name, contents) in files {
+            archive
+                .start_file(*name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            archive.write_all(contents.as_bytes()).unwrap();
+        }
+        archive.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn members_and_published_posts_are_read() {
+        let data = archive(&[
+            (
+                "email_list.mypub.csv",
+                "email,active_subscription,email_disabled,created_at\n\
+                 ursula@example.com,false,false,2021-03-04T05:06:07.000Z\n\
+                 octavia@example.com,true,true,2022-01-01T00:00:00.000Z\n",
+            ),
+            (
+                "posts.csv",
+                "post_id,post_date,is_published,title\n\
+                 1.hello,2021-04-01T10:00:00.000Z,true,Hello world\n\
+                 2.draft,,false,Unfinished\n",
+            ),
+            ("posts/1.hello.html", "<p>Our <b>first</b> post</p>"),
+        ]);
+
+        let parsed = parse(&data).unwrap();
+
+        assert_eq!(parsed.rows.len(), 2);
+        assert_eq!(parsed.rows[0].name, "ursula");
+        assert_eq!(parsed.rows[0].status, Some(SubscriptionStatus::Confirmed));
+        assert_eq!(
+            parsed.rows[0].subscribed_at,
+            Some(Utc.with_ymd_and_hms(2021, 3, 4, 5, 6, 7).unwrap())
+        );
+        assert_eq!(
+            parsed.rows[1].status,
+            Some(SubscriptionStatus::Unsubscribed)
+        );
+
+        assert_eq!(parsed.issues.len(), 1);
+        assert_eq!(parsed.issues[0].title, "Hello world");
+        assert_eq!(parsed.issues[0].source_id, "1.hello");
+        assert!(parsed.issues[0].text_content.contains("first"));
+    }
+
+    #[test]
+    fn an_archive_without_an_email_list_is_rejected() {
+        let data = archive(&[("posts.csv", "post_id,title\n")]);
+        assert!(parse(&data).is_err());
+    }
+}
@@ -0,0 +1,401 @@
+use std::error::Error;
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgExecutor;
+
+use crate::issue_rendering::render_admin_template;
+
+/// What templates are checked and previewed with.
+pub const SAMPLE_NAME: &str = "Ada";
+// No characters the templates would escape, so it can be found in the output.
+const SAMPLE_LINK: &str = "sample-link";
+
+/// The emails sent to one person on their own request rather than to the whole list.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionalEmail {
+    Confirmation,
+    ConfirmationReminder,
+    DataExport,
+    DataErasure,
+    PasswordReset,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TemplateVariable {
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+const CONFIRMATION_VARIABLES: &[TemplateVariable] = &[
+    TemplateVariable {
+        name: "name",
+        description: "The name the subscriber signed up with.",
+    },
+    TemplateVariable {
+        name: "link",
+        description: "The link that confirms the subscription.",
+    },
+];
+
+const DATA_REQUEST_VARIABLES: &[TemplateVariable] = &[
+    TemplateVariable {
+        name: "action",
+        description: "What the link does, e.g. \"download a copy of the data we hold about you\".",
+    },
+    TemplateVariable {
+        name: "link",
+        description: "The link that continues the request. It is valid for 24 hours.",
+    },
+];
+
+const PASSWORD_RESET_VARIABLES: &[TemplateVariable] = &[
+    TemplateVariable {
+        name: "username",
+        description: "The admin whose password is being reset.",
+    },
+    TemplateVariable {
+        name: "link",
+        description: "The link to choose a new password. It is valid for an hour.",
+    },
+];
+
+impl TransactionalEmail {
+    pub const ALL: [TransactionalEmail; 5] = [
+        TransactionalEmail::Confirmation,
+        TransactionalEmail::ConfirmationReminder,
+        TransactionalEmail::DataExport,
+        TransactionalEmail::DataErasure,
+        TransactionalEmail::PasswordReset,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransactionalEmail::Confirmation => "confirmation",
+            TransactionalEmail::ConfirmationReminder => "confirmation_reminder",
+            TransactionalEmail::DataExport => "data_export",
+            TransactionalEmail::DataErasure => "data_erasure",
+            TransactionalEmail::PasswordReset => "password_reset",
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            TransactionalEmail::Confirmation => "Subscription confirmation",
+            TransactionalEmail::ConfirmationReminder => "Confirmation reminder",
+            TransactionalEmail::DataExport => "Data export link",
+            TransactionalEmail::DataErasure => "Data erasure link",
+            TransactionalEmail::PasswordReset => "Admin password reset",
+        }
+    }
+
+    pub fn variables(&self) -> &'static [TemplateVariable] {
+        match self {
+            TransactionalEmail::Confirmation | TransactionalEmail::ConfirmationReminder => {
+                CONFIRMATION_VARIABLES
+            }
+            TransactionalEmail::DataExport | TransactionalEmail::DataErasure => {
+                DATA_REQUEST_VARIABLES
+            }
+            TransactionalEmail::PasswordReset => PASSWORD_RESET_VARIABLES,
+        }
+    }
+
+    /// Values for every variable, with `link` standing in for the real one.
+    pub fn sample_variables<'a>(&self, link: &'a str) -> Vec<(&'static str, &'a str)> {
+        match self {
+            TransactionalEmail::Confirmation | TransactionalEmail::ConfirmationReminder => {
+                vec![("name", SAMPLE_NAME), ("link", link)]
+            }
+            TransactionalEmail::DataExport => vec![
+                ("action", "download a copy of the data we hold about you"),
+                ("link", link),
+            ],
+            TransactionalEmail::DataErasure => vec![
+                ("action", "permanently erase the data we hold about you"),
+                ("link", link),
+            ],
+            TransactionalEmail::PasswordReset => vec![("username", "admin"), ("link", link)],
+        }
+    }
+
+    fn default_subject(&self) -> &'static str {
+        match self {
+            TransactionalEmail::Confirmation => "Please confirm your subscription",
+            TransactionalEmail::ConfirmationReminder => {
+                "Reminder: please confirm your subscription"
+            }
+            TransactionalEmail::DataExport => "Your data export",
+            TransactionalEmail::DataErasure => "Confirm the erasure of your data",
+            TransactionalEmail::PasswordReset => "Reset your password",
+        }
+    }
+
+    /// The HTML and text templates in `views/`.
+    fn default_views(&self) -> (&'static str, &'static str) {
+        match self {
+            TransactionalEmail::Confirmation | TransactionalEmail::ConfirmationReminder => (
+                "confirm_subscription_letter.html",
+                "confirm_subscription_letter.txt",
+            ),
+            TransactionalEmail::DataExport | TransactionalEmail::DataErasure => {
+                ("data_request_letter.html", "data_request_letter.txt")
+            }
+            TransactionalEmail::PasswordReset => {
+                ("password_reset_letter.html", "password_reset_letter.txt")
+            }
+        }
+    }
+}
+
+/// An email's templates, edited by an admin if `updated_at` is set.
+#[derive(Serialize, Debug)]
+pub struct EmailTemplate {
+    pub kind: TransactionalEmail,
+    pub title: &'static str,
+    pub subject: String,
+    pub html_template: String,
+    pub text_template: String,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl EmailTemplate {
+    pub fn bundled(kind: TransactionalEmail) -> Self {
+        let (html_view, text_view) = kind.default_views();
+        let read_view = |name: &str| {
+            std::fs::read_to_string(format!("views/{name}"))
+                .expect("Failed to read a bundled email template")
+        };
+        Self {
+            kind,
+            title: kind.title(),
+            subject: kind.default_subject().to_string(),
+            html_template: read_view(html_view),
+            text_template: read_view(text_view),
+            updated_at: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct RenderedEmail {
+    pub subject: String,
+    pub html: String,
+    pub text: String,
+}
+
+/// Only the HTML is escaped. The subject is a header and the text body is sent as it is.
+pub fn render_email(
+    template: &EmailTemplate,
+    variables: &[(&str, &str)],
+) -> Result<RenderedEmail, tera::Error> {
+    let mut ctx = tera::Context::new();
+    for (key, value) in variables.iter().copied() {
+        ctx.insert(key, value);
+    }
+    let subject = render_admin_template(&template.subject, &ctx, false)?;
+    Ok(RenderedEmail {
+        subject: subject.split_whitespace().collect::<Vec<_>>().join(" "),
+        html: render_admin_template(&template.html_template, &ctx, true)?,
+        text: render_admin_template(&template.text_template, &ctx, false)?,
+    })
+}
+
+/// Checked before an edit is stored, so a broken template is never found out at send time.
+pub fn validate_email_template(template: &EmailTemplate) -> Result<(), String> {
+    let rendered =
+        render_email(template, &template.kind.sample_variables(SAMPLE_LINK)).map_err(|e| {
+            let detail = e
+                .source()
+                .map_or_else(|| e.to_string(), ToString::to_string);
+            format!("The email is not a valid template: {detail}")
+        })?;
+    if rendered.subject.is_empty() {
+        return Err("The email needs a subject.".into());
+    }
+    if !rendered.html.contains(SAMPLE_LINK) || !rendered.text.contains(SAMPLE_LINK) {
+        return Err("Both versions of the email must include {{ link }}.".into());
+    }
+    Ok(())
+}
+
+// A row of `transactional_email_templates`.
+struct EditedTemplate {
+    kind: String,
+    subject: String,
+    html_template: String,
+    text_template: String,
+    updated_at: DateTime<Utc>,
+}
+
+impl EditedTemplate {
+    fn into_template(self, kind: TransactionalEmail) -> EmailTemplate {
+        EmailTemplate {
+            kind,
+            title: kind.title(),
+            subject: self.subject,
+            html_template: self.html_template,
+            text_template: self.text_template,
+            updated_at: Some(self.updated_at),
+        }
+    }
+}
+
+/// Kinds nobody has edited come back with their bundled templates.
+#[tracing::instrument(name = "Getting an email template", skip(executor))]
+pub async fn get_email_template(
+    executor: impl PgExecutor<'_>,
+    kind: TransactionalEmail,
+) -> Result<EmailTemplate, sqlx::Error> {
+    let edited = sqlx::query_as!(
+        EditedTemplate,
+        r#"
+        SELECT kind, subject, html_template, text_template, updated_at
+        FROM transactional_email_templates
+        WHERE kind = $1
+        "#,
+        kind.as_str()
+    )
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(edited.map_or_else(
+        || EmailTemplate::bundled(kind),
+        |edited| edited.into_template(kind),
+    ))
+}
+
+#[tracing::instrument(name = "Getting the email templates", skip(executor))]
+pub async fn get_email_templates(
+    executor: impl PgExecutor<'_>,
+) -> Result<Vec<EmailTemplate>, sqlx::Error> {
+    let mut edited = sqlx::query_as!(
+        EditedTemplate,
+        r#"
+        SELECT kind, subject, html_template, text_template, updated_at
+        FROM transactional_email_templates
+        "#
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(TransactionalEmail::ALL
+        .into_iter()
+        .map(
+            |kind| match edited.iter().position(|e| e.kind == kind.as_str()) {
+                Some(i) => edited.swap_remove(i).into_template(kind),
+                None => EmailTemplate::bundled(kind),
+            },
+        )
+        .collect())
+}
+
+/// Renders the email with the admin's templates, falling back to the bundled ones if they
+/// no longer render, so the subscriber still gets their link.
+pub async fn render_transactional_email(
+    executor: impl PgExecutor<'_>,
+    kind: TransactionalEmail,
+    variables: &[(&str, &str)],
+) -> Result<RenderedEmail, anyhow::Error> {
+    let template = get_email_template(executor, kind)
+        .await
+        .context("Failed to read the email template.")?;
+    match render_email(&template, variables) {
+        Ok(rendered) => Ok(rendered),
+        Err(e) => {
+            tracing::error!(
+            error.cause_chain = ?e,
+            error.message = %e,
+            kind = kind.as_str(),
+            "Failed to render an edited email template. Sending the bundled one",
+            );
+            render_email(&EmailTemplate::bundled(kind), variables)
+                .context("Failed to render the bundled email template.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        EmailTemplate, SAMPLE_LINK, TransactionalEmail, render_email, validate_email_template,
+    };
+
+    #[test]
+    fn bundled_templates_are_valid() {
+        for kind in TransactionalEmail::ALL {
+            assert_eq!(
+                validate_email_template(&EmailTemplate::bundled(kind)),
+                Ok(()),
+                "{kind:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn only_the_html_is_escaped() {
+        let template = EmailTemplate {
+            subject: "Welcome\n{{ name }}".into(),
+            html_template: "<p>{{ name }}</p><a href=\"{{ link | safe }}\">Confirm</a>".into(),
+            text_template: "{{ name }}: {{ link }}".into(),
+            ..EmailTemplate::bundled(TransactionalEmail::Confirmation)
+        };
+
+        let rendered = render_email(
+            &template,
+            &[("name", "Tom & Jerry"), ("link", "https://x.test/?a=1&b=2")],
+        )
+        .unwrap();
+
+        assert_eq!(rendered.subject, "Welcome Tom & Jerry");
+        assert_eq!(
+            rendered.html,
+            "<p>Tom &amp; Jerry</p><a href=\"https://x.test/?a=1&b=2\">Confirm</a>"
+        );
+        assert_eq!(rendered.text, "Tom & Jerry: https://x.test/?a=1&b=2");
+    }
+
+    #[test]
+    fn templates_without_the_link_are_rejected() {
+        let template = EmailTemplate {
+            text_template: "Please confirm.".into(),
+            ..EmailTemplate::bundled(TransactionalEmail::Confirmation)
+        };
+        assert!(validate_email_template(&template).is_err());
+
+        let template = EmailTemplate {
+            html_template: format!("<a href=\"{{{{ link }}}}\">{SAMPLE_LINK}</a>"),
+            text_template: "{{ link }}".into(),
+            subject: "{% if %}".into(),
+            ..EmailTemplate::bundled(TransactionalEmail::Confirmation)
+        };
+        assert!(
+            validate_email_template(&template)
+                .unwrap_err()
+                .starts_with("The email is not a valid template")
+        );
+    }
+
+    #[test]
+    fn templates_cannot_read_the_environment() {
+        let template = EmailTemplate {
+            subject: r#"{{ get_env(name="PATH") }}"#.into(),
+            ..EmailTemplate::bundled(TransactionalEmail::Confirmation)
+        };
+        assert!(validate_email_template(&template).is_err());
+    }
+
+    #[test]
+    fn templates_without_a_subject_are_rejected() {
+        let template = EmailTemplate {
+            subject: "  ".into(),
+            ..EmailTemplate::bundled(TransactionalEmail::DataExport)
+        };
+        assert_eq!(
+            validate_email_template(&template),
+            Err("The email needs a subject.".into())
+        );
+    }
+}
@@ -0,0 +1,178 @@
+use wiremock::{
+    Mock, ResponseTemplate,
+    matchers::{method, path},
+};
+
+use crate::helpers::{
+    TestApp, assert_is_redirect_to, create_confirmed_subscriber, create_unconfirmed_subscriber,
+    spawn_app,
+};
+
+async fn emails_with_status(app: &TestApp, status: &str) -> Vec<String> {
+    sqlx::query_scalar!(
+        "SELECT email FROM subscriptions WHERE status = $1 ORDER BY email",
+        status
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn anonymous_users_cannot_export() {
+    let app = spawn_app().await;
+
+    assert_is_redirect_to(&app.get_admin_export("subscribers").await, "/login");
+    assert_is_redirect_to(&app.get_admin_export("issues").await, "/login");
+}
+
+#[tokio::test]
+async fn subscribers_are_exported_as_csv_with_a_header() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    create_confirmed_subscriber(&app).await;
+    create_unconfirmed_subscriber(&app).await;
+
+    let response = app.get_admin_export("subscribers?format=csv").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(
+        response.headers()["Content-Type"]
+            .to_str()
+            .unwrap()
+            .starts_with("text/csv")
+    );
+    assert!(
+        response.headers()["Content-Disposition"]
+            .to_str()
+            .unwrap()
+            .contains("subscribers-")
+    );
+    let body = response.text().await.unwrap();
+    let mut lines = body.lines();
+    assert!(lines.next().unwrap().starts_with("id,email,name,status,"));
+    assert_eq!(lines.count(), 2);
+}
+
+#[tokio::test]
+async fn csv_cells_that_look_like_formulas_are_defused() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    create_confirmed_subscriber(&app).await;
+    sqlx::query!("UPDATE subscriptions SET name = '=HYPERLINK(\"https://evil.example\")'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    let body = app
+        .get_admin_export("subscribers?format=csv")
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    let mut reader = csv::Reader::from_reader(body.as_bytes());
+    let record = reader.records().next().unwrap().unwrap();
+    assert_eq!(&record[2], "'=HYPERLINK(\"https://evil.example\")");
+}
+
+#[tokio::test]
+async fn subscribers_can_be_exported_as_json_and_filtered_by_status() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    create_confirmed_subscriber(&app).await;
+    create_unconfirmed_subscriber(&app).await;
+    let confirmed = emails_with_status(&app, "confirmed").await;
+
+    let response = app
+        .get_admin_export("subscribers?format=json&status=confirmed")
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let exported: Vec<serde_json::Value> = response.json().await.unwrap();
+    assert_eq!(exported.len(), 1);
+    assert_eq!(exported[0]["email"], confirmed[0]);
+    assert_eq!(exported[0]["status"], "confirmed");
+
+    let response = app.get_admin_export("subscribers?status=nonsense").await;
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn subscribers_can_be_filtered_by_segment() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    let emails = emails_with_status(&app, "confirmed").await;
+    sqlx::query!(
+        r#"UPDATE subscriptions SET attributes = '{"plan": "pro"}' WHERE email = $1"#,
+        emails[0]
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    let exported: Vec<serde_json::Value> = app
+        .get_admin_export("subscribers?format=json&segment_attribute=plan&segment_value=pro")
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(exported.len(), 1);
+    assert_eq!(exported[0]["email"], emails[0]);
+    assert_eq!(exported[0]["attributes"], r#"{"plan": "pro"}"#);
+}
+
+#[tokio::test]
+async fn an_empty_export_is_valid_json() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    let exported: Vec<serde_json::Value> = app
+        .get_admin_export("issues?format=json")
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    assert!(exported.is_empty());
+}
+
+#[tokio::test]
+async fn newsletter_issues_are_exported() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    app.post_newsletters(&serde_json::json!({
+        "title": "Newsletter title",
+        "text": "Newsletter body as plain text",
+        "html": "<p>Newsletter body, with a comma</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    }))
+    .await;
+
+    let exported: Vec<serde_json::Value> = app
+        .get_admin_export("issues?format=json")
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(exported.len(), 1);
+    assert_eq!(exported[0]["title"], "Newsletter title");
+
+    let csv = app
+        .get_admin_export("issues?format=csv")
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(csv.contains("\"<p>Newsletter body, with a comma</p>\""));
+}
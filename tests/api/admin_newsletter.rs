@@ -170,6 +170,12 @@ async fn confirmed_subscriber_should_get_a_newsletter() {
         html_page.contains("The newsletter issue has been accepted - emails will go out shortly.")
     );
     app.dispatch_all_pending_emails().await;
+
+    let delivery = sqlx::query!("SELECT outcome FROM issue_delivery_log")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(delivery.outcome, "sent");
 }
 
 #[tokio::test]
@@ -245,3 +251,77 @@ async fn concurrent_form_submission_is_handled_gracefully() {
 
     app.dispatch_all_pending_emails().await;
 }
+
+#[tokio::test]
+async fn markdown_issues_are_sent_as_html_and_text() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    create_confirmed_subscriber(&app).await;
+    let name = sqlx::query_scalar!("SELECT name FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+
+    Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let body = serde_json::json!({
+        "title": "Markdown issue",
+        "html": "<p>Ignored</p>",
+        "text": "Ignored",
+        "markdown": "# Hello {{ subscriber.name }}\n\nA [link](https://example.com).",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    let response = app.post_newsletters(&body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
+
+    let issue = sqlx::query!("SELECT html_content, markdown_content FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(issue.html_content.contains("<h1>"));
+    assert!(!issue.html_content.contains("Ignored"));
+    assert_eq!(
+        issue.markdown_content.as_deref(),
+        Some("# Hello {{ subscriber.name }}\n\nA [link](https://example.com).")
+    );
+
+    let requests = app.email_server.received_requests().await.unwrap();
+    let email_request = requests.last().unwrap();
+    let email: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    let html = email["html"].as_str().unwrap();
+    assert!(html.contains(&format!("<h1>Hello {name}</h1>")));
+    assert!(html.contains(r#"<a href="https://example.com" rel="noopener noreferrer">link</a>"#));
+    let text = email["text"].as_str().unwrap();
+    assert!(text.contains(&format!("Hello {name}")));
+    assert!(!text.contains('<'));
+}
+
+#[tokio::test]
+async fn submitted_html_is_sanitised_and_the_text_is_derived_from_it() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    let body = serde_json::json!({
+        "title": "Newsletter title",
+        "html": r#"<p onmouseover="steal()">Hello <strong>there</strong></p><script>steal()</script>"#,
+        "text": "",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    let response = app.post_newsletters(&body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    let issue = sqlx::query!("SELECT html_content, text_content FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(issue.html_content, "<p>Hello <strong>there</strong></p>");
+    assert!(issue.text_content.contains("Hello"));
+    assert!(issue.text_content.contains("there"));
+    assert!(!issue.text_content.contains("steal"));
+}
@@ -0,0 +1,268 @@
+use uuid::Uuid;
+use wiremock::{
+    Mock, ResponseTemplate,
+    matchers::{any, method, path},
+};
+
+use crate::helpers::{TestApp, assert_is_redirect_to, create_confirmed_subscriber, spawn_app};
+
+const DRAFTS_PATH: &str = "/admin/newsletters/drafts";
+
+async fn create_draft(app: &TestApp, body: &serde_json::Value) -> Uuid {
+    let response = app.post_newsletter_drafts("", body).await;
+    let draft_id = sqlx::query_scalar!(
+        "SELECT draft_id FROM newsletter_drafts ORDER BY created_at DESC LIMIT 1"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_is_redirect_to(&response, &format!("{DRAFTS_PATH}/{draft_id}"));
+    draft_id
+}
+
+fn draft_body(title: &str) -> serde_json::Value {
+    serde_json::json!({
+        "title": title,
+        "html": "<p>Hi {{ subscriber.name }}</p>",
+        "text": "Hi {{ subscriber.name }}",
+    })
+}
+
+async fn n_queued(app: &TestApp) -> i64 {
+    sqlx::query_scalar!(r#"SELECT count(*) AS "count!" FROM issue_delivery_queue"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn anonymous_users_cannot_manage_drafts() {
+    let app = spawn_app().await;
+
+    assert_is_redirect_to(&app.get_newsletter_drafts("").await, "/login");
+    assert_is_redirect_to(
+        &app.post_newsletter_drafts("", &draft_body("Draft")).await,
+        "/login",
+    );
+}
+
+#[tokio::test]
+async fn saving_a_draft_does_not_send_anything() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let draft_id = create_draft(&app, &draft_body("Half written")).await;
+    let response = app
+        .post_newsletter_drafts(&format!("/{draft_id}"), &draft_body("Still half written"))
+        .await;
+    assert_is_redirect_to(&response, &format!("{DRAFTS_PATH}/{draft_id}"));
+    app.dispatch_all_pending_emails().await;
+
+    assert_eq!(n_queued(&app).await, 0);
+    let list = app.get_newsletter_drafts("").await.text().await.unwrap();
+    assert!(list.contains("Still half written"));
+    let edit = app
+        .get_newsletter_drafts(&format!("/{draft_id}"))
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(edit.contains("value=\"Still half written\""));
+}
+
+#[tokio::test]
+async fn autosave_updates_the_draft_without_redirecting() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let draft_id = create_draft(&app, &serde_json::json!({})).await;
+
+    let response = app
+        .post_newsletter_drafts(&format!("/{draft_id}/autosave"), &draft_body("Autosaved"))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 204);
+    let title = sqlx::query_scalar!(
+        "SELECT title FROM newsletter_drafts WHERE draft_id = $1",
+        draft_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(title, "Autosaved");
+
+    let response = app
+        .post_newsletter_drafts(
+            &format!("/{}/autosave", Uuid::new_v4()),
+            &draft_body("Lost"),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn preview_renders_the_draft_for_a_subscriber() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    create_confirmed_subscriber(&app).await;
+    let subscriber = sqlx::query!("SELECT name, email FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    let draft_id = create_draft(&app, &draft_body("Preview")).await;
+
+    let response = app
+        .get_newsletter_drafts(&format!("/{draft_id}/preview?email={}", subscriber.email))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["Content-Security-Policy"], "sandbox");
+    assert_eq!(
+        response.text().await.unwrap(),
+        format!("<p>Hi {}</p>", subscriber.name)
+    );
+
+    let text = app
+        .get_newsletter_drafts(&format!("/{draft_id}/preview?format=text"))
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(text, "Hi ");
+}
+
+#[tokio::test]
+async fn publishing_a_draft_sends_it_once() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let draft_id = create_draft(&app, &draft_body("Ready")).await;
+    let body = serde_json::json!({ "idempotency_key": Uuid::new_v4().to_string() });
+    for _ in 0..2 {
+        let response = app
+            .post_newsletter_drafts(&format!("/{draft_id}/publish"), &body)
+            .await;
+        assert_is_redirect_to(&response, DRAFTS_PATH);
+    }
+    let list = app.get_newsletter_drafts("").await.text().await.unwrap();
+    assert!(list.contains("emails will go out shortly"));
+    app.dispatch_all_pending_emails().await;
+
+    let published = sqlx::query_scalar!("SELECT title FROM newsletter_issues")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(published, vec!["Ready".to_string()]);
+    let n_drafts = sqlx::query_scalar!(r#"SELECT count(*) AS "count!" FROM newsletter_drafts"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(n_drafts, 0);
+}
+
+#[tokio::test]
+async fn a_draft_without_a_title_cannot_be_published() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    create_confirmed_subscriber(&app).await;
+    let draft_id = create_draft(&app, &draft_body(" ")).await;
+
+    let response = app
+        .post_newsletter_drafts(
+            &format!("/{draft_id}/publish"),
+            &serde_json::json!({ "idempotency_key": Uuid::new_v4().to_string() }),
+        )
+        .await;
+
+    assert_is_redirect_to(&response, &format!("{DRAFTS_PATH}/{draft_id}"));
+    assert_eq!(n_queued(&app).await, 0);
+}
+
+#[tokio::test]
+async fn deleted_drafts_are_gone() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let draft_id = create_draft(&app, &draft_body("Scrap this")).await;
+
+    let response = app
+        .post_newsletter_drafts(&format!("/{draft_id}/delete"), &serde_json::json!({}))
+        .await;
+
+    assert_is_redirect_to(&response, DRAFTS_PATH);
+    let response = app.get_newsletter_drafts(&format!("/{draft_id}")).await;
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn markdown_drafts_are_previewed_as_html() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let draft_id = create_draft(
+        &app,
+        &serde_json::json!({
+            "title": "Markdown draft",
+            "markdown": "Some **bold** words",
+        }),
+    )
+    .await;
+
+    let preview = app
+        .get_newsletter_drafts(&format!("/{draft_id}/preview"))
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(preview.contains("<strong>bold</strong>"));
+
+    let edit = app
+        .get_newsletter_drafts(&format!("/{draft_id}"))
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(edit.contains("Some **bold** words"));
+}
+
+#[tokio::test]
+async fn a_published_issue_can_be_copied_into_a_new_draft() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let body = serde_json::json!({
+        "title": "Published",
+        "html": "",
+        "text": "",
+        "markdown": "Written in *Markdown*",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    assert_is_redirect_to(&app.post_newsletters(&body).await, "/admin/newsletters");
+    let issue_id = sqlx::query_scalar!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+
+    let response = app.post_newsletter_issue_draft(issue_id).await;
+
+    let draft = sqlx::query!("SELECT draft_id, title, markdown_content FROM newsletter_drafts")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_is_redirect_to(&response, &format!("{DRAFTS_PATH}/{}", draft.draft_id));
+    assert_eq!(draft.title, "Published");
+    assert_eq!(draft.markdown_content, "Written in *Markdown*");
+
+    let response = app.post_newsletter_issue_draft(Uuid::new_v4()).await;
+    assert_eq!(response.status().as_u16(), 404);
+}
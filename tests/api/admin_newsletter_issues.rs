@@ -0,0 +1,123 @@
+use wiremock::{
+    Mock, ResponseTemplate,
+    matchers::{method, path},
+};
+
+use crate::helpers::{TestApp, assert_is_redirect_to, create_confirmed_subscriber, spawn_app};
+
+async fn publish_issue(app: &TestApp, title: &str) {
+    let body = serde_json::json!({
+        "title": title,
+        "html": "<p>Hello <script>alert(1)</script></p>",
+        "text": "Hello in plain text",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    let response = app.post_newsletters(&body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+}
+
+async fn issue_ids(app: &TestApp) -> Vec<uuid::Uuid> {
+    sqlx::query_scalar!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn anonymous_users_cannot_see_the_issue_archive() {
+    let app = spawn_app().await;
+
+    let response = app.get_admin_newsletter_issues("").await;
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn published_issues_are_listed_with_author_and_delivery_stats() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    publish_issue(&app, "First issue").await;
+    app.dispatch_all_pending_emails().await;
+    publish_issue(&app, "Second issue").await;
+
+    let html = app
+        .get_admin_newsletter_issues("")
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    assert!(html.contains("2 issue(s)"));
+    assert!(html.contains(&app.test_user.username));
+    // The first issue went out, the second is still waiting in the queue.
+    let first = html.find("First issue").unwrap();
+    let second = html.find("Second issue").unwrap();
+    assert!(second < first, "The newest issue should come first");
+    assert!(html[first..].contains("<td>1</td>"));
+    assert!(html[second..first].contains("<td>1</td>"));
+}
+
+#[tokio::test]
+async fn the_issue_list_is_paginated() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    for i in 0..26 {
+        publish_issue(&app, &format!("Issue number {i}")).await;
+    }
+
+    let first_page = app
+        .get_admin_newsletter_issues("")
+        .await
+        .text()
+        .await
+        .unwrap();
+    let second_page = app
+        .get_admin_newsletter_issues("?page=2")
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    assert!(first_page.contains("Page 1 of 2"));
+    assert_eq!(second_page.matches("/admin/newsletters/issues/").count(), 1);
+}
+
+#[tokio::test]
+async fn issue_detail_shows_both_versions_without_running_scripts() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    publish_issue(&app, "First issue").await;
+    let issue_id = issue_ids(&app).await.remove(0);
+
+    let html = app
+        .get_admin_newsletter_issues(&format!("/{issue_id}"))
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    assert!(html.contains("First issue"));
+    assert!(html.contains("Hello in plain text"));
+    assert!(html.contains("sandbox srcdoc=\"&lt;p&gt;Hello "));
+    assert!(!html.contains("alert(1)"));
+}
+
+#[tokio::test]
+async fn unknown_issues_are_not_found() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    let response = app
+        .get_admin_newsletter_issues(&format!("/{}", uuid::Uuid::new_v4()))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 404);
+}
@@ -0,0 +1,193 @@
+use uuid::Uuid;
+
+use crate::helpers::{
+    TestApp, assert_is_redirect_to, create_confirmed_subscriber, create_unconfirmed_subscriber,
+    spawn_app,
+};
+
+async fn subscribers(app: &TestApp) -> Vec<(Uuid, String, String, String)> {
+    sqlx::query!("SELECT id, name, email, status FROM subscriptions ORDER BY subscribed_at")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|r| (r.id, r.name, r.email, r.status))
+        .collect()
+}
+
+#[tokio::test]
+async fn anonymous_users_cannot_manage_subscribers() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let (id, ..) = subscribers(&app).await.remove(0);
+
+    assert_is_redirect_to(&app.get_admin_subscribers("").await, "/login");
+    assert_is_redirect_to(&app.get_admin_subscriber(id).await, "/login");
+    let response = app
+        .post_admin_subscriber_action(id, "delete", &serde_json::json!({}))
+        .await;
+    assert_is_redirect_to(&response, "/login");
+    assert_eq!(subscribers(&app).await.len(), 1);
+}
+
+#[tokio::test]
+async fn subscribers_can_be_searched_and_filtered() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    create_confirmed_subscriber(&app).await;
+    create_unconfirmed_subscriber(&app).await;
+    let all = subscribers(&app).await;
+    let (_, _, confirmed_email, _) = &all[0];
+    let (_, _, pending_email, _) = &all[1];
+
+    let html = app.get_admin_subscribers("").await.text().await.unwrap();
+    assert!(html.contains(confirmed_email));
+    assert!(html.contains(pending_email));
+
+    let query = serde_urlencoded::to_string([("q", confirmed_email.as_str())]).unwrap();
+    let html = app
+        .get_admin_subscribers(&query)
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(html.contains(confirmed_email));
+    assert!(!html.contains(pending_email));
+
+    let html = app
+        .get_admin_subscribers("status=pending_confirmation&from=2000-01-01")
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(!html.contains(confirmed_email));
+    assert!(html.contains(pending_email));
+
+    let html = app
+        .get_admin_subscribers("to=2000-01-01")
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(html.contains("No subscribers match these filters."));
+
+    let response = app.get_admin_subscribers("status=nonsense").await;
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn the_detail_page_shows_tokens() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let links = create_unconfirmed_subscriber(&app).await;
+    let (id, _, email, _) = subscribers(&app).await.remove(0);
+    let token = links
+        .html
+        .query_pairs()
+        .find(|(k, _)| k == "subscription_token")
+        .unwrap()
+        .1
+        .to_string();
+
+    let html = app.get_admin_subscriber(id).await.text().await.unwrap();
+    assert!(html.contains(&email));
+    assert!(html.contains(&token));
+
+    let response = app.get_admin_subscriber(Uuid::new_v4()).await;
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn admins_can_confirm_and_unsubscribe_a_subscriber() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    create_unconfirmed_subscriber(&app).await;
+    let (id, ..) = subscribers(&app).await.remove(0);
+    let detail_path = format!("/admin/subscribers/{id}");
+
+    let response = app
+        .post_admin_subscriber_action(id, "confirm", &serde_json::json!({}))
+        .await;
+    assert_is_redirect_to(&response, &detail_path);
+    assert_eq!(subscribers(&app).await[0].3, "confirmed");
+
+    // Confirming twice isn't a valid transition, so the status is left alone.
+    app.post_admin_subscriber_action(id, "confirm", &serde_json::json!({}))
+        .await;
+    let html = app.get_admin_subscriber(id).await.text().await.unwrap();
+    assert!(html.contains("cannot go from `confirmed` to `confirmed`"));
+
+    app.post_admin_subscriber_action(id, "unsubscribe", &serde_json::json!({}))
+        .await;
+    assert_eq!(subscribers(&app).await[0].3, "unsubscribed");
+}
+
+#[tokio::test]
+async fn admins_can_edit_a_subscriber() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    let all = subscribers(&app).await;
+    let (id, ..) = all[0];
+    let (_, _, other_email, _) = &all[1];
+
+    app.post_admin_subscriber_action(
+        id,
+        "edit",
+        &serde_json::json!({ "name": "Ursula", "email": "ursula@example.com" }),
+    )
+    .await;
+    let (_, name, email, _) = subscribers(&app).await.remove(0);
+    assert_eq!(name, "Ursula");
+    assert_eq!(email, "ursula@example.com");
+
+    let response = app
+        .post_admin_subscriber_action(
+            id,
+            "edit",
+            &serde_json::json!({ "name": "Ursula", "email": other_email.to_uppercase() }),
+        )
+        .await;
+    assert_is_redirect_to(&response, &format!("/admin/subscribers/{id}"));
+    let html = app.get_admin_subscriber(id).await.text().await.unwrap();
+    assert!(html.contains("Another subscriber already uses the address"));
+    assert_eq!(subscribers(&app).await[0].2, "ursula@example.com");
+}
+
+#[tokio::test]
+async fn pending_emails_follow_an_edited_address() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    app.post_subscription("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+    let (id, ..) = subscribers(&app).await.remove(0);
+
+    app.post_admin_subscriber_action(
+        id,
+        "edit",
+        &serde_json::json!({ "name": "Ursula", "email": "ursula@example.com" }),
+    )
+    .await;
+
+    let recipients = sqlx::query_scalar!("SELECT recipient FROM email_outbox")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(recipients, vec!["ursula@example.com".to_string()]);
+}
+
+#[tokio::test]
+async fn admins_can_delete_a_subscriber() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    create_confirmed_subscriber(&app).await;
+    let (id, ..) = subscribers(&app).await.remove(0);
+
+    let response = app
+        .post_admin_subscriber_action(id, "delete", &serde_json::json!({}))
+        .await;
+
+    assert_is_redirect_to(&response, "/admin/subscribers");
+    assert!(subscribers(&app).await.is_empty());
+}
@@ -0,0 +1,175 @@
+use wiremock::{
+    Mock, ResponseTemplate,
+    matchers::{method, path},
+};
+
+use crate::helpers::spawn_app;
+
+#[tokio::test]
+async fn api_subscribe_returns_201_with_a_json_body_for_valid_data() {
+    let app = spawn_app().await;
+    let body = serde_json::json!({
+        "name": "le guin",
+        "email": "ursula_le_guin@gmail.com"
+    });
+
+    let response = app.post_api_subscription(&body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+    let json: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(json["status"], "pending_confirmation");
+
+    let saved = sqlx::query!("SELECT email, status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription");
+    assert_eq!(saved.email, "ursula_le_guin@gmail.com");
+    assert_eq!(saved.status, "pending_confirmation");
+}
+
+#[tokio::test]
+async fn api_subscribe_queues_a_confirmation_email() {
+    let app = spawn_app().await;
+    let body = serde_json::json!({
+        "name": "le guin",
+        "email": "ursula_le_guin@gmail.com"
+    });
+
+    Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_api_subscription(&body).await;
+    app.dispatch_all_pending_emails().await;
+
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_links = app.get_confirmation_links(email_request);
+    assert_eq!(confirmation_links.html, confirmation_links.text);
+}
+
+#[tokio::test]
+async fn api_subscribe_returns_a_validation_error_for_invalid_fields() {
+    let app = spawn_app().await;
+    let test_cases = vec![
+        (
+            serde_json::json!({"name": "", "email": "ursula_le_guin@gmail.com"}),
+            "empty name",
+        ),
+        (
+            serde_json::json!({"name": "Ursula", "email": "definitely-not-an-email"}),
+            "invalid email",
+        ),
+    ];
+
+    for (body, description) in test_cases {
+        let response = app.post_api_subscription(&body).await;
+
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "The API did not return a 400 Bad Request when the payload was {description}."
+        );
+        let json: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(json["error"]["code"], "validation_error");
+        assert!(json["error"]["message"].is_string());
+    }
+}
+
+#[tokio::test]
+async fn api_subscribe_rejects_disposable_email_domains() {
+    let app = spawn_app().await;
+    let body = serde_json::json!({
+        "name": "le guin",
+        "email": "ursula@yopmail.com"
+    });
+
+    let response = app.post_api_subscription(&body).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    let json: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(json["error"]["code"], "validation_error");
+    assert!(
+        json["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("disposable")
+    );
+}
+
+#[tokio::test]
+async fn api_subscribe_returns_a_json_error_for_malformed_payloads() {
+    let app = spawn_app().await;
+    let test_cases = vec![
+        (serde_json::json!({"name": "le guin"}), "missing the email"),
+        (
+            serde_json::json!({"email": "ursula_le_guin@gmail.com"}),
+            "missing the name",
+        ),
+        (serde_json::json!([]), "not an object"),
+    ];
+
+    for (body, description) in test_cases {
+        let response = app.post_api_subscription(&body).await;
+
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "The API did not return a 400 Bad Request when the payload was {description}."
+        );
+        let json: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(json["error"]["code"], "invalid_payload");
+    }
+}
+
+#[tokio::test]
+async fn api_subscribe_returns_409_for_a_confirmed_subscriber() {
+    let app = spawn_app().await;
+    let body = serde_json::json!({
+        "name": "le guin",
+        "email": "ursula_le_guin@gmail.com"
+    });
+
+    Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    app.post_api_subscription(&body).await;
+    app.dispatch_all_pending_emails().await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_links = app.get_confirmation_links(email_request);
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    let response = app.post_api_subscription(&body).await;
+
+    assert_eq!(response.status().as_u16(), 409);
+    let json: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(json["error"]["code"], "already_subscribed");
+}
+
+#[tokio::test]
+async fn api_subscribe_returns_429_with_retry_after_when_throttled() {
+    let app = spawn_app().await;
+    let body = serde_json::json!({
+        "name": "le guin",
+        "email": "ursula_le_guin@gmail.com"
+    });
+
+    for _ in 0..3 {
+        app.post_api_subscription(&body).await;
+    }
+    let response = app.post_api_subscription(&body).await;
+
+    assert_eq!(response.status().as_u16(), 429);
+    assert!(response.headers().contains_key("Retry-After"));
+    let json: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(json["error"]["code"], "rate_limited");
+}
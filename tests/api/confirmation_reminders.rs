@@ -0,0 +1,138 @@
+use wiremock::{
+    Mock, ResponseTemplate,
+    matchers::{method, path},
+};
+use z2p::{
+    configuration::ConfirmationReminderSettings,
+    confirmation_reminders::{purge_unconfirmed_subscribers, try_send_confirmation_reminder},
+    issue_delivery_worker::ExecutionOutcome,
+    startup::ApplicationBaseURL,
+};
+
+use crate::helpers::{
+    TestApp, create_confirmed_subscriber, create_unconfirmed_subscriber, spawn_app,
+};
+
+fn settings() -> ConfirmationReminderSettings {
+    ConfirmationReminderSettings {
+        remind_after_hours: 48,
+        purge_after_hours: 168,
+    }
+}
+
+async fn send_all_reminders(app: &TestApp) {
+    let base_url = ApplicationBaseURL(app.address.clone());
+    while let ExecutionOutcome::TaskCompleted =
+        try_send_confirmation_reminder(&app.db_pool, &base_url, &settings())
+            .await
+            .unwrap()
+    {}
+}
+
+async fn age_pending_subscribers(app: &TestApp, hours: i32) {
+    sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET
+            pending_since = pending_since - make_interval(hours => $1),
+            confirmation_reminder_sent_at =
+                confirmation_reminder_sent_at - make_interval(hours => $1)
+        WHERE status = 'pending_confirmation'
+        "#,
+        hours
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn recent_signups_are_not_reminded() {
+    let app = spawn_app().await;
+    create_unconfirmed_subscriber(&app).await;
+
+    Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    send_all_reminders(&app).await;
+    app.dispatch_all_pending_emails().await;
+}
+
+#[tokio::test]
+async fn stale_signups_get_one_reminder_with_a_fresh_link() {
+    let app = spawn_app().await;
+    let original_links = create_unconfirmed_subscriber(&app).await;
+    age_pending_subscribers(&app, 49).await;
+
+    Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    send_all_reminders(&app).await;
+    send_all_reminders(&app).await;
+    app.dispatch_all_pending_emails().await;
+
+    let email_request = app.email_server.received_requests().await.unwrap();
+    let reminder = email_request.last().unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&reminder.body).unwrap();
+    assert_eq!(
+        body["subject"],
+        "Reminder: please confirm your subscription"
+    );
+    let reminder_links = app.get_confirmation_links(reminder);
+    assert_ne!(reminder_links.html, original_links.html);
+
+    let resp = reqwest::get(original_links.html).await.unwrap();
+    assert_eq!(resp.status().as_u16(), 401);
+    let resp = reqwest::get(reminder_links.html).await.unwrap();
+    assert_eq!(resp.status().as_u16(), 200);
+
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "confirmed");
+}
+
+#[tokio::test]
+async fn signups_that_ignore_the_reminder_are_purged() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    create_unconfirmed_subscriber(&app).await;
+    age_pending_subscribers(&app, 49).await;
+
+    Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    send_all_reminders(&app).await;
+
+    assert_eq!(
+        purge_unconfirmed_subscribers(&app.db_pool, &settings())
+            .await
+            .unwrap(),
+        0
+    );
+
+    age_pending_subscribers(&app, 169).await;
+    assert_eq!(
+        purge_unconfirmed_subscribers(&app.db_pool, &settings())
+            .await
+            .unwrap(),
+        1
+    );
+
+    let statuses: Vec<String> = sqlx::query_scalar!("SELECT status FROM subscriptions")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(statuses, vec!["confirmed".to_string()]);
+}
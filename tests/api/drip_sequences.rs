@@ -0,0 +1,203 @@
+use wiremock::{
+    Mock, ResponseTemplate,
+    matchers::{method, path},
+};
+
+use crate::helpers::{
+    TestApp, assert_is_redirect_to, create_confirmed_subscriber, create_unconfirmed_subscriber,
+    spawn_app,
+};
+
+async fn add_step(app: &TestApp, delay_hours: i32, subject: &str) {
+    let response = app
+        .post_drip_step(&serde_json::json!({
+            "delay_hours": delay_hours.to_string(),
+            "subject": subject,
+            "html": "<p>Hi {{ subscriber.name }}</p>",
+            "text": "Hi {{ subscriber.name }}"
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/sequence");
+}
+
+async fn drip_statuses(app: &TestApp) -> Vec<String> {
+    sqlx::query!(
+        r#"
+        SELECT d.status
+        FROM subscriber_drip_emails d
+        JOIN drip_steps st USING (drip_step_id)
+        ORDER BY st.delay_hours
+        "#
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap()
+    .into_iter()
+    .map(|r| r.status)
+    .collect()
+}
+
+#[tokio::test]
+async fn anonymous_users_cannot_change_the_sequence() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_drip_step(&serde_json::json!({
+            "delay_hours": "0",
+            "subject": "Welcome",
+            "html": "",
+            "text": "Welcome"
+        }))
+        .await;
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn a_welcome_email_is_sent_right_after_confirmation() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    add_step(&app, 0, "Welcome aboard").await;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.dispatch_all_pending_emails().await;
+
+    let email_request = app.email_server.received_requests().await.unwrap();
+    let body: serde_json::Value =
+        serde_json::from_slice(&email_request.last().unwrap().body).unwrap();
+    assert_eq!(body["subject"], "Welcome aboard");
+    let name = sqlx::query!("SELECT name FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .name;
+    assert!(
+        body["text"]
+            .as_str()
+            .unwrap()
+            .starts_with(&format!("Hi {name}\n"))
+    );
+    assert_eq!(drip_statuses(&app).await, ["sent"]);
+}
+
+#[tokio::test]
+async fn drip_emails_carry_a_working_unsubscribe_link() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    add_step(&app, 0, "Welcome aboard").await;
+    add_step(&app, 72, "Day three").await;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.dispatch_all_pending_emails().await;
+
+    let email_request = app.email_server.received_requests().await.unwrap();
+    let body: serde_json::Value =
+        serde_json::from_slice(&email_request.last().unwrap().body).unwrap();
+    let text = body["text"].as_str().unwrap();
+    let link = text.split("Unsubscribe: ").nth(1).unwrap().trim();
+    let mut link = reqwest::Url::parse(link).unwrap();
+    link.set_port(Some(app.port)).unwrap();
+    assert!(body["html"].as_str().unwrap().contains(&format!(
+        "?subscription_token={}",
+        link.query().unwrap().split('=').nth(1).unwrap()
+    )));
+
+    app.follow_unsubscribe_link(link)
+        .await
+        .error_for_status()
+        .unwrap();
+    assert_eq!(drip_statuses(&app).await, ["sent", "cancelled"]);
+}
+
+#[tokio::test]
+async fn later_steps_wait_for_their_delay() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    add_step(&app, 72, "Day three").await;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.dispatch_all_pending_emails().await;
+    assert_eq!(drip_statuses(&app).await, ["scheduled"]);
+
+    sqlx::query!("UPDATE subscriber_drip_emails SET send_after = now() - interval '1 minute'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    app.dispatch_all_pending_emails().await;
+    assert_eq!(drip_statuses(&app).await, ["sent"]);
+}
+
+#[tokio::test]
+async fn unconfirmed_subscribers_are_not_enrolled() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    add_step(&app, 0, "Welcome aboard").await;
+
+    create_unconfirmed_subscriber(&app).await;
+
+    assert!(drip_statuses(&app).await.is_empty());
+}
+
+#[tokio::test]
+async fn unsubscribing_cancels_the_rest_of_the_sequence() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    add_step(&app, 24, "Day one").await;
+    add_step(&app, 72, "Day three").await;
+    let links = create_unconfirmed_subscriber(&app).await;
+    reqwest::get(links.html.clone())
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    let mut unsubscribe_link = links.html;
+    unsubscribe_link.set_path("/subscriptions/unsubscribe");
+    app.follow_unsubscribe_link(unsubscribe_link)
+        .await
+        .error_for_status()
+        .unwrap();
+
+    assert_eq!(drip_statuses(&app).await, ["cancelled", "cancelled"]);
+}
+
+#[tokio::test]
+async fn due_steps_are_cancelled_if_the_subscriber_left_another_way() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    add_step(&app, 0, "Welcome aboard").await;
+    create_confirmed_subscriber(&app).await;
+    sqlx::query!("UPDATE subscriptions SET status = 'bounced'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    app.dispatch_all_pending_emails().await;
+
+    assert_eq!(drip_statuses(&app).await, ["cancelled"]);
+}
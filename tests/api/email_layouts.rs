@@ -0,0 +1,207 @@
+use uuid::Uuid;
+use wiremock::{
+    Mock, ResponseTemplate,
+    matchers::{method, path},
+};
+
+use crate::helpers::{TestApp, assert_is_redirect_to, create_confirmed_subscriber, spawn_app};
+
+const LAYOUT: &str = r#"<html><head><style>.brand { color: #123456; }</style></head><body>
+<div class="brand">Acme news for {{ subscriber.name }}</div>
+{{ content | safe }}
+<p>Acme Ltd, 1 Main Street. <a href="{{ unsubscribe_url }}">Unsubscribe</a></p>
+</body></html>"#;
+
+async fn create_layout(app: &TestApp, name: &str) -> Uuid {
+    app.post_email_layouts("", &serde_json::json!({ "name": name, "template": LAYOUT }))
+        .await;
+    sqlx::query_scalar!(
+        "SELECT email_layout_id FROM email_layouts WHERE name = $1",
+        name
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn anonymous_users_cannot_manage_layouts() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_email_layouts(
+            "",
+            &serde_json::json!({ "name": "Acme", "template": LAYOUT }),
+        )
+        .await;
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn layouts_without_the_content_or_an_unsubscribe_link_are_rejected() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    let cases = [
+        (
+            r#"<a href="{{ unsubscribe_url }}">Unsubscribe</a>"#,
+            "The layout must place the issue with",
+        ),
+        ("{{ content | safe }}", "The layout must link to"),
+        ("{% if %}", "The layout is not a valid template"),
+    ];
+    for (template, error) in cases {
+        let response = app
+            .post_email_layouts(
+                "",
+                &serde_json::json!({ "name": "Broken", "template": template }),
+            )
+            .await;
+        assert_is_redirect_to(&response, "/admin/layouts");
+
+        let page = app.get_email_layouts("").await.text().await.unwrap();
+        assert!(page.contains(error), "{template}: {page}");
+    }
+    let stored = sqlx::query!("SELECT name FROM email_layouts WHERE name = 'Broken'")
+        .fetch_optional(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(stored.is_none());
+}
+
+#[tokio::test]
+async fn issues_are_sent_in_their_layout_with_inlined_css() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    create_confirmed_subscriber(&app).await;
+    let layout_id = create_layout(&app, "Acme").await;
+
+    Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_newsletters(&serde_json::json!({
+        "title": "Issue title",
+        "html": "<p>The news</p>",
+        "text": "The news",
+        "email_layout": layout_id.to_string(),
+        "idempotency_key": Uuid::new_v4().to_string()
+    }))
+    .await;
+    app.dispatch_all_pending_emails().await;
+
+    let requests = app.email_server.received_requests().await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&requests[1].body).unwrap();
+    let html = body["html"].as_str().unwrap();
+    assert!(
+        html.contains(r#"<div class="brand" style="color: #123456">Acme news for "#),
+        "{html}"
+    );
+    assert!(html.contains("<p>The news</p>"));
+    assert!(html.contains("Acme Ltd, 1 Main Street."));
+    assert!(!html.contains("<style>"));
+
+    // The text version carries the subscriber's own unsubscribe link, and it works.
+    let text = body["text"].as_str().unwrap();
+    assert!(text.starts_with("The news"));
+    let link = text.split("Unsubscribe: ").nth(1).unwrap().trim();
+    let mut link = reqwest::Url::parse(link).unwrap();
+    link.set_port(Some(app.port)).unwrap();
+    assert!(html.contains(&format!(
+        "?subscription_token={}",
+        link.query().unwrap().split('=').nth(1).unwrap()
+    )));
+    app.follow_unsubscribe_link(link)
+        .await
+        .error_for_status()
+        .unwrap();
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "unsubscribed");
+}
+
+#[tokio::test]
+async fn drafts_keep_their_layout_when_published() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let layout_id = create_layout(&app, "Acme").await;
+
+    app.post_newsletter_drafts(
+        "",
+        &serde_json::json!({
+            "title": "Draft title",
+            "markdown": "Some *news*",
+            "email_layout": layout_id.to_string(),
+        }),
+    )
+    .await;
+    let draft_id = sqlx::query_scalar!("SELECT draft_id FROM newsletter_drafts")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+
+    // The preview shows the layout, and Markdown doesn't get the built-in one as well.
+    let preview = app
+        .get_newsletter_drafts(&format!("/{draft_id}/preview"))
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(preview.contains("Acme news for"));
+    assert_eq!(preview.matches("<body").count(), 1);
+
+    app.post_newsletter_drafts(
+        &format!("/{draft_id}/publish"),
+        &serde_json::json!({ "idempotency_key": Uuid::new_v4().to_string() }),
+    )
+    .await;
+    let issue_layout = sqlx::query_scalar!("SELECT email_layout_id FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(issue_layout, Some(layout_id));
+}
+
+#[tokio::test]
+async fn layouts_used_by_an_issue_cannot_be_removed() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let used = create_layout(&app, "Used").await;
+    let unused = create_layout(&app, "Unused").await;
+    app.post_newsletters(&serde_json::json!({
+        "title": "Issue title",
+        "html": "<p>The news</p>",
+        "text": "The news",
+        "email_layout": used.to_string(),
+        "idempotency_key": Uuid::new_v4().to_string()
+    }))
+    .await;
+
+    let response = app
+        .post_email_layouts(&format!("/{used}/delete"), &serde_json::json!({}))
+        .await;
+    assert_is_redirect_to(&response, &format!("/admin/layouts/{used}"));
+    let page = app
+        .get_email_layouts(&format!("/{used}"))
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(page.contains("The layout is used by published issues and can&#x27;t be removed."));
+
+    let response = app
+        .post_email_layouts(&format!("/{unused}/delete"), &serde_json::json!({}))
+        .await;
+    assert_is_redirect_to(&response, "/admin/layouts");
+    let names = sqlx::query_scalar!("SELECT name FROM email_layouts ORDER BY name")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(names, vec!["Default".to_string(), "Used".to_string()]);
+}
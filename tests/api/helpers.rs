@@ -14,10 +14,13 @@ use wiremock::{
     matchers::{method, path},
 };
 use z2p::{
-    configuration::{DatabaseSettings, get_configuration},
+    configuration::{DatabaseSettings, Settings, get_configuration},
+    drip_sequences::try_enqueue_due_drip_email,
     email_client::EmailClient,
+    email_outbox::try_send_outbox_email,
     issue_delivery_worker::{ExecutionOutcome, try_execute_task},
-    startup::{Application, get_connection_pull},
+    startup::{Application, ApplicationBaseURL, get_connection_pull},
+    subscriber_imports::try_import_subscriber_batch,
     telemetry::{get_subscriber, init_subscriber},
 };
 
@@ -54,13 +57,34 @@ impl TestApp {
     pub async fn dispatch_all_pending_emails(&self) {
         loop {
             if let ExecutionOutcome::EmptyQueue =
-                try_execute_task(&self.db_pool, &self.email_client)
+                try_enqueue_due_drip_email(&self.db_pool, &ApplicationBaseURL(self.address.clone()))
                     .await
                     .unwrap()
             {
                 break;
             }
         }
+        loop {
+            if let ExecutionOutcome::EmptyQueue =
+                try_send_outbox_email(&self.db_pool, &self.email_client)
+                    .await
+                    .unwrap()
+            {
+                break;
+            }
+        }
+        loop {
+            if let ExecutionOutcome::EmptyQueue = try_execute_task(
+                &self.db_pool,
+                &self.email_client,
+                &ApplicationBaseURL(self.address.clone()),
+            )
+            .await
+            .unwrap()
+            {
+                break;
+            }
+        }
     }
 
     pub async fn get_send_newsletters_html(&self) -> String {
@@ -130,7 +154,7 @@ impl TestApp {
 
     pub async fn get_login_html(&self) -> String {
         self.api_client
-            .get(&format!("{}/login", &self.address))
+            .get(format!("{}/login", &self.address))
             .send()
             .await
             .expect("Failde to send request.")
@@ -144,7 +168,7 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.api_client
-            .post(&format!("{}/login", self.address))
+            .post(format!("{}/login", self.address))
             .form(&body)
             .send()
             .await
@@ -173,6 +197,328 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn post_unsubscribe(&self, subscription_token: &str) -> Response {
+        self.api_client
+            .post(format!("{}/subscriptions/unsubscribe", self.address))
+            .form(&[("subscription_token", subscription_token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Opens an emailed unsubscribe link and submits the page it shows.
+    pub async fn follow_unsubscribe_link(&self, link: reqwest::Url) -> Response {
+        let page = reqwest::get(link.clone()).await.unwrap();
+        assert_eq!(page.status().as_u16(), 200);
+        let (_, token) = link
+            .query_pairs()
+            .find(|(key, _)| key == "subscription_token")
+            .unwrap();
+        assert!(page.text().await.unwrap().contains(token.as_ref()));
+        self.post_unsubscribe(&token).await
+    }
+
+    pub async fn get_home_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_data_request<Body>(&self, body: &Body) -> Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/subscriptions/data-requests", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_admin_erase_subscriber<Body>(&self, body: &Body) -> Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/subscribers/erase", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_subscribers(&self, query: &str) -> Response {
+        self.api_client
+            .get(format!("{}/admin/subscribers?{}", &self.address, query))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_newsletter_issues(&self, path_and_query: &str) -> Response {
+        self.api_client
+            .get(format!(
+                "{}/admin/newsletters/issues{}",
+                &self.address, path_and_query
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_newsletter_issue_draft(&self, issue_id: Uuid) -> Response {
+        self.api_client
+            .post(format!(
+                "{}/admin/newsletters/issues/{}/draft",
+                &self.address, issue_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_newsletter_issue_visibility(
+        &self,
+        issue_id: Uuid,
+        visibility: &str,
+    ) -> Response {
+        self.api_client
+            .post(format!(
+                "{}/admin/newsletters/issues/{}/visibility",
+                &self.address, issue_id
+            ))
+            .form(&serde_json::json!({ "visibility": visibility }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_public(&self, path: &str) -> Response {
+        self.api_client
+            .get(format!("{}{}", &self.address, path))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_newsletter_drafts(&self, path: &str) -> Response {
+        self.api_client
+            .get(format!(
+                "{}/admin/newsletters/drafts{}",
+                &self.address, path
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_newsletter_drafts<Body>(&self, path: &str, body: &Body) -> Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!(
+                "{}/admin/newsletters/drafts{}",
+                &self.address, path
+            ))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_email_layouts(&self, path: &str) -> Response {
+        self.api_client
+            .get(format!("{}/admin/layouts{}", &self.address, path))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_email_layouts<Body>(&self, path: &str, body: &Body) -> Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/layouts{}", &self.address, path))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_email_templates(&self, path: &str) -> Response {
+        self.api_client
+            .get(format!("{}/admin/emails{}", &self.address, path))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_email_templates<Body>(&self, path: &str, body: &Body) -> Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/emails{}", &self.address, path))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_subscriber(&self, subscriber_id: Uuid) -> Response {
+        self.api_client
+            .get(format!(
+                "{}/admin/subscribers/{}",
+                &self.address, subscriber_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_admin_subscriber_action<Body>(
+        &self,
+        subscriber_id: Uuid,
+        action: &str,
+        body: &Body,
+    ) -> Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!(
+                "{}/admin/subscribers/{}/{}",
+                &self.address, subscriber_id, action
+            ))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_subscriber_import(&self, csv: &str, mode: &str, consent: bool) -> Response {
+        self.post_platform_import("csv", "list.csv", csv.as_bytes(), mode, consent)
+            .await
+    }
+
+    pub async fn post_platform_import(
+        &self,
+        source: &str,
+        file_name: &str,
+        data: &[u8],
+        mode: &str,
+        consent: bool,
+    ) -> Response {
+        let boundary = "subscriber-import-boundary";
+        let mut body = format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{file_name}\"\r\nContent-Type: application/octet-stream\r\n\r\n"
+        )
+        .into_bytes();
+        body.extend_from_slice(data);
+        body.extend_from_slice(
+            format!(
+                "\r\n--{boundary}\r\nContent-Disposition: form-data; name=\"source\"\r\n\r\n{source}\r\n--{boundary}\r\nContent-Disposition: form-data; name=\"mode\"\r\n\r\n{mode}\r\n"
+            )
+            .as_bytes(),
+        );
+        if consent {
+            body.extend_from_slice(
+                format!(
+                    "--{boundary}\r\nContent-Disposition: form-data; name=\"consent\"\r\n\r\nyes\r\n"
+                )
+                .as_bytes(),
+            );
+        }
+        body.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
+
+        self.api_client
+            .post(format!("{}/admin/subscribers/import", &self.address))
+            .header(
+                "Content-Type",
+                format!("multipart/form-data; boundary={boundary}"),
+            )
+            .body(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn process_subscriber_imports(&self) {
+        let base_url = ApplicationBaseURL(self.address.clone());
+        loop {
+            if let ExecutionOutcome::EmptyQueue =
+                try_import_subscriber_batch(&self.db_pool, &base_url)
+                    .await
+                    .unwrap()
+            {
+                break;
+            }
+        }
+    }
+
+    pub async fn get_admin_export(&self, path_and_query: &str) -> Response {
+        self.api_client
+            .get(format!(
+                "{}/admin/exports/{}",
+                &self.address, path_and_query
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_subscriber_attribute<Body>(&self, body: &Body) -> Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/subscribers/attributes", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_subscriber_attributes_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/admin/subscribers/attributes", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_drip_step<Body>(&self, body: &Body) -> Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/sequence", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_api_subscription(&self, body: &serde_json::Value) -> Response {
+        self.api_client
+            .post(format!("{}/api/v1/subscriptions", self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub fn get_confirmation_links(&self, email_request: &wiremock::Request) -> ConfirmationLinks {
         let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
 
@@ -222,6 +568,10 @@ pub async fn configure_database(config: &DatabaseSettings) -> PgPool {
 }
 
 pub async fn spawn_app() -> TestApp {
+    spawn_app_with(|config| config.bot_protection.require_form_token = false).await
+}
+
+pub async fn spawn_app_with(customise: impl FnOnce(&mut Settings)) -> TestApp {
     Lazy::force(&TRACING);
 
     let email_server = MockServer::start().await;
@@ -231,6 +581,8 @@ pub async fn spawn_app() -> TestApp {
         config.database.database_name = Uuid::new_v4().to_string();
         config.app.port = 0;
         config.email_client.base_url = email_server.uri();
+        config.rate_limits.key_prefix = Uuid::new_v4().to_string();
+        customise(&mut config);
         config
     };
 
@@ -266,7 +618,7 @@ pub async fn spawn_app() -> TestApp {
 pub async fn create_unconfirmed_subscriber(app: &TestApp) -> ConfirmationLinks {
     let name: String = Name().fake();
     let email: String = SafeEmail().fake();
-    let body = serde_urlencoded::to_string(&serde_json::json!({
+    let body = serde_urlencoded::to_string(serde_json::json!({
         "name": name,
         "email": email
     }))
@@ -280,10 +632,11 @@ pub async fn create_unconfirmed_subscriber(app: &TestApp) -> ConfirmationLinks {
         .mount_as_scoped(&app.email_server)
         .await;
 
-    app.post_subscription(body.into())
+    app.post_subscription(body)
         .await
         .error_for_status()
         .unwrap();
+    app.dispatch_all_pending_emails().await;
 
     let req = &app.email_server.received_requests().await.unwrap()[0];
 
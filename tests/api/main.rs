@@ -1,8 +1,25 @@
 mod admin_dashboard;
+mod admin_exports;
 mod admin_newsletter;
+mod admin_newsletter_drafts;
+mod admin_newsletter_issues;
+mod admin_subscribers;
+mod api_subscriptions;
 mod change_password;
+mod confirmation_reminders;
+mod drip_sequences;
+mod email_layouts;
 mod health_check;
 mod helpers;
 mod login;
+mod newsletter_test_sends;
+mod password_reset;
+mod public_issues;
+mod signup_attribution;
+mod subscriber_attributes;
+mod subscriber_data;
+mod subscriber_imports;
 mod subscriptions;
 mod subscriptions_confirm;
+mod subscriptions_unsubscribe;
+mod transactional_emails;
@@ -0,0 +1,210 @@
+use uuid::Uuid;
+use wiremock::{
+    Mock, ResponseTemplate,
+    matchers::{any, method, path},
+};
+
+use crate::helpers::{TestApp, assert_is_redirect_to, create_confirmed_subscriber, spawn_app};
+
+async fn create_draft(app: &TestApp) -> Uuid {
+    app.post_newsletter_drafts(
+        "",
+        &serde_json::json!({
+            "title": "Draft title",
+            "html": "<p>Hi {{ subscriber.name }}</p>",
+            "text": "Hi {{ subscriber.name }}",
+        }),
+    )
+    .await;
+    sqlx::query_scalar!("SELECT draft_id FROM newsletter_drafts")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+}
+
+async fn test_sends(app: &TestApp) -> Vec<(String, String, String)> {
+    sqlx::query!("SELECT recipient, subject, outcome FROM test_sends ORDER BY recipient")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|r| (r.recipient, r.subject, r.outcome))
+        .collect()
+}
+
+#[tokio::test]
+async fn anonymous_users_cannot_send_tests() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_newsletter_drafts(
+            &format!("/{}/test", Uuid::new_v4()),
+            &serde_json::json!({ "recipients": "reviewer@example.com" }),
+        )
+        .await;
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn a_draft_is_sent_only_to_the_reviewers() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    create_confirmed_subscriber(&app).await;
+    let draft_id = create_draft(&app).await;
+
+    Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_newsletter_drafts(
+            &format!("/{draft_id}/test"),
+            &serde_json::json!({ "recipients": "editor@example.com,\nreviewer@example.com" }),
+        )
+        .await;
+    assert_is_redirect_to(&response, &format!("/admin/newsletters/drafts/{draft_id}"));
+    // Nothing reaches the subscriber, not even later on.
+    app.dispatch_all_pending_emails().await;
+
+    // The subscriber's own confirmation email comes first.
+    let requests = app.email_server.received_requests().await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&requests[1].body).unwrap();
+    assert_eq!(body["subject"], "[Test] Draft title");
+    assert_eq!(body["html"], "<p>Hi </p>");
+
+    let page = app
+        .get_newsletter_drafts(&format!("/{draft_id}"))
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(
+        page.contains("The test email has been sent to editor@example.com, reviewer@example.com.")
+    );
+    assert!(page.contains("<td>reviewer@example.com</td>"));
+    assert_eq!(
+        test_sends(&app).await,
+        vec![
+            (
+                "editor@example.com".into(),
+                "[Test] Draft title".into(),
+                "sent".into()
+            ),
+            (
+                "reviewer@example.com".into(),
+                "[Test] Draft title".into(),
+                "sent".into()
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn testing_a_published_issue_leaves_its_delivery_alone() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    create_confirmed_subscriber(&app).await;
+    app.post_newsletters(&serde_json::json!({
+        "title": "Issue title",
+        "html": "<p>Issue</p>",
+        "text": "Issue",
+        "idempotency_key": Uuid::new_v4().to_string()
+    }))
+    .await;
+    let issue_id = sqlx::query_scalar!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+
+    Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .api_client
+        .post(format!(
+            "{}/admin/newsletters/issues/{issue_id}/test",
+            &app.address
+        ))
+        .form(&serde_json::json!({ "recipients": "editor@example.com" }))
+        .send()
+        .await
+        .unwrap();
+    assert_is_redirect_to(&response, &format!("/admin/newsletters/issues/{issue_id}"));
+
+    let queued = sqlx::query_scalar!(r#"SELECT count(*) AS "count!" FROM issue_delivery_queue"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(queued, 1);
+    let page = app
+        .get_admin_newsletter_issues(&format!("/{issue_id}"))
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(page.contains("<td>editor@example.com</td>"));
+}
+
+#[tokio::test]
+async fn invalid_or_too_many_recipients_are_rejected() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let draft_id = create_draft(&app).await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    for recipients in [
+        "not-an-email",
+        "",
+        "a@example.com b@example.com c@example.com d@example.com e@example.com f@example.com",
+    ] {
+        let response = app
+            .post_newsletter_drafts(
+                &format!("/{draft_id}/test"),
+                &serde_json::json!({ "recipients": recipients }),
+            )
+            .await;
+        assert_is_redirect_to(&response, &format!("/admin/newsletters/drafts/{draft_id}"));
+    }
+    assert!(test_sends(&app).await.is_empty());
+}
+
+#[tokio::test]
+async fn failed_test_sends_are_logged() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let draft_id = create_draft(&app).await;
+
+    Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&app.email_server)
+        .await;
+
+    app.post_newsletter_drafts(
+        &format!("/{draft_id}/test"),
+        &serde_json::json!({ "recipients": "editor@example.com" }),
+    )
+    .await;
+
+    let page = app
+        .get_newsletter_drafts(&format!("/{draft_id}"))
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(page.contains("could not be sent to editor@example.com"));
+    assert_eq!(test_sends(&app).await[0].2, "failed");
+}
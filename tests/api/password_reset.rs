@@ -0,0 +1,157 @@
+use reqwest::Url;
+use uuid::Uuid;
+use wiremock::{
+    Mock, ResponseTemplate,
+    matchers::{method, path},
+};
+
+use crate::helpers::{TestApp, assert_is_redirect_to, spawn_app};
+
+async fn post_recovery_email(app: &TestApp, email: &str, password: &str) -> reqwest::Response {
+    app.api_client
+        .post(format!("{}/admin/password/email", app.address))
+        .form(&serde_json::json!({ "email": email, "current_password": password }))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn post_password_reset_request(app: &TestApp) -> reqwest::Response {
+    app.api_client
+        .post(format!("{}/login/password-reset", app.address))
+        .form(&serde_json::json!({ "username": &app.test_user.username }))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn post_new_password(app: &TestApp, link: &Url, new_password: &str) -> reqwest::Response {
+    let token = link
+        .query_pairs()
+        .find(|(key, _)| key == "token")
+        .unwrap()
+        .1
+        .into_owned();
+    app.api_client
+        .post(format!("{}/login/password-reset/confirm", app.address))
+        .form(&serde_json::json!({
+            "token": token,
+            "new_password": new_password,
+            "new_password_check": new_password,
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+#[tokio::test]
+async fn a_reset_link_lets_an_admin_choose_a_new_password_once() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let response = post_recovery_email(&app, "admin@example.com", &app.test_user.password).await;
+    assert_is_redirect_to(&response, "/admin/password");
+    assert!(
+        app.get_change_password_html()
+            .await
+            .contains("value=\"admin@example.com\"")
+    );
+    app.post_logout().await;
+
+    Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let response = post_password_reset_request(&app).await;
+    assert_is_redirect_to(&response, "/login/password-reset");
+    app.dispatch_all_pending_emails().await;
+    let requests = app.email_server.received_requests().await.unwrap();
+    let link = app.get_confirmation_links(&requests[0]).html;
+    assert_eq!(link.path(), "/login/password-reset/confirm");
+
+    let response = reqwest::get(link.clone()).await.unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+
+    let new_password = Uuid::new_v4().to_string();
+    let response = post_new_password(&app, &link, &new_password).await;
+    assert_is_redirect_to(&response, "/login");
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &new_password
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+
+    let response = post_new_password(&app, &link, &Uuid::new_v4().to_string()).await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn a_password_reset_logs_out_existing_sessions() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    post_recovery_email(&app, "admin@example.com", &app.test_user.password).await;
+
+    Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    post_password_reset_request(&app).await;
+    app.dispatch_all_pending_emails().await;
+    let requests = app.email_server.received_requests().await.unwrap();
+    let link = app.get_confirmation_links(&requests[0]).html;
+    let response = post_new_password(&app, &link, &Uuid::new_v4().to_string()).await;
+    assert_is_redirect_to(&response, "/login");
+
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn the_recovery_email_can_only_be_changed_with_the_current_password() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    let response = post_recovery_email(&app, "attacker@example.com", "wrong-password").await;
+
+    assert_is_redirect_to(&response, "/admin/password");
+    let page = app.get_change_password_html().await;
+    assert!(page.contains("The current password is incorrect."));
+    assert!(!page.contains("attacker@example.com"));
+}
+
+#[tokio::test]
+async fn accounts_without_a_recovery_email_get_no_reset_link() {
+    let app = spawn_app().await;
+
+    Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = post_password_reset_request(&app).await;
+    app.dispatch_all_pending_emails().await;
+
+    assert_is_redirect_to(&response, "/login/password-reset");
+}
+
+#[tokio::test]
+async fn an_unknown_reset_token_is_rejected_with_401() {
+    let app = spawn_app().await;
+
+    let response = reqwest::get(format!(
+        "{}/login/password-reset/confirm?token={}",
+        app.address,
+        "a".repeat(25)
+    ))
+    .await
+    .unwrap();
+
+    assert_eq!(response.status().as_u16(), 401);
+}
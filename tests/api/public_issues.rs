@@ -0,0 +1,193 @@
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use uuid::Uuid;
+
+use crate::helpers::{TestApp, assert_is_redirect_to, spawn_app};
+
+async fn publish_issue(app: &TestApp, title: &str, html: &str) -> (Uuid, String) {
+    let body = serde_json::json!({
+        "title": title,
+        "html": html,
+        "text": "Plain text",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    let response = app.post_newsletters(&body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let issue = sqlx::query!(
+        "SELECT newsletter_issue_id, slug FROM newsletter_issues WHERE title = $1",
+        title
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    (issue.newsletter_issue_id, issue.slug)
+}
+
+#[tokio::test]
+async fn published_issues_are_readable_on_the_web() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let (_, slug) = publish_issue(
+        &app,
+        "Hello, World!",
+        "<p>Hi {{ subscriber.name }}reader</p>",
+    )
+    .await;
+    app.post_logout().await;
+    assert!(slug.starts_with("hello-world-"));
+
+    let archive = app.get_public("/issues").await.text().await.unwrap();
+    assert!(archive.contains(&format!("/issues/{slug}")));
+    assert!(archive.contains("Hello, World!"));
+
+    let response = app.get_public(&format!("/issues/{slug}")).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.text().await.unwrap().contains("<p>Hi reader</p>"));
+}
+
+#[tokio::test]
+async fn unknown_slugs_are_not_found() {
+    let app = spawn_app().await;
+
+    let response = app.get_public("/issues/not-an-issue").await;
+
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn the_feed_lists_public_issues() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let (_, slug) = publish_issue(&app, "Feed issue", "<p>Fish & chips</p>").await;
+
+    let response = app.get_public("/feed.xml").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers()["Content-Type"],
+        "application/atom+xml; charset=utf-8"
+    );
+    let feed = response.text().await.unwrap();
+    assert!(feed.contains("<title>Feed issue</title>"));
+    assert!(feed.contains(&slug));
+    // The sanitised HTML is escaped inside the XML document.
+    assert!(feed.contains("&lt;p&gt;Fish &amp;amp; chips&lt;"));
+}
+
+#[tokio::test]
+async fn private_issues_are_hidden_from_the_public() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let (issue_id, slug) = publish_issue(&app, "Members only", "<p>Secret</p>").await;
+
+    let response = app
+        .post_newsletter_issue_visibility(issue_id, "private")
+        .await;
+    assert_is_redirect_to(&response, &format!("/admin/newsletters/issues/{issue_id}"));
+
+    assert_eq!(
+        app.get_public(&format!("/issues/{slug}"))
+            .await
+            .status()
+            .as_u16(),
+        404
+    );
+    let archive = app.get_public("/issues").await.text().await.unwrap();
+    assert!(!archive.contains("Members only"));
+    let feed = app.get_public("/feed.xml").await.text().await.unwrap();
+    assert!(!feed.contains("Members only"));
+
+    app.post_newsletter_issue_visibility(issue_id, "public")
+        .await;
+    assert_eq!(
+        app.get_public(&format!("/issues/{slug}"))
+            .await
+            .status()
+            .as_u16(),
+        200
+    );
+}
+
+#[tokio::test]
+async fn only_admins_can_change_the_visibility_of_an_issue() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_newsletter_issue_visibility(Uuid::new_v4(), "private")
+        .await;
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn unchanged_pages_are_answered_with_not_modified() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let (issue_id, slug) = publish_issue(&app, "Cached issue", "<p>Cached</p>").await;
+
+    for path in ["/issues", &format!("/issues/{slug}"), "/feed.xml"] {
+        let response = app.get_public(path).await;
+        let etag = response.headers()[ETAG].clone();
+        let last_modified = response.headers()[LAST_MODIFIED].clone();
+
+        let by_etag = app
+            .api_client
+            .get(format!("{}{}", &app.address, path))
+            .header(IF_NONE_MATCH, etag)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(by_etag.status().as_u16(), 304, "{path}");
+        assert!(by_etag.text().await.unwrap().is_empty());
+
+        let by_date = app
+            .api_client
+            .get(format!("{}{}", &app.address, path))
+            .header(IF_MODIFIED_SINCE, last_modified)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(by_date.status().as_u16(), 304, "{path}");
+    }
+
+    // Hiding an issue changes the archive, so the old validator no longer matches.
+    let etag = app.get_public("/issues").await.headers()[ETAG].clone();
+    app.post_newsletter_issue_visibility(issue_id, "private")
+        .await;
+    let response = app
+        .api_client
+        .get(format!("{}/issues", &app.address))
+        .header(IF_NONE_MATCH, etag)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn unsanitised_issues_are_cleaned_before_being_served() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let (issue_id, slug) = publish_issue(&app, "Old issue", "<p>Old</p>").await;
+    // Stored before sanitising was in place.
+    sqlx::query!(
+        "UPDATE newsletter_issues SET html_content = $1 WHERE newsletter_issue_id = $2",
+        "<p>Old</p><script>steal()</script>",
+        issue_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    app.post_logout().await;
+
+    let page = app
+        .get_public(&format!("/issues/{slug}"))
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(page.contains("<p>Old</p>"));
+    assert!(!page.contains("steal"));
+
+    let feed = app.get_public("/feed.xml").await.text().await.unwrap();
+    assert!(!feed.contains("steal"));
+}
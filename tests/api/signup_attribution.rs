@@ -0,0 +1,142 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+
+#[tokio::test]
+async fn form_subscriptions_record_source_utm_parameters_and_referrer() {
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com&source=footer\
+        &utm_source=twitter&utm_medium=social&utm_campaign=launch";
+
+    let response = app
+        .api_client
+        .post(format!("{}/subscriptions", app.address))
+        .header("Content-type", "application/x-www-form-urlencoded")
+        .header("Referer", "https://blog.example/post")
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 200);
+    let saved = sqlx::query!(
+        "SELECT signup_source, referrer, utm_source, utm_medium, utm_campaign, utm_term \
+        FROM subscriptions"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(saved.signup_source.as_deref(), Some("footer"));
+    assert_eq!(saved.referrer.as_deref(), Some("https://blog.example/post"));
+    assert_eq!(saved.utm_source.as_deref(), Some("twitter"));
+    assert_eq!(saved.utm_medium.as_deref(), Some("social"));
+    assert_eq!(saved.utm_campaign.as_deref(), Some("launch"));
+    assert_eq!(saved.utm_term, None);
+}
+
+#[tokio::test]
+async fn json_subscriptions_record_attribution() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_api_subscription(&serde_json::json!({
+            "name": "le guin",
+            "email": "ursula_le_guin@gmail.com",
+            "source": "mobile-app",
+            "utm_campaign": "spring"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+    let saved = sqlx::query!("SELECT signup_source, utm_campaign FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.signup_source.as_deref(), Some("mobile-app"));
+    assert_eq!(saved.utm_campaign.as_deref(), Some("spring"));
+}
+
+#[tokio::test]
+async fn home_page_carries_utm_parameters_into_the_form() {
+    let app = spawn_app().await;
+
+    let html = app
+        .api_client
+        .get(format!(
+            "{}/?utm_source=newsletter&utm_campaign=winter",
+            app.address
+        ))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+
+    assert!(html.contains(r#"name="utm_source" value="newsletter""#));
+    assert!(html.contains(r#"name="utm_campaign" value="winter""#));
+    assert!(!html.contains(r#"name="utm_medium""#));
+}
+
+#[tokio::test]
+async fn signup_report_requires_login() {
+    let app = spawn_app().await;
+
+    let response = app
+        .api_client
+        .get(format!("{}/admin/reports/signups", app.address))
+        .send()
+        .await
+        .unwrap();
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn signup_report_breaks_down_signups_and_confirmations_by_source() {
+    let app = spawn_app().await;
+    for (i, source) in ["footer", "footer", "twitter"].iter().enumerate() {
+        let body = format!("name=reader&email=reader_{i}%40example.com&utm_source={source}");
+        app.post_subscription(body)
+            .await
+            .error_for_status()
+            .unwrap();
+    }
+    sqlx::query!(
+        "UPDATE subscriptions SET confirmed_at = now() WHERE email = 'reader_0@example.com'"
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    app.login_test_user().await;
+
+    let html = app
+        .api_client
+        .get(format!("{}/admin/reports/signups?period=day", app.address))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+
+    let compact: String = html.split_whitespace().collect();
+    assert!(compact.contains("<td>footer</td><td></td><td>2</td><td>1</td><td>50%</td>"));
+    assert!(compact.contains("<td>twitter</td><td></td><td>1</td><td>0</td><td>0%</td>"));
+}
+
+#[tokio::test]
+async fn confirming_a_subscription_records_when_it_happened() {
+    let app = spawn_app().await;
+    let links = crate::helpers::create_unconfirmed_subscriber(&app).await;
+
+    reqwest::get(links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    let saved = sqlx::query!("SELECT confirmed_at FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(saved.confirmed_at.is_some());
+}
@@ -0,0 +1,227 @@
+use wiremock::{
+    Mock, ResponseTemplate,
+    matchers::{method, path},
+};
+
+use crate::helpers::{TestApp, assert_is_redirect_to, spawn_app};
+
+async fn add_attributes(app: &TestApp) {
+    app.login_test_user().await;
+    for attribute in [
+        serde_json::json!({
+            "key": "company",
+            "label": "Company",
+            "kind": "text",
+            "required": "on",
+            "max_length": "20"
+        }),
+        serde_json::json!({
+            "key": "country",
+            "label": "Country",
+            "kind": "choice",
+            "choices": "NZ, UK"
+        }),
+        serde_json::json!({ "key": "seats", "label": "Seats", "kind": "number" }),
+    ] {
+        let response = app.post_subscriber_attribute(&attribute).await;
+        assert_is_redirect_to(&response, "/admin/subscribers/attributes");
+    }
+}
+
+async fn stored_attributes(app: &TestApp) -> serde_json::Value {
+    sqlx::query!("SELECT attributes FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .attributes
+}
+
+#[tokio::test]
+async fn anonymous_users_cannot_define_attributes() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_subscriber_attribute(&serde_json::json!({
+            "key": "company",
+            "label": "Company",
+            "kind": "text"
+        }))
+        .await;
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn admins_can_define_attributes() {
+    let app = spawn_app().await;
+    add_attributes(&app).await;
+
+    let html = app.get_subscriber_attributes_html().await;
+    assert!(html.contains("The attribute seats has been added."));
+    assert!(html.contains("<td>company</td>"));
+    assert!(html.contains("NZ, UK"));
+}
+
+#[tokio::test]
+async fn invalid_attribute_definitions_are_rejected() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    app.post_subscriber_attribute(&serde_json::json!({
+        "key": "email",
+        "label": "Email",
+        "kind": "text"
+    }))
+    .await;
+
+    let html = app.get_subscriber_attributes_html().await;
+    assert!(html.contains("email is reserved"));
+}
+
+#[tokio::test]
+async fn home_page_renders_custom_fields() {
+    let app = spawn_app().await;
+    add_attributes(&app).await;
+
+    let html = app.get_home_html().await;
+
+    assert!(html.contains(r#"name="company""#));
+    assert!(html.contains(r#"<option value="UK">UK</option>"#));
+}
+
+#[tokio::test]
+async fn form_subscriptions_store_custom_attributes() {
+    let app = spawn_app().await;
+    add_attributes(&app).await;
+
+    let response = app
+        .post_subscription(
+            "name=le%20guin&email=ursula_le_guin%40gmail.com&company=Acme&country=NZ&seats=3"
+                .into(),
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        stored_attributes(&app).await,
+        serde_json::json!({ "company": "Acme", "country": "NZ", "seats": 3 })
+    );
+}
+
+#[tokio::test]
+async fn subscriptions_with_invalid_attributes_are_rejected() {
+    let app = spawn_app().await;
+    add_attributes(&app).await;
+
+    for (body, description) in [
+        (
+            "name=le%20guin&email=ursula_le_guin%40gmail.com",
+            "missing required attribute",
+        ),
+        (
+            "name=le%20guin&email=ursula_le_guin%40gmail.com&company=Acme&country=FR",
+            "value outside the choices",
+        ),
+    ] {
+        let response = app.post_subscription(body.into()).await;
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "The API did not reject a subscription with a {description}."
+        );
+    }
+}
+
+#[tokio::test]
+async fn form_fields_that_are_not_attributes_are_ignored() {
+    let app = spawn_app().await;
+    add_attributes(&app).await;
+
+    let response = app
+        .post_subscription(
+            "name=le%20guin&email=ursula_le_guin%40gmail.com&company=Acme&country=NZ&submit=Subscribe"
+                .into(),
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        stored_attributes(&app).await,
+        serde_json::json!({ "company": "Acme", "country": "NZ" })
+    );
+}
+
+#[tokio::test]
+async fn json_subscriptions_store_custom_attributes() {
+    let app = spawn_app().await;
+    add_attributes(&app).await;
+
+    let response = app
+        .post_api_subscription(&serde_json::json!({
+            "name": "le guin",
+            "email": "ursula_le_guin@gmail.com",
+            "attributes": { "company": "Acme", "seats": 12 }
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+    assert_eq!(
+        stored_attributes(&app).await,
+        serde_json::json!({ "company": "Acme", "seats": 12 })
+    );
+}
+
+#[tokio::test]
+async fn newsletters_can_be_personalised_and_segmented_by_attribute() {
+    let app = spawn_app().await;
+    add_attributes(&app).await;
+    for (email, company, country) in [
+        ("nz_reader@example.com", "Acme", "NZ"),
+        ("uk_reader@example.com", "Initech", "UK"),
+    ] {
+        let body = serde_urlencoded::to_string([
+            ("name", "reader"),
+            ("email", email),
+            ("company", company),
+            ("country", country),
+        ])
+        .unwrap();
+        app.post_subscription(body)
+            .await
+            .error_for_status()
+            .unwrap();
+    }
+    sqlx::query!("UPDATE subscriptions SET status = 'confirmed'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    sqlx::query!("DELETE FROM email_outbox")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_newsletters(&serde_json::json!({
+            "title": "Newsletter title",
+            "html": "<p>Hello {{ subscriber.attributes.company }}</p>",
+            "text": "Hello {{ subscriber.attributes.company }}",
+            "idempotency_key": uuid::Uuid::new_v4().to_string(),
+            "segment_attribute": "country",
+            "segment_value": "NZ"
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
+
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(body["to"][0]["email"], "nz_reader@example.com");
+    assert_eq!(body["text"], "Hello Acme");
+}
@@ -0,0 +1,216 @@
+use reqwest::Url;
+use wiremock::{
+    Mock, ResponseTemplate,
+    matchers::{method, path},
+};
+
+use crate::helpers::{TestApp, assert_is_redirect_to, create_confirmed_subscriber, spawn_app};
+
+async fn subscriber_email(app: &TestApp) -> String {
+    sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .email
+}
+
+async fn request_data_link(app: &TestApp, email: &str, kind: &str) -> Url {
+    let _mock_guard = Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+
+    let response = app
+        .post_data_request(&serde_json::json!({ "email": email, "kind": kind }))
+        .await;
+    assert_is_redirect_to(&response, "/subscriptions/data-requests");
+    app.dispatch_all_pending_emails().await;
+
+    let requests = app.email_server.received_requests().await.unwrap();
+    app.get_confirmation_links(requests.last().unwrap()).html
+}
+
+#[tokio::test]
+async fn export_link_returns_everything_held_about_the_subscriber() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let email = subscriber_email(&app).await;
+
+    let link = request_data_link(&app, &email, "export").await;
+    let response = reqwest::get(link).await.unwrap();
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(
+        response.headers()["Content-Disposition"]
+            .to_str()
+            .unwrap()
+            .starts_with("attachment")
+    );
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["subscriber"]["email"], email);
+    assert_eq!(body["subscriber"]["status"], "confirmed");
+    for field in [
+        "signup_flag",
+        "pending_since",
+        "confirmation_reminder_sent_at",
+    ] {
+        assert!(body["subscriber"].get(field).is_some(), "missing {field}");
+    }
+    assert_eq!(body["subscription_tokens"].as_array().unwrap().len(), 1);
+    assert_eq!(body["data_requests"][0]["kind"], "export");
+}
+
+#[tokio::test]
+async fn data_requests_for_unknown_addresses_send_no_email() {
+    let app = spawn_app().await;
+
+    Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_data_request(&serde_json::json!({
+            "email": "nobody@example.com",
+            "kind": "export"
+        }))
+        .await;
+    app.dispatch_all_pending_emails().await;
+
+    assert_is_redirect_to(&response, "/subscriptions/data-requests");
+}
+
+#[tokio::test]
+async fn export_with_an_unknown_token_is_rejected_with_401() {
+    let app = spawn_app().await;
+
+    let response = reqwest::get(format!(
+        "{}/subscriptions/data/export?token={}",
+        app.address,
+        "a".repeat(25)
+    ))
+    .await
+    .unwrap();
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn an_export_token_cannot_be_used_to_erase() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let email = subscriber_email(&app).await;
+    let link = request_data_link(&app, &email, "export").await;
+    let token = link.query_pairs().find(|(k, _)| k == "token").unwrap().1;
+
+    let response = app
+        .api_client
+        .post(format!("{}/subscriptions/data/erase", app.address))
+        .form(&serde_json::json!({ "token": token }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn erase_link_asks_for_confirmation_before_erasing() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let email = subscriber_email(&app).await;
+
+    let link = request_data_link(&app, &email, "erase").await;
+    let response = reqwest::get(link).await.unwrap();
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.text().await.unwrap().contains("Erase my data"));
+    let n_subscribers = sqlx::query!(r#"SELECT count(*) AS "count!" FROM subscriptions"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_subscribers, 1);
+}
+
+#[tokio::test]
+async fn confirming_an_erasure_removes_the_subscriber_everywhere() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let email = subscriber_email(&app).await;
+    let link = request_data_link(&app, &email, "erase").await;
+    let token = link.query_pairs().find(|(k, _)| k == "token").unwrap().1;
+
+    let response = app
+        .api_client
+        .post(format!("{}/subscriptions/data/erase", app.address))
+        .form(&serde_json::json!({ "token": token }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 200);
+    let leftovers = sqlx::query!(
+        r#"
+        SELECT
+            (SELECT count(*) FROM subscriptions) AS "subscriptions!",
+            (SELECT count(*) FROM subscription_tokens) AS "subscription_tokens!",
+            (SELECT count(*) FROM data_request_tokens) AS "data_request_tokens!",
+            (SELECT count(*) FROM email_outbox) AS "email_outbox!",
+            (SELECT count(*) FROM data_erasure_log) AS "erasures!"
+        "#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(leftovers.subscriptions, 0);
+    assert_eq!(leftovers.subscription_tokens, 0);
+    assert_eq!(leftovers.data_request_tokens, 0);
+    assert_eq!(leftovers.email_outbox, 0);
+    assert_eq!(leftovers.erasures, 1);
+}
+
+#[tokio::test]
+async fn anonymous_users_cannot_erase_subscribers() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let email = subscriber_email(&app).await;
+
+    let response = app
+        .post_admin_erase_subscriber(&serde_json::json!({ "email": email }))
+        .await;
+
+    assert_is_redirect_to(&response, "/login");
+    let n_subscribers = sqlx::query!(r#"SELECT count(*) AS "count!" FROM subscriptions"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_subscribers, 1);
+}
+
+#[tokio::test]
+async fn admins_can_erase_a_subscriber() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let email = subscriber_email(&app).await;
+    app.login_test_user().await;
+
+    let response = app
+        .post_admin_erase_subscriber(&serde_json::json!({ "email": email }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+
+    let html = app.get_admin_dashboard_html().await;
+    assert!(html.contains(&format!("{email} has been erased.")));
+    let n_subscribers = sqlx::query!(r#"SELECT count(*) AS "count!" FROM subscriptions"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_subscribers, 0);
+}
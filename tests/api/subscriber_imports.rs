@@ -0,0 +1,386 @@
+use wiremock::{
+    Mock, ResponseTemplate,
+    matchers::{method, path},
+};
+
+use crate::helpers::{TestApp, assert_is_redirect_to, create_confirmed_subscriber, spawn_app};
+
+const IMPORTS_PATH: &str = "/admin/subscribers/import";
+
+async fn statuses(app: &TestApp) -> Vec<(String, String)> {
+    sqlx::query!("SELECT email, status FROM subscriptions ORDER BY email")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|r| (r.email, r.status))
+        .collect()
+}
+
+async fn import_page(app: &TestApp, response: &reqwest::Response) -> String {
+    let location = response
+        .headers()
+        .get("Location")
+        .unwrap()
+        .to_str()
+        .unwrap();
+    app.api_client
+        .get(format!("{}{}", &app.address, location))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn anonymous_users_cannot_import_subscribers() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_subscriber_import("email,name\nursula@example.com,Ursula\n", "pending", false)
+        .await;
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn importing_as_confirmed_requires_a_consent_attestation() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    let response = app
+        .post_subscriber_import(
+            "email,name\nursula@example.com,Ursula\n",
+            "confirmed",
+            false,
+        )
+        .await;
+    assert_is_redirect_to(&response, IMPORTS_PATH);
+    let html = app
+        .api_client
+        .get(format!("{}{}", &app.address, IMPORTS_PATH))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html.contains("requires attesting that they consented"));
+
+    app.process_subscriber_imports().await;
+    assert!(statuses(&app).await.is_empty());
+}
+
+#[tokio::test]
+async fn a_file_without_an_email_column_is_rejected() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    let response = app
+        .post_subscriber_import("name\nUrsula\n", "pending", false)
+        .await;
+
+    assert_is_redirect_to(&response, IMPORTS_PATH);
+}
+
+#[tokio::test]
+async fn confirmed_import_reports_invalid_rows_and_duplicates() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    create_confirmed_subscriber(&app).await;
+    let existing = statuses(&app).await.remove(0).0;
+    let csv = format!(
+        "email,name\n\
+         ursula@example.com,Ursula\n\
+         not-an-email,Broken\n\
+         URSULA@example.com,Ursula again\n\
+         {existing},Existing\n\
+         octavia@example.com,\n\
+         octavia@example.com,Octavia\n"
+    );
+
+    Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_subscriber_import(&csv, "confirmed", true).await;
+    assert_eq!(response.status().as_u16(), 303);
+    assert!(
+        import_page(&app, &response)
+            .await
+            .contains("0 of 6 rows processed")
+    );
+
+    app.process_subscriber_imports().await;
+    app.dispatch_all_pending_emails().await;
+
+    let html = import_page(&app, &response).await;
+    assert!(html.contains("Imported: 2"));
+    // The nameless row is imported under its local part, so the named one is a duplicate.
+    assert!(html.contains("Already subscribed: 3"));
+    assert!(html.contains("Invalid: 1"));
+    assert!(html.contains("not-an-email"));
+
+    let mut expected = vec![
+        (existing, "confirmed".to_string()),
+        ("octavia@example.com".into(), "confirmed".into()),
+        ("ursula@example.com".into(), "confirmed".into()),
+    ];
+    expected.sort();
+    assert_eq!(statuses(&app).await, expected);
+}
+
+#[tokio::test]
+async fn pending_import_sends_confirmation_emails() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_subscriber_import(
+        "name,email\nUrsula,ursula@example.com\nOctavia,octavia@example.com\n",
+        "pending",
+        false,
+    )
+    .await;
+    app.process_subscriber_imports().await;
+    app.dispatch_all_pending_emails().await;
+
+    let email_requests = app.email_server.received_requests().await.unwrap();
+    let confirmation_links = app.get_confirmation_links(&email_requests[0]);
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    let statuses: Vec<String> = statuses(&app).await.into_iter().map(|(_, s)| s).collect();
+    assert_eq!(statuses.iter().filter(|s| *s == "confirmed").count(), 1);
+    assert_eq!(
+        statuses
+            .iter()
+            .filter(|s| *s == "pending_confirmation")
+            .count(),
+        1
+    );
+}
+
+fn zip_archive(files: &[(&str, &str)]) -> Vec<u8> {
+    let mut archive = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    for (name, contents) in files {
+        archive
+            .start_file(*name, zip::write::SimpleFileOptions::default())
+            .unwrap();
+        std::io::Write::write_all(&mut archive, contents.as_bytes()).unwrap();
+    }
+    archive.finish().unwrap().into_inner()
+}
+
+#[tokio::test]
+async fn mailchimp_statuses_and_signup_dates_are_carried_over() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let header = "Email Address,First Name,Last Name,OPTIN_TIME,CONFIRM_TIME\n";
+    let data = zip_archive(&[
+        (
+            "subscribed_members_export_1.csv",
+            &format!("{header}ursula@example.com,Ursula,Le Guin,2020-01-02 03:04:05,\n"),
+        ),
+        (
+            "unsubscribed_members_export_1.csv",
+            &format!("{header}octavia@example.com,,,2019-05-06 07:08:09,\n"),
+        ),
+        (
+            "cleaned_members_export_1.csv",
+            &format!("{header}bounced@example.com,,,2018-01-01 00:00:00,\n"),
+        ),
+    ]);
+
+    Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_platform_import("mailchimp", "members_export.zip", &data, "pending", false)
+        .await;
+    assert_eq!(response.status().as_u16(), 303);
+    app.process_subscriber_imports().await;
+    app.dispatch_all_pending_emails().await;
+
+    let subscribers = sqlx::query!(
+        r#"
+        SELECT email, name, status, signup_source, subscribed_at::text AS "subscribed_at!"
+        FROM subscriptions
+        ORDER BY email
+        "#
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap();
+    let subscribers: Vec<_> = subscribers
+        .into_iter()
+        .map(|s| (s.email, s.name, s.status, s.signup_source, s.subscribed_at))
+        .collect();
+    let mailchimp = Some("mailchimp".to_string());
+    assert_eq!(
+        subscribers,
+        vec![
+            (
+                "bounced@example.com".into(),
+                "bounced".into(),
+                "bounced".into(),
+                mailchimp.clone(),
+                "2018-01-01 00:00:00+00".into()
+            ),
+            (
+                "octavia@example.com".into(),
+                "octavia".into(),
+                "unsubscribed".into(),
+                mailchimp.clone(),
+                "2019-05-06 07:08:09+00".into()
+            ),
+            // Subscribed members follow the import mode, so they confirm again.
+            (
+                "ursula@example.com".into(),
+                "Ursula Le Guin".into(),
+                "pending_confirmation".into(),
+                mailchimp,
+                "2020-01-02 03:04:05+00".into()
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn substack_posts_are_archived_without_being_delivered() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    create_confirmed_subscriber(&app).await;
+    let data = zip_archive(&[
+        (
+            "email_list.mypub.csv",
+            "email,active_subscription,email_disabled,created_at\n\
+             ursula@example.com,false,false,2021-03-04T05:06:07.000Z\n\
+             octavia@example.com,false,true,2021-03-04T05:06:07.000Z\n",
+        ),
+        (
+            "posts.csv",
+            "post_id,post_date,is_published,title\n\
+             1.hello,2021-04-01T10:00:00.000Z,true,Hello world\n\
+             2.draft,,false,Unfinished\n",
+        ),
+        ("posts/1.hello.html", "<p>Our <b>first</b> post</p>"),
+        ("posts/2.draft.html", "<p>Not yet</p>"),
+    ]);
+
+    Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_platform_import("substack", "export.zip", &data, "confirmed", true)
+        .await;
+    assert!(
+        import_page(&app, &response)
+            .await
+            .contains("1 past issues have been added to the archive")
+    );
+    app.process_subscriber_imports().await;
+    app.dispatch_all_pending_emails().await;
+
+    let issues = sqlx::query!(
+        "SELECT title, text_content, published_at, imported_from FROM newsletter_issues"
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].title, "Hello world");
+    assert!(issues[0].text_content.contains("first"));
+    assert_eq!(issues[0].published_at, "2021-04-01 10:00:00+00");
+    assert_eq!(issues[0].imported_from.as_deref(), Some("substack"));
+
+    let statuses = statuses(&app).await;
+    assert!(statuses.contains(&("ursula@example.com".into(), "confirmed".into())));
+    assert!(statuses.contains(&("octavia@example.com".into(), "unsubscribed".into())));
+
+    // People who had already left never confirmed with us.
+    let confirmed = sqlx::query_scalar!(
+        r#"
+        SELECT email FROM subscriptions
+        WHERE signup_source = 'substack' AND confirmed_at IS NOT NULL
+        "#
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(confirmed, vec!["ursula@example.com".to_string()]);
+}
+
+#[tokio::test]
+async fn importing_a_substack_export_twice_archives_its_posts_once() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let data = zip_archive(&[
+        (
+            "email_list.mypub.csv",
+            "email,active_subscription,email_disabled,created_at\n\
+             ursula@example.com,false,false,2021-03-04T05:06:07.000Z\n",
+        ),
+        (
+            "posts.csv",
+            "post_id,post_date,is_published,title\n\
+             1.hello,2021-04-01T10:00:00.000Z,true,Hello world\n",
+        ),
+        ("posts/1.hello.html", "<p>Our <b>first</b> post</p>"),
+    ]);
+
+    app.post_platform_import("substack", "export.zip", &data, "confirmed", true)
+        .await;
+    let response = app
+        .post_platform_import("substack", "export.zip", &data, "confirmed", true)
+        .await;
+
+    assert!(!import_page(&app, &response).await.contains("past issues"));
+    let issues = sqlx::query_scalar!(r#"SELECT count(*) AS "count!" FROM newsletter_issues"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(issues, 1);
+}
+
+#[tokio::test]
+async fn platform_imports_as_confirmed_require_a_consent_attestation() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    let response = app
+        .post_platform_import(
+            "mailchimp",
+            "subscribed_members_export_1.csv",
+            b"Email Address\nursula@example.com\n",
+            "confirmed",
+            false,
+        )
+        .await;
+
+    assert_is_redirect_to(&response, IMPORTS_PATH);
+    app.process_subscriber_imports().await;
+    assert!(statuses(&app).await.is_empty());
+}
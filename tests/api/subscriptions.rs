@@ -3,7 +3,7 @@ use wiremock::{
     matchers::{method, path},
 };
 
-use crate::helpers::spawn_app;
+use crate::helpers::{spawn_app, spawn_app_with};
 
 #[tokio::test]
 async fn subscribe_returns_200_for_valid_form_data() {
@@ -18,6 +18,7 @@ async fn subscribe_returns_200_for_valid_form_data() {
         .await;
 
     let response = app.post_subscription(body.into()).await;
+    app.dispatch_all_pending_emails().await;
 
     assert_eq!(200, response.status().as_u16());
 }
@@ -35,6 +36,7 @@ async fn subscribe_persists_the_new_subscriber() {
         .await;
 
     app.post_subscription(body.into()).await;
+    app.dispatch_all_pending_emails().await;
 
     let saved = sqlx::query!("SELECT name, email, status FROM subscriptions")
         .fetch_one(&app.db_pool)
@@ -87,6 +89,60 @@ async fn subscribe_returns_a_400_when_fields_are_present_but_empty() {
     }
 }
 
+#[tokio::test]
+async fn subscribe_rejects_disposable_email_domains_without_sending_email() {
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula%40mailinator.com";
+
+    Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_subscription(body.into()).await;
+    app.dispatch_all_pending_emails().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert!(response.text().await.unwrap().contains("disposable"));
+    let n_subscribers = sqlx::query!(r#"SELECT count(*) AS "count!" FROM subscriptions"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_subscribers, 0);
+}
+
+#[tokio::test]
+async fn role_addresses_are_accepted_and_flagged_for_admins() {
+    let app = spawn_app().await;
+    app.post_subscription("name=le%20guin&email=info%40example.com".into())
+        .await;
+    app.post_subscription("name=le%20guin&email=ursula%40example.com".into())
+        .await;
+
+    let flagged = sqlx::query!("SELECT email, signup_flag FROM subscriptions ORDER BY email")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(
+        flagged[0].signup_flag.as_deref(),
+        Some("info@example.com looks like a role address.")
+    );
+    assert_eq!(flagged[1].signup_flag, None);
+
+    app.login_test_user().await;
+    let page = app
+        .get_admin_subscribers("flagged=on")
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(page.contains("info@example.com"));
+    assert!(!page.contains("ursula@example.com"));
+}
+
 #[tokio::test]
 async fn subscribe_send_confirmation_email_for_valid_data() {
     let app = spawn_app().await;
@@ -101,6 +157,7 @@ async fn subscribe_send_confirmation_email_for_valid_data() {
         .await;
 
     app.post_subscription(body.into()).await;
+    app.dispatch_all_pending_emails().await;
 }
 
 #[tokio::test]
@@ -116,6 +173,7 @@ async fn subscribe_send_confirmation_email_with_link() {
         .await;
 
     app.post_subscription(body.into()).await;
+    app.dispatch_all_pending_emails().await;
 
     let received_request = &app.email_server.received_requests().await.unwrap()[0];
 
@@ -138,6 +196,36 @@ async fn send_two_confirmation_emails_if_user_subscribes_twice() {
 
     app.post_subscription(body.into()).await;
     app.post_subscription(body.into()).await;
+    app.dispatch_all_pending_emails().await;
+}
+
+#[tokio::test]
+async fn subscribing_with_differently_cased_email_does_not_create_a_second_subscriber() {
+    let app = spawn_app().await;
+
+    Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_subscription("name=le%20guin&email=Ursula_Le_Guin%40Gmail.com".into())
+        .await;
+    let response = app
+        .post_subscription("name=le%20guin&email=%20ursula_le_guin%40gmail.com".into())
+        .await;
+    app.dispatch_all_pending_emails().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let saved = sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_all(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscriptions");
+
+    assert_eq!(saved.len(), 1);
+    assert_eq!(saved[0].email, "Ursula_Le_Guin@gmail.com");
 }
 
 #[tokio::test]
@@ -153,6 +241,7 @@ async fn subscribe_returns_409_when_confirmed_user_tries_to_subscribe_again() {
         .await;
 
     app.post_subscription(body.into()).await;
+    app.dispatch_all_pending_emails().await;
     let email_request = &app.email_server.received_requests().await.unwrap()[0];
     let confirmation_links = app.get_confirmation_links(email_request);
 
@@ -167,6 +256,296 @@ async fn subscribe_returns_409_when_confirmed_user_tries_to_subscribe_again() {
     assert_eq!(resp.status().as_u16(), 409);
 }
 
+#[tokio::test]
+async fn resubscribing_after_unsubscribing_restarts_double_opt_in() {
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_subscription(body.into()).await;
+    app.dispatch_all_pending_emails().await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let first_links = app.get_confirmation_links(email_request);
+    reqwest::get(first_links.html.clone())
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    let mut unsubscribe_link = first_links.html.clone();
+    unsubscribe_link.set_path("/subscriptions/unsubscribe");
+    app.follow_unsubscribe_link(unsubscribe_link)
+        .await
+        .error_for_status()
+        .unwrap();
+
+    let resp = app.post_subscription(body.into()).await;
+    assert_eq!(resp.status().as_u16(), 200);
+    app.dispatch_all_pending_emails().await;
+
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription");
+    assert_eq!(saved.status, "pending_confirmation");
+
+    let email_request = &app.email_server.received_requests().await.unwrap()[1];
+    let second_links = app.get_confirmation_links(email_request);
+    assert_ne!(first_links.html, second_links.html);
+    reqwest::get(second_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription");
+    assert_eq!(saved.status, "confirmed");
+}
+
+#[tokio::test]
+async fn subscribe_succeeds_even_if_the_email_provider_is_down() {
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_subscription(body.into()).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.dispatch_all_pending_emails().await;
+
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription");
+    assert_eq!(saved.status, "pending_confirmation");
+
+    let outbox =
+        sqlx::query!("SELECT n_retries, execute_after > now() AS \"delayed!\" FROM email_outbox")
+            .fetch_one(&app.db_pool)
+            .await
+            .expect("Failed to fetch the queued email");
+    assert_eq!(outbox.n_retries, 1);
+    assert!(outbox.delayed);
+}
+
+#[tokio::test]
+async fn subscribe_does_not_contact_the_email_provider_inline() {
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_subscription(body.into()).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let queued = sqlx::query!("SELECT recipient FROM email_outbox")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch the queued email");
+    assert_eq!(queued.recipient, "ursula_le_guin@gmail.com");
+}
+
+#[tokio::test]
+async fn repeated_subscriptions_for_the_same_email_are_throttled() {
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    for _ in 0..3 {
+        let response = app.post_subscription(body.into()).await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    let response = app
+        .post_subscription("name=le%20guin&email=URSULA_LE_GUIN%40gmail.com".into())
+        .await;
+
+    assert_eq!(response.status().as_u16(), 429);
+    let retry_after: u64 = response.headers()["Retry-After"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(retry_after > 0);
+
+    let n_queued = sqlx::query!(r#"SELECT count(*) AS "count!" FROM email_outbox"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_queued, 3);
+}
+
+#[tokio::test]
+async fn too_many_subscriptions_from_the_same_ip_are_throttled() {
+    let app = spawn_app().await;
+
+    for i in 0..20 {
+        let body = format!("name=le%20guin&email=ursula_{i}%40gmail.com");
+        let response = app.post_subscription(body).await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    let response = app
+        .post_subscription("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+
+    assert_eq!(response.status().as_u16(), 429);
+    assert!(response.headers().contains_key("Retry-After"));
+}
+
+#[tokio::test]
+async fn forwarded_headers_from_untrusted_clients_do_not_escape_the_ip_limit() {
+    let app = spawn_app_with(|config| {
+        config.bot_protection.require_form_token = false;
+        config.rate_limits.subscribe_per_ip.max_requests = 1;
+    })
+    .await;
+
+    for (i, forwarded_for) in ["198.51.100.1", "198.51.100.2"].into_iter().enumerate() {
+        let response = app
+            .api_client
+            .post(format!("{}/subscriptions", app.address))
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header("X-Forwarded-For", forwarded_for)
+            .body(format!("name=le%20guin&email=ursula_{i}%40gmail.com"))
+            .send()
+            .await
+            .unwrap();
+        let expected = if i == 0 { 200 } else { 429 };
+        assert_eq!(response.status().as_u16(), expected);
+    }
+}
+
+#[tokio::test]
+async fn subscribe_rejects_a_filled_honeypot_without_sending_email() {
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com&website=https%3A%2F%2Fspam.example";
+
+    Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_subscription(body.into()).await;
+    app.dispatch_all_pending_emails().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    let n_subscribers = sqlx::query!(r#"SELECT count(*) AS "count!" FROM subscriptions"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_subscribers, 0);
+}
+
+#[tokio::test]
+async fn subscribe_rejects_a_submission_without_a_form_token() {
+    let app = spawn_app_with(|config| config.bot_protection.require_form_token = true).await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    let response = app.post_subscription(body.into()).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn subscribe_accepts_the_form_token_rendered_on_the_home_page() {
+    let app = spawn_app_with(|config| {
+        config.bot_protection.require_form_token = true;
+        config.bot_protection.min_submit_secs = 0;
+        config.bot_protection.proof_of_work_difficulty = 0;
+    })
+    .await;
+
+    let html = app.get_home_html().await;
+    let form_token = html
+        .split(r#"name="form_token" value=""#)
+        .nth(1)
+        .and_then(|rest| rest.split('"').next())
+        .expect("The home page should render a form token.");
+    let body = serde_urlencoded::to_string([
+        ("name", "le guin"),
+        ("email", "ursula_le_guin@gmail.com"),
+        ("website", ""),
+        ("form_token", form_token),
+    ])
+    .unwrap();
+
+    let response = app.post_subscription(body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn a_form_token_can_only_be_used_once() {
+    let app = spawn_app_with(|config| {
+        config.bot_protection.require_form_token = true;
+        config.bot_protection.min_submit_secs = 0;
+        config.bot_protection.proof_of_work_difficulty = 0;
+    })
+    .await;
+
+    let html = app.get_home_html().await;
+    let form_token = html
+        .split(r#"name="form_token" value=""#)
+        .nth(1)
+        .and_then(|rest| rest.split('"').next())
+        .expect("The home page should render a form token.");
+    for (email, expected_status) in [("ursula@gmail.com", 200), ("le_guin@gmail.com", 400)] {
+        let body = serde_urlencoded::to_string([
+            ("name", "le guin"),
+            ("email", email),
+            ("form_token", form_token),
+        ])
+        .unwrap();
+
+        let response = app.post_subscription(body).await;
+
+        assert_eq!(response.status().as_u16(), expected_status);
+    }
+    let n_subscribers = sqlx::query!(r#"SELECT count(*) AS "count!" FROM subscriptions"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_subscribers, 1);
+}
+
+#[tokio::test]
+async fn subscribe_rejects_a_tampered_form_token() {
+    let app = spawn_app_with(|config| {
+        config.bot_protection.require_form_token = true;
+        config.bot_protection.min_submit_secs = 0;
+    })
+    .await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com&form_token=0.challenge.deadbeef";
+
+    let response = app.post_subscription(body.into()).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
 #[tokio::test]
 async fn subscribe_fails_if_there_is_fatal_db_error() {
     let app = spawn_app().await;
@@ -3,7 +3,7 @@ use wiremock::{
     matchers::{method, path},
 };
 
-use crate::helpers::spawn_app;
+use crate::helpers::{assert_is_redirect_to, spawn_app, spawn_app_with};
 
 #[tokio::test]
 async fn confirmation_without_token_rejected_with_400() {
@@ -28,6 +28,7 @@ async fn link_returned_by_subscribe_returns_200_if_called() {
         .await;
 
     app.post_subscription(body.into()).await;
+    app.dispatch_all_pending_emails().await;
     let email_request = &app.email_server.received_requests().await.unwrap()[0];
 
     let confirmation_links = app.get_confirmation_links(email_request);
@@ -48,6 +49,7 @@ async fn click_on_confirmation_link_confirms_the_subscriber() {
         .await;
 
     app.post_subscription(body.into()).await;
+    app.dispatch_all_pending_emails().await;
     let email_request = &app.email_server.received_requests().await.unwrap()[0];
     let confirmation_links = app.get_confirmation_links(email_request);
 
@@ -79,6 +81,7 @@ async fn second_click_on_confirmation_link_changes_nothing() {
         .await;
 
     app.post_subscription(body.into()).await;
+    app.dispatch_all_pending_emails().await;
     let email_request = &app.email_server.received_requests().await.unwrap()[0];
     let confirmation_links = app.get_confirmation_links(email_request);
 
@@ -114,3 +117,113 @@ async fn second_click_on_confirmation_link_changes_nothing() {
         saved_after_first_click.status
     );
 }
+
+#[tokio::test]
+async fn confirmation_link_renders_a_confirmation_page() {
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    app.post_subscription(body.into()).await;
+    app.dispatch_all_pending_emails().await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_links = app.get_confirmation_links(email_request);
+
+    let resp = reqwest::get(confirmation_links.html.clone()).await.unwrap();
+    assert_eq!(resp.status().as_u16(), 200);
+    assert!(resp.text().await.unwrap().contains("You're subscribed!"));
+
+    let resp = reqwest::get(confirmation_links.html).await.unwrap();
+    assert_eq!(resp.status().as_u16(), 200);
+    assert!(
+        resp.text()
+            .await
+            .unwrap()
+            .contains("You're already subscribed")
+    );
+}
+
+#[tokio::test]
+async fn unknown_or_malformed_tokens_render_the_invalid_link_page() {
+    let app = spawn_app().await;
+
+    let resp = reqwest::get(format!(
+        "{}/subscriptions/confirm?subscription_token=aaaaaaaaaaaaaaaaaaaaaaaaa",
+        &app.address
+    ))
+    .await
+    .unwrap();
+    assert_eq!(resp.status().as_u16(), 401);
+    assert!(
+        resp.text()
+            .await
+            .unwrap()
+            .contains("This link is not valid")
+    );
+
+    let resp = reqwest::get(format!(
+        "{}/subscriptions/confirm?subscription_token=not-a-token",
+        &app.address
+    ))
+    .await
+    .unwrap();
+    assert_eq!(resp.status().as_u16(), 400);
+    assert!(
+        resp.text()
+            .await
+            .unwrap()
+            .contains("This link is not valid")
+    );
+}
+
+#[tokio::test]
+async fn confirmation_redirects_to_the_configured_landing_pages() {
+    let app = spawn_app_with(|config| {
+        config.bot_protection.require_form_token = false;
+        config.confirmation_pages.confirmed_url = Some("https://example.com/welcome".into());
+        config.confirmation_pages.invalid_token_url = Some("https://example.com/oops".into());
+    })
+    .await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    app.post_subscription(body.into()).await;
+    app.dispatch_all_pending_emails().await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_links = app.get_confirmation_links(email_request);
+
+    let resp = app
+        .api_client
+        .get(confirmation_links.html.clone())
+        .send()
+        .await
+        .unwrap();
+    assert_is_redirect_to(&resp, "https://example.com/welcome");
+
+    // No URL is configured for this outcome, so the built-in page is rendered.
+    let resp = app
+        .api_client
+        .get(confirmation_links.html)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status().as_u16(), 200);
+
+    let resp = app
+        .api_client
+        .get(format!("{}/subscriptions/confirm", &app.address))
+        .send()
+        .await
+        .unwrap();
+    assert_is_redirect_to(&resp, "https://example.com/oops");
+}
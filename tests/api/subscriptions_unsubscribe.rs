@@ -0,0 +1,127 @@
+use wiremock::{
+    Mock, ResponseTemplate,
+    matchers::{method, path},
+};
+
+use crate::helpers::{create_unconfirmed_subscriber, spawn_app};
+
+#[tokio::test]
+async fn unsubscribe_without_token_rejected_with_400() {
+    let app = spawn_app().await;
+
+    let resp = reqwest::get(&format!("{}/subscriptions/unsubscribe", &app.address))
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn unsubscribe_with_unknown_token_is_rejected_with_401() {
+    let app = spawn_app().await;
+
+    let resp = reqwest::get(&format!(
+        "{}/subscriptions/unsubscribe?subscription_token={}",
+        &app.address,
+        "a".repeat(25)
+    ))
+    .await
+    .unwrap();
+
+    assert_eq!(resp.status().as_u16(), 401);
+    assert!(
+        resp.text()
+            .await
+            .unwrap()
+            .contains("This link is not valid")
+    );
+}
+
+#[tokio::test]
+async fn posting_an_unknown_token_is_rejected_with_401() {
+    let app = spawn_app().await;
+
+    let resp = app.post_unsubscribe(&"a".repeat(25)).await;
+
+    assert_eq!(resp.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn opening_the_unsubscribe_link_does_not_unsubscribe() {
+    let app = spawn_app().await;
+    let confirmation_links = create_unconfirmed_subscriber(&app).await;
+    reqwest::get(confirmation_links.html.clone())
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    let mut unsubscribe_link = confirmation_links.html;
+    unsubscribe_link.set_path("/subscriptions/unsubscribe");
+    let resp = reqwest::get(unsubscribe_link).await.unwrap();
+
+    assert_eq!(resp.status().as_u16(), 200);
+    assert!(
+        resp.text()
+            .await
+            .unwrap()
+            .contains(r#"<form action="/subscriptions/unsubscribe" method="post">"#)
+    );
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved data from db.");
+    assert_eq!(saved.status, "confirmed");
+}
+
+#[tokio::test]
+async fn unsubscribe_link_marks_confirmed_subscriber_as_unsubscribed() {
+    let app = spawn_app().await;
+    let confirmation_links = create_unconfirmed_subscriber(&app).await;
+    reqwest::get(confirmation_links.html.clone())
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    let mut unsubscribe_link = confirmation_links.html;
+    unsubscribe_link.set_path("/subscriptions/unsubscribe");
+    let resp = app.follow_unsubscribe_link(unsubscribe_link).await;
+
+    assert_eq!(resp.status().as_u16(), 200);
+    assert!(resp.text().await.unwrap().contains("You're unsubscribed"));
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved data from db.");
+    assert_eq!(saved.status, "unsubscribed");
+}
+
+#[tokio::test]
+async fn old_confirmation_link_does_not_resubscribe_an_unsubscribed_subscriber() {
+    let app = spawn_app().await;
+    let confirmation_links = create_unconfirmed_subscriber(&app).await;
+
+    let mut unsubscribe_link = confirmation_links.html.clone();
+    unsubscribe_link.set_path("/subscriptions/unsubscribe");
+    app.follow_unsubscribe_link(unsubscribe_link)
+        .await
+        .error_for_status()
+        .unwrap();
+
+    Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let resp = reqwest::get(confirmation_links.html).await.unwrap();
+
+    assert_eq!(resp.status().as_u16(), 401);
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved data from db.");
+    assert_eq!(saved.status, "unsubscribed");
+}
@@ -0,0 +1,178 @@
+use wiremock::{
+    Mock, ResponseTemplate,
+    matchers::{method, path},
+};
+
+use crate::helpers::{TestApp, assert_is_redirect_to, spawn_app};
+
+async fn subscribe_and_get_email(app: &TestApp) -> serde_json::Value {
+    Mock::given(path("v1/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    app.post_subscription("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+    app.dispatch_all_pending_emails().await;
+
+    let requests = app.email_server.received_requests().await.unwrap();
+    serde_json::from_slice(&requests.last().unwrap().body).unwrap()
+}
+
+#[tokio::test]
+async fn anonymous_users_cannot_edit_emails() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_email_templates(
+            "/confirmation",
+            &serde_json::json!({
+                "subject": "Hi",
+                "html_template": "{{ link }}",
+                "text_template": "{{ link }}",
+            }),
+        )
+        .await;
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn confirmation_emails_use_the_bundled_templates_until_edited() {
+    let app = spawn_app().await;
+
+    let body = subscribe_and_get_email(&app).await;
+
+    assert_eq!(body["subject"], "Please confirm your subscription");
+    assert!(body["html"].as_str().unwrap().contains("Welcome, le guin!"));
+    assert!(body["text"].as_str().unwrap().contains("Welcome, le guin!"));
+}
+
+#[tokio::test]
+async fn edited_templates_are_used_for_confirmation_emails() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    let response = app
+        .post_email_templates(
+            "/confirmation",
+            &serde_json::json!({
+                "subject": "Confirm, {{ name }}",
+                "html_template": r#"<p>Hi {{ name }}</p><a href="{{ link | safe }}">Yes please</a>"#,
+                "text_template": "Hi {{ name }}, confirm at {{ link }}",
+            }),
+        )
+        .await;
+    assert_is_redirect_to(&response, "/admin/emails/confirmation");
+
+    let body = subscribe_and_get_email(&app).await;
+    assert_eq!(body["subject"], "Confirm, le guin");
+    assert!(
+        body["html"]
+            .as_str()
+            .unwrap()
+            .starts_with("<p>Hi le guin</p>")
+    );
+    assert!(
+        body["text"]
+            .as_str()
+            .unwrap()
+            .starts_with("Hi le guin, confirm at http")
+    );
+
+    // The edited link still confirms the subscription.
+    let requests = app.email_server.received_requests().await.unwrap();
+    let links = app.get_confirmation_links(requests.last().unwrap());
+    let response = reqwest::get(links.html).await.unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn templates_without_the_link_are_rejected() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    let response = app
+        .post_email_templates(
+            "/data_export",
+            &serde_json::json!({
+                "subject": "Your data",
+                "html_template": "<p>Your data is ready.</p>",
+                "text_template": "Your data is ready.",
+            }),
+        )
+        .await;
+    assert_is_redirect_to(&response, "/admin/emails/data_export");
+
+    let page = app
+        .get_email_templates("/data_export")
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(page.contains("Both versions of the email must include"));
+    let stored = sqlx::query!("SELECT kind FROM transactional_email_templates")
+        .fetch_optional(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(stored.is_none());
+}
+
+#[tokio::test]
+async fn edited_emails_can_be_previewed_and_reset() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    app.post_email_templates(
+        "/confirmation_reminder",
+        &serde_json::json!({
+            "subject": "Still there, {{ name }}?",
+            "html_template": r#"<a href="{{ link | safe }}">Confirm</a>"#,
+            "text_template": "{{ link }}",
+        }),
+    )
+    .await;
+
+    let page = app
+        .get_email_templates("/confirmation_reminder")
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(page.contains("Subject: Still there, Ada?"));
+    assert!(page.contains("The link that confirms the subscription."));
+    let preview = app
+        .get_email_templates("/confirmation_reminder/preview")
+        .await;
+    assert_eq!(
+        preview.headers()["content-security-policy"]
+            .to_str()
+            .unwrap(),
+        "sandbox"
+    );
+    assert!(
+        preview
+            .text()
+            .await
+            .unwrap()
+            .contains("/subscriptions/confirm?subscription_token=sample")
+    );
+
+    let response = app
+        .post_email_templates("/confirmation_reminder/reset", &serde_json::json!({}))
+        .await;
+    assert_is_redirect_to(&response, "/admin/emails/confirmation_reminder");
+    let page = app.get_email_templates("").await.text().await.unwrap();
+    assert!(page.contains("Reminder: please confirm your subscription"));
+    assert!(!page.contains("Still there"));
+}
+
+#[tokio::test]
+async fn unknown_emails_are_not_found() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    let response = app.get_email_templates("/welcome").await;
+
+    assert_eq!(response.status().as_u16(), 404);
+}